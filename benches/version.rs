@@ -0,0 +1,57 @@
+//! Benchmarks of versions with many authors.
+
+use chronofold::{AuthorIndex, Chronofold, LocalIndex, Op, Timestamp, Version};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+type CFold = Chronofold<usize, char>;
+
+/// Returns a chronofold with one element by each of `authors` authors.
+fn cfold_by(authors: usize) -> CFold {
+    let mut cfold = CFold::new(0);
+    let root = Timestamp::new(AuthorIndex(0), 0);
+    for author in 1..=authors {
+        let id = Timestamp::new(AuthorIndex(author), author);
+        cfold.apply(Op::insert(id, Some(root), 'x')).unwrap();
+    }
+    cfold
+}
+
+fn inc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Version::inc");
+    for authors in &[10usize, 100, 1000, 10000] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(authors),
+            authors,
+            |b, authors| {
+                b.iter(|| {
+                    let mut version = Version::new();
+                    for author in (0..*authors).rev() {
+                        version.inc(&Timestamp::new(AuthorIndex(1), author));
+                    }
+                    version
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn iter_newer_ops(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter_newer_ops");
+    for authors in &[10usize, 100, 1000, 10000] {
+        let cfold = cfold_by(*authors);
+        // A peer missing the ops of every other author.
+        let version: Version<usize> = cfold
+            .iter_ops::<&char>(LocalIndex(0)..)
+            .map(|op| op.id)
+            .filter(|id| id.author % 2 == 0)
+            .collect();
+        group.bench_with_input(BenchmarkId::from_parameter(authors), &cfold, |b, cfold| {
+            b.iter(|| cfold.iter_newer_ops::<&char>(&version).count())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, inc, iter_newer_ops);
+criterion_main!(benches);
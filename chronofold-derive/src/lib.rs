@@ -0,0 +1,129 @@
+//! Provides `#[derive(ChronoDoc)]`, re-exported by `chronofold` with the
+//! `derive` feature. See `chronofold::ChronoDoc` for details.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse_macro_input, Data, DataStruct, DeriveInput, Error, Fields, GenericArgument, Ident,
+    PathArguments, Type,
+};
+
+/// Implements `ChronoDoc` for a struct whose fields are all chronofolds of
+/// the same type.
+///
+/// This also generates an enum `<Struct>Field` with a variant per field.
+/// Attributes given as `#[chrono_doc(...)]` are put on that enum, e.g.
+/// `#[chrono_doc(derive(Serialize, Deserialize))]`.
+#[proc_macro_derive(ChronoDoc, attributes(chrono_doc))]
+pub fn derive_chrono_doc(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(Error::new_spanned(
+                name,
+                "ChronoDoc can only be derived for structs with named fields",
+            ))
+        }
+    };
+    let first = fields
+        .first()
+        .ok_or_else(|| Error::new_spanned(name, "ChronoDoc requires at least one field"))?;
+    let (author, value) = chronofold_args(&first.ty)?;
+    for field in fields {
+        if field.ty.to_token_stream().to_string() != first.ty.to_token_stream().to_string() {
+            return Err(Error::new_spanned(
+                &field.ty,
+                "all fields of a ChronoDoc must have the same type",
+            ));
+        }
+    }
+
+    let idents: Vec<&Ident> = fields
+        .iter()
+        .filter_map(|field| field.ident.as_ref())
+        .collect();
+    let variants: Vec<Ident> = idents
+        .iter()
+        .map(|ident| format_ident!("{}", camel_case(&ident.to_string())))
+        .collect();
+    let field_enum = format_ident!("{}Field", name);
+    let enum_attrs = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("chrono_doc"))
+        .map(|attr| attr.parse_args::<TokenStream2>())
+        .collect::<syn::Result<Vec<_>>>()?;
+    let doc = format!("Identifies a field of `{}`.", name);
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        #[doc = #doc]
+        #(#[#enum_attrs])*
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+        #vis enum #field_enum {
+            #(#variants,)*
+        }
+
+        impl #impl_generics ::chronofold::ChronoDoc<#author, #value> for #name #ty_generics #where_clause {
+            type Field = #field_enum;
+
+            fn fields(&self) -> ::std::vec::Vec<(Self::Field, &::chronofold::Chronofold<#author, #value>)> {
+                ::std::vec![#((#field_enum::#variants, &self.#idents),)*]
+            }
+
+            fn field_mut(&mut self, field: Self::Field) -> &mut ::chronofold::Chronofold<#author, #value> {
+                match field {
+                    #(#field_enum::#variants => &mut self.#idents,)*
+                }
+            }
+        }
+    })
+}
+
+/// Returns the author and value types of a `Chronofold<A, T>`.
+fn chronofold_args(ty: &Type) -> syn::Result<(&Type, &Type)> {
+    let error = || Error::new_spanned(ty, "expected a field of type `Chronofold<A, T>`");
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last().ok_or_else(error)?,
+        _ => return Err(error()),
+    };
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) if segment.ident == "Chronofold" => &args.args,
+        _ => return Err(error()),
+    };
+    let mut types = args.iter().filter_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    });
+    match (types.next(), types.next(), types.next()) {
+        (Some(author), Some(value), None) => Ok((author, value)),
+        _ => Err(error()),
+    }
+}
+
+fn camel_case(snake_case: &str) -> String {
+    snake_case
+        .split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .into_iter()
+                .flat_map(char::to_uppercase)
+                .chain(chars)
+        })
+        .collect()
+}
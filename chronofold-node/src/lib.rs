@@ -0,0 +1,68 @@
+//! Node.js bindings for chronofold.
+//!
+//! Exposes chronofolds of text as a native Node.js addon, e.g. for relays
+//! written in TypeScript. Authors are integers, and batches of ops as well as
+//! versions are passed around as CBOR-encoded buffers.
+//!
+//! Build the addon with `cargo build --release -p chronofold-node` and
+//! rename the library to `chronofold.node`.
+
+use napi::bindgen_prelude::Buffer;
+use napi::{Error, Result};
+use napi_derive::napi;
+
+use chronofold::{from_cbor_slice, to_cbor_bytes, Chronofold, FormatError, Op, Version};
+
+fn format_error(err: FormatError) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+/// A replicated text.
+#[napi(js_name = "Chronofold")]
+pub struct JsChronofold {
+    inner: Chronofold<usize, char>,
+}
+
+#[napi]
+impl JsChronofold {
+    #[napi(constructor)]
+    pub fn new(author: Option<u32>) -> Self {
+        Self {
+            inner: Chronofold::new(author.unwrap_or(0) as usize),
+        }
+    }
+
+    /// Returns the text.
+    #[napi]
+    pub fn text(&self) -> String {
+        self.inner.to_string()
+    }
+
+    /// Returns the version as a buffer.
+    #[napi]
+    pub fn version(&self) -> Result<Buffer> {
+        Ok(to_cbor_bytes(self.inner.version())
+            .map_err(format_error)?
+            .into())
+    }
+
+    /// Returns the ops newer than `version` (all ops if omitted) as a
+    /// buffer.
+    #[napi]
+    pub fn ops_since(&self, version: Option<Buffer>) -> Result<Buffer> {
+        let version: Version<usize> = match version {
+            Some(bytes) => from_cbor_slice(&bytes).map_err(format_error)?,
+            None => Version::new(),
+        };
+        let ops: Vec<Op<usize, &char>> = self.inner.iter_newer_ops(&version).collect();
+        Ok(to_cbor_bytes(&ops).map_err(format_error)?.into())
+    }
+
+    /// Applies a buffer of ops as returned by `opsSince`, and returns the
+    /// number of ops applied. See `apply_iter`.
+    #[napi]
+    pub fn apply_ops(&mut self, ops: Buffer) -> Result<u32> {
+        let ops: Vec<Op<usize, char>> = from_cbor_slice(&ops).map_err(format_error)?;
+        Ok(self.inner.apply_iter(ops).applied as u32)
+    }
+}
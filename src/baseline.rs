@@ -0,0 +1,83 @@
+//! Baselines for bootstrapping new replicas.
+
+use crate::{Author, Change, Chronofold, LocalIndex, Version};
+
+/// The content of a chronofold at a stable version.
+///
+/// New replicas start from a baseline instead of replaying all ops, and then
+/// apply the ops newer than `version`. Elements in the baseline keep their
+/// timestamps, so later ops can reference them. Deleted elements are left
+/// out, as far as the `RetentionPolicy` allows, like with `compact`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Chronofold<A, T>: serde::Serialize",
+        deserialize = "Chronofold<A, T>: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Baseline<A, T> {
+    chronofold: Chronofold<A, T>,
+}
+
+impl<A: Author, T> Baseline<A, T> {
+    /// Returns the version the baseline was taken at.
+    pub fn version(&self) -> &Version<A> {
+        self.chronofold.version()
+    }
+
+    /// Returns the chronofold of the baseline.
+    pub fn chronofold(&self) -> &Chronofold<A, T> {
+        &self.chronofold
+    }
+
+    /// Turns the baseline into a chronofold, to apply newer ops to.
+    pub fn into_chronofold(self) -> Chronofold<A, T> {
+        self.chronofold
+    }
+}
+
+impl<A: Author, T: Clone> Chronofold<A, T> {
+    /// Returns a baseline of this chronofold at `stable`.
+    ///
+    /// Ops not part of `stable` are left out. As for `compact`, every replica
+    /// must have seen `stable`, and all ops concurrent to it must have been
+    /// applied locally.
+    pub fn baseline(&self, stable: &Version<A>) -> Baseline<A, T> {
+        let mut chronofold = self.clone();
+        let mut removed = chronofold.find_removable(stable);
+        for (idx, removed) in removed.iter_mut().enumerate() {
+            let idx = LocalIndex(idx);
+            let is_stable = chronofold
+                .timestamp(idx)
+                .is_some_and(|timestamp| stable.contains(&timestamp));
+            if !is_stable && !matches!(chronofold.log[idx.0], Change::Root) {
+                *removed = true;
+            }
+        }
+        if removed.contains(&true) {
+            chronofold.remove_entries(&removed);
+        }
+        chronofold.version = stable.meet(&self.version);
+        // All ops of the version were applied, whether they're left out or
+        // not.
+        chronofold.compacted_version = chronofold.version.clone();
+
+        // Left out ops don't count as compacted, as new replicas never see
+        // them. Timestamps of remaining entries still have to be reachable
+        // from their log indices, and local ops have to be newer than the
+        // version.
+        let len = chronofold.log.len();
+        let shifts = (0..len).filter_map(|idx| {
+            let timestamp = chronofold.timestamp(LocalIndex(idx))?;
+            Some(timestamp.idx.0.saturating_sub(idx))
+        });
+        let newest = chronofold
+            .version
+            .iter()
+            .map(|timestamp| (timestamp.idx.0 + 1).saturating_sub(len));
+        chronofold.compacted = shifts.chain(newest).max().unwrap_or(0);
+        Baseline { chronofold }
+    }
+}
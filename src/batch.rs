@@ -0,0 +1,512 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::{
+    Author, BatchError, Chronofold, ChronofoldError, IntoLocalValue, LocalIndex, Op, Timestamp,
+};
+
+/// A summary of applying multiple ops at once.
+///
+/// This struct is created by the `apply_iter` and `apply_batch` methods on
+/// `Chronofold`. See their documentation for more.
+///
+/// Like `ChronofoldError`, this implements `Debug` for all types `T`.
+#[derive(PartialEq, Eq, Clone)]
+pub struct AppliedSummary<A, T> {
+    /// The number of ops that were applied.
+    pub applied: usize,
+    /// The ids of ops that were skipped, because they were applied before.
+    pub duplicates: Vec<Timestamp<A>>,
+    /// Ops that couldn't be applied due to missing causal history.
+    ///
+    /// These are either `ChronofoldError::UnknownReference`, where the op's
+    /// reference is the blocking one, or `ChronofoldError::FutureTimestamp`.
+    pub deferred: Vec<ChronofoldError<A, T>>,
+    /// Ops that were rejected by the chronofold's `Validator`, reused the
+    /// timestamp of a different op (`ChronofoldError::ExistingTimestamp`) or
+    /// failed strict mode (`ChronofoldError::ImplausibleReference`).
+    pub rejected: Vec<ChronofoldError<A, T>>,
+}
+
+impl<A, T> Default for AppliedSummary<A, T> {
+    fn default() -> Self {
+        Self {
+            applied: 0,
+            duplicates: Vec::new(),
+            deferred: Vec::new(),
+            rejected: Vec::new(),
+        }
+    }
+}
+
+impl<A, T> fmt::Debug for AppliedSummary<A, T>
+where
+    A: fmt::Debug + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AppliedSummary")
+            .field("applied", &self.applied)
+            .field("duplicates", &self.duplicates)
+            .field("deferred", &self.deferred)
+            .field("rejected", &self.rejected)
+            .finish()
+    }
+}
+
+impl<A, T> AppliedSummary<A, T> {
+    /// Returns `true` if all ops were either applied or skipped as duplicates.
+    pub fn is_complete(&self) -> bool {
+        self.deferred.is_empty() && self.rejected.is_empty()
+    }
+
+    /// Returns the missing ops the deferred ops wait for.
+    ///
+    /// Ops deferred with `ChronofoldError::FutureTimestamp` wait for unknown
+    /// ops of any author and aren't included.
+    pub fn waiting_for(&self) -> BTreeSet<&Timestamp<A>>
+    where
+        A: Ord,
+    {
+        self.deferred
+            .iter()
+            .filter_map(|err| match err {
+                ChronofoldError::UnknownReference(op, _) => op.payload.reference(),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// An op held by an `OpBuffer` until its causal history arrives.
+#[derive(PartialEq, Eq, Clone)]
+pub struct DeferredOp<A, T> {
+    pub op: Op<A, T>,
+    /// The missing op this op waits for, i.e. its reference.
+    ///
+    /// This is `None` if the reference is known, but the op's timestamp is
+    /// ahead of the chronofold's log (`ChronofoldError::FutureTimestamp`).
+    pub waiting_for: Option<Timestamp<A>>,
+    /// When the op was deferred.
+    pub deferred_at: Instant,
+}
+
+impl<A, T> DeferredOp<A, T> {
+    /// Returns how long the op has been waiting at `now`.
+    pub fn waited(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.deferred_at)
+    }
+}
+
+impl<A, T> fmt::Debug for DeferredOp<A, T>
+where
+    A: fmt::Debug + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeferredOp")
+            .field("op", &self.op.omit_value())
+            .field("waiting_for", &self.waiting_for)
+            .field("deferred_at", &self.deferred_at)
+            .finish()
+    }
+}
+
+/// Holds ops that arrived before their causal history, and applies them
+/// once it does.
+///
+/// Unlike `apply_iter`, the buffer keeps deferred ops across calls, so ops
+/// can be fed to it one by one as they arrive from the network.
+#[derive(PartialEq, Eq, Clone)]
+pub struct OpBuffer<A, T> {
+    pending: Vec<DeferredOp<A, T>>,
+    rejected: Vec<ChronofoldError<A, T>>,
+}
+
+impl<A, T> Default for OpBuffer<A, T> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            rejected: Vec::new(),
+        }
+    }
+}
+
+impl<A, T> fmt::Debug for OpBuffer<A, T>
+where
+    A: fmt::Debug + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OpBuffer")
+            .field("pending", &self.pending)
+            .field("rejected", &self.rejected)
+            .finish()
+    }
+}
+
+impl<A, T> OpBuffer<A, T> {
+    /// Creates an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the deferred ops, in the order they arrived.
+    pub fn pending(&self) -> &[DeferredOp<A, T>] {
+        &self.pending
+    }
+
+    /// Returns the missing ops the deferred ops wait for.
+    pub fn waiting_for(&self) -> BTreeSet<&Timestamp<A>>
+    where
+        A: Ord,
+    {
+        self.pending
+            .iter()
+            .filter_map(|deferred| deferred.waiting_for.as_ref())
+            .collect()
+    }
+
+    /// Returns the op that has been deferred the longest, if any.
+    pub fn oldest(&self) -> Option<&DeferredOp<A, T>> {
+        self.pending
+            .iter()
+            .min_by_key(|deferred| deferred.deferred_at)
+    }
+
+    /// Returns the number of deferred ops.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if no ops are deferred.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns the errors of deferred ops that failed for other reasons
+    /// once their causal history arrived, and clears them.
+    pub fn take_rejected(&mut self) -> Vec<ChronofoldError<A, T>> {
+        std::mem::take(&mut self.rejected)
+    }
+}
+
+impl<A: Author, T> OpBuffer<A, T> {
+    /// Applies `op` to `chronofold`, or defers it if its causal history is
+    /// missing.
+    ///
+    /// Returns the ids of previously deferred ops that were applied because
+    /// of this op, in the order they were applied. Deferred ops that were
+    /// applied in the meantime are dropped silently, other failures are
+    /// collected (see `take_rejected`).
+    ///
+    /// Errors other than missing causal history are returned for `op`
+    /// itself.
+    pub fn apply<U>(
+        &mut self,
+        chronofold: &mut Chronofold<A, U>,
+        op: Op<A, T>,
+    ) -> Result<Vec<Timestamp<A>>, ChronofoldError<A, T>>
+    where
+        T: IntoLocalValue<A, U>,
+    {
+        match chronofold.apply(op) {
+            Ok(()) => Ok(self.replay(chronofold)),
+            Err(
+                ChronofoldError::UnknownReference(op, _) | ChronofoldError::FutureTimestamp(op),
+            ) => {
+                self.pending.push(DeferredOp {
+                    waiting_for: missing_reference(chronofold, &op),
+                    op,
+                    deferred_at: Instant::now(),
+                });
+                Ok(Vec::new())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Retries deferred ops as long as some of them can be applied.
+    fn replay<U>(&mut self, chronofold: &mut Chronofold<A, U>) -> Vec<Timestamp<A>>
+    where
+        T: IntoLocalValue<A, U>,
+    {
+        let mut unblocked = Vec::new();
+        loop {
+            let applied_before = unblocked.len();
+            for deferred in std::mem::take(&mut self.pending) {
+                let id = deferred.op.id.clone();
+                match chronofold.apply(deferred.op) {
+                    Ok(()) => unblocked.push(id),
+                    Err(
+                        ChronofoldError::UnknownReference(op, _)
+                        | ChronofoldError::FutureTimestamp(op),
+                    ) => self.pending.push(DeferredOp {
+                        waiting_for: missing_reference(chronofold, &op),
+                        op,
+                        deferred_at: deferred.deferred_at,
+                    }),
+                    Err(ChronofoldError::AlreadyApplied(_)) => {}
+                    Err(err) => self.rejected.push(err),
+                }
+            }
+            if unblocked.len() == applied_before || self.pending.is_empty() {
+                break unblocked;
+            }
+        }
+    }
+}
+
+/// Returns the reference of `op`, if it's missing in `chronofold`.
+fn missing_reference<A: Author, T, U>(
+    chronofold: &Chronofold<A, T>,
+    op: &Op<A, U>,
+) -> Option<Timestamp<A>> {
+    op.payload
+        .reference()
+        .filter(|reference| chronofold.log_index(reference).is_none())
+        .cloned()
+}
+
+/// A batch of remote ops applied to a chronofold.
+///
+/// This struct is created by the `begin_remote_batch` method on
+/// `Chronofold`. See its documentation for more. The batch ends when it's
+/// dropped.
+#[derive(Debug)]
+pub struct RemoteBatch<'a, A, T> {
+    chronofold: &'a mut Chronofold<A, T>,
+}
+
+impl<A: Author, T> RemoteBatch<'_, A, T> {
+    /// Applies an op, see `Chronofold::apply`.
+    pub fn apply<V>(&mut self, op: Op<A, V>) -> Result<(), ChronofoldError<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        self.chronofold.apply(op)
+    }
+
+    /// Applies multiple ops, see `Chronofold::apply_iter`.
+    pub fn apply_iter<V>(&mut self, ops: impl IntoIterator<Item = Op<A, V>>) -> AppliedSummary<A, V>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        self.chronofold.apply_iter(ops)
+    }
+
+    /// Applies multiple ops in order, see `Chronofold::apply_all`.
+    pub fn apply_all<V>(
+        &mut self,
+        ops: impl IntoIterator<Item = Op<A, V>>,
+    ) -> Result<(), BatchError<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        self.chronofold.apply_all(ops)
+    }
+
+    /// Ends the batch, like dropping it.
+    pub fn end(self) {}
+}
+
+impl<A, T> AsRef<Chronofold<A, T>> for RemoteBatch<'_, A, T> {
+    fn as_ref(&self) -> &Chronofold<A, T> {
+        self.chronofold
+    }
+}
+
+impl<A, T> Drop for RemoteBatch<'_, A, T> {
+    fn drop(&mut self) {
+        if let Some(end) = self.chronofold.remote_batch.take() {
+            end(self.chronofold);
+        }
+    }
+}
+
+/// Ends a `RemoteBatch`, see `Chronofold::begin_remote_batch`.
+pub(crate) type RemoteBatchEnd<A, T> = fn(&mut Chronofold<A, T>);
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Begins a batch of remote ops, e.g. when loading a long history.
+    ///
+    /// While the batch is in progress, neither the version nor the
+    /// structures used to count and position visible elements are updated
+    /// op by op. They are brought up to date once the batch ends, which is a
+    /// lot faster for many ops. Observers are notified then as well.
+    pub fn begin_remote_batch(&mut self) -> RemoteBatch<'_, A, T> {
+        self.visibility = None;
+        self.remote_batch = Some(Self::end_remote_batch);
+        self.mark_batch_start();
+        RemoteBatch { chronofold: self }
+    }
+
+    /// Catches up on the work deferred during a `RemoteBatch`.
+    fn end_remote_batch(&mut self) {
+        for idx in self.batch_start()..self.log.len() {
+            let timestamp = self
+                .timestamp(LocalIndex(idx))
+                .expect("log entries have timestamps");
+            self.version.inc(&timestamp);
+        }
+        self.prepare_visibility();
+        self.report_batch();
+        self.flush_version_observer();
+    }
+
+    /// Applies multiple ops and returns a summary of the outcome.
+    ///
+    /// Unlike applying ops one by one, this does not stop at the first error:
+    /// Duplicates are skipped, and ops missing their causal history are
+    /// deferred and retried as long as other ops make progress. This means
+    /// the ops don't need to be in causal order.
+    pub fn apply_iter<V>(&mut self, ops: impl IntoIterator<Item = Op<A, V>>) -> AppliedSummary<A, V>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        let mut summary = AppliedSummary::default();
+        let mut pending: Vec<Op<A, V>> = ops.into_iter().collect();
+        loop {
+            let applied_before = summary.applied;
+            for op in pending.drain(..) {
+                match self.apply(op) {
+                    Ok(()) => summary.applied += 1,
+                    Err(ChronofoldError::AlreadyApplied(op)) => summary.duplicates.push(op.id),
+                    Err(
+                        err @ (ChronofoldError::Rejected(..)
+                        | ChronofoldError::ExistingTimestamp(_)
+                        | ChronofoldError::ImplausibleReference(_)),
+                    ) => summary.rejected.push(err),
+                    Err(err) => summary.deferred.push(err),
+                }
+            }
+            if summary.applied == applied_before || summary.deferred.is_empty() {
+                break summary;
+            }
+            pending.extend(
+                summary
+                    .deferred
+                    .drain(..)
+                    .filter_map(ChronofoldError::into_op),
+            );
+        }
+    }
+
+    /// Applies many remote ops at once and returns a summary of the
+    /// outcome.
+    ///
+    /// The ops are sorted by timestamp first, which is a causal order unless
+    /// references are implausible (see `set_strict`), and applied in a
+    /// `RemoteBatch`, so the version observer is notified once.
+    /// This is a lot faster than `apply_iter` for large batches, e.g. when
+    /// catching up with a peer.
+    ///
+    /// Like with `apply_iter`, duplicates are skipped and rejected ops are
+    /// collected, without stopping the batch. Ops missing their causal
+    /// history are retried until no more of them can be applied; the rest
+    /// are returned as deferred.
+    pub fn apply_batch<V>(
+        &mut self,
+        ops: impl IntoIterator<Item = Op<A, V>>,
+    ) -> AppliedSummary<A, V>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        let mut ops: Vec<Op<A, V>> = ops.into_iter().collect();
+        ops.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut summary = AppliedSummary::default();
+        let mut batch = self.begin_remote_batch();
+        loop {
+            let applied_before = summary.applied;
+            for op in ops.drain(..) {
+                match batch.apply(op) {
+                    Ok(()) => summary.applied += 1,
+                    Err(ChronofoldError::AlreadyApplied(op)) => summary.duplicates.push(op.id),
+                    Err(
+                        err @ (ChronofoldError::UnknownReference(..)
+                        | ChronofoldError::FutureTimestamp(_)),
+                    ) => summary.deferred.push(err),
+                    Err(err) => summary.rejected.push(err),
+                }
+            }
+            if summary.applied == applied_before || summary.deferred.is_empty() {
+                break summary;
+            }
+            ops.extend(
+                summary
+                    .deferred
+                    .drain(..)
+                    .filter_map(ChronofoldError::into_op),
+            );
+        }
+    }
+
+    /// Applies multiple ops in order, stopping at the first error.
+    ///
+    /// On failure, the returned `BatchError` contains the failing op as well
+    /// as all ops that were not applied yet.
+    pub fn apply_all<V>(
+        &mut self,
+        ops: impl IntoIterator<Item = Op<A, V>>,
+    ) -> Result<(), BatchError<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        let mut ops = ops.into_iter();
+        for (applied, op) in (&mut ops).enumerate() {
+            if let Err(error) = self.apply(op) {
+                return Err(BatchError {
+                    applied,
+                    error,
+                    remaining: ops.collect(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies an op, fetching missing causal history on demand.
+    ///
+    /// Whenever an op's reference is unknown, `fetch` is called with the
+    /// missing timestamp and should return ops including it, in causal order.
+    /// Fetched ops that were applied before are skipped. If `fetch` returns
+    /// `None` or doesn't provide the missing op, the error of the blocked op
+    /// is returned.
+    pub fn apply_with_resolver<V, F>(
+        &mut self,
+        op: Op<A, V>,
+        mut fetch: F,
+    ) -> Result<(), ChronofoldError<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+        F: FnMut(&Timestamp<A>) -> Option<Vec<Op<A, V>>>,
+    {
+        let id = op.id.clone();
+        let mut requested = BTreeSet::new();
+        let mut pending = vec![op];
+        while let Some(op) = pending.pop() {
+            let err = match self.apply(op) {
+                Ok(()) => continue,
+                Err(ChronofoldError::AlreadyApplied(op)) if op.id != id => continue,
+                Err(err) => err,
+            };
+            // Ops missing their history may fail the timestamp check before
+            // their reference is looked up.
+            let missing = match &err {
+                ChronofoldError::UnknownReference(op, _) | ChronofoldError::FutureTimestamp(op) => {
+                    missing_reference(self, op)
+                }
+                _ => None,
+            };
+            match missing
+                .filter(|missing| requested.insert(missing.clone()))
+                .and_then(|missing| fetch(&missing))
+            {
+                Some(fetched) => {
+                    pending.push(err.into_op().expect("blocked ops are kept"));
+                    pending.extend(fetched.into_iter().rev());
+                }
+                None => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+//! Deterministic exports of a chronofold's history.
+
+use crate::{Author, Chronofold, OpPayload, Timestamp};
+
+/// An author that can be encoded in canonical exports.
+///
+/// The encoding must be injective and self-delimiting: different authors
+/// are encoded differently, and no encoding is a prefix of another one.
+/// Fixed-size encodings are, variable-size ones should be prefixed with
+/// their length.
+pub trait CanonicalAuthor {
+    /// Appends the encoded author to `bytes`.
+    fn encode_canonical(&self, bytes: &mut Vec<u8>);
+}
+
+macro_rules! impl_canonical_author_for_uint {
+    ($($type:ty),*) => {
+        $(
+            impl CanonicalAuthor for $type {
+                fn encode_canonical(&self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&(*self as u64).to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_canonical_author_for_uint!(u8, u16, u32, u64, usize);
+
+impl CanonicalAuthor for u128 {
+    fn encode_canonical(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl<const N: usize> CanonicalAuthor for [u8; N] {
+    fn encode_canonical(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(self);
+    }
+}
+
+impl CanonicalAuthor for Vec<u8> {
+    fn encode_canonical(&self, bytes: &mut Vec<u8>) {
+        push_u64(bytes, self.len());
+        bytes.extend_from_slice(self);
+    }
+}
+
+impl CanonicalAuthor for String {
+    fn encode_canonical(&self, bytes: &mut Vec<u8>) {
+        push_u64(bytes, self.len());
+        bytes.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl<A: Author + CanonicalAuthor, T> Chronofold<A, T> {
+    /// Encodes `canonical_ops` in a fixed binary format.
+    ///
+    /// Converged replicas produce byte-identical exports, as long as they
+    /// compacted the same ops (see `compact`) and `encode` is
+    /// deterministic. This makes exports usable as keys for
+    /// content-addressed storage.
+    ///
+    /// Each op is encoded as a tag (`0` root, `1` insert, `2` delete), its
+    /// timestamp, and for inserts and deletes the reference. Inserts are
+    /// followed by the length of the encoded value and the value. Timestamps
+    /// are an author index and the author, see `CanonicalAuthor`. Integer
+    /// authors are encoded as numbers. References are prefixed with `0`
+    /// (none) or `1`. All numbers are little-endian `u64`s.
+    pub fn export_canonical<E>(
+        &self,
+        mut encode: impl FnMut(&T) -> Result<Vec<u8>, E>,
+    ) -> Result<Vec<u8>, E> {
+        let mut bytes = Vec::new();
+        for op in self.canonical_ops() {
+            match op.payload {
+                OpPayload::Root => {
+                    bytes.push(0);
+                    push_timestamp(&mut bytes, &op.id);
+                }
+                OpPayload::Insert(reference, value) => {
+                    bytes.push(1);
+                    push_timestamp(&mut bytes, &op.id);
+                    push_reference(&mut bytes, reference.as_ref());
+                    let value = encode(value)?;
+                    push_u64(&mut bytes, value.len());
+                    bytes.extend_from_slice(&value);
+                }
+                OpPayload::Delete(reference) => {
+                    bytes.push(2);
+                    push_timestamp(&mut bytes, &op.id);
+                    push_reference(&mut bytes, Some(&reference));
+                }
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+fn push_u64(bytes: &mut Vec<u8>, n: usize) {
+    bytes.extend_from_slice(&(n as u64).to_le_bytes());
+}
+
+fn push_timestamp<A: CanonicalAuthor>(bytes: &mut Vec<u8>, timestamp: &Timestamp<A>) {
+    push_u64(bytes, timestamp.idx.0);
+    timestamp.author.encode_canonical(bytes);
+}
+
+fn push_reference<A: CanonicalAuthor>(bytes: &mut Vec<u8>, reference: Option<&Timestamp<A>>) {
+    match reference {
+        Some(reference) => {
+            bytes.push(1);
+            push_timestamp(bytes, reference);
+        }
+        None => bytes.push(0),
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{AuthorIndex, Op, OpPayload, Timestamp};
+
+/// A run of elements inserted by one author, each directly after the
+/// previous one.
+///
+/// The first element has the timestamp `id` and is inserted after
+/// `reference`; the following elements' timestamps increment `id.idx`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InsertRun<A, T> {
+    pub id: Timestamp<A>,
+    pub reference: Option<Timestamp<A>>,
+    pub values: Vec<T>,
+}
+
+/// Either a single op or a run of inserts.
+///
+/// This is created by `coalesce_ops`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CoalescedOp<A, T> {
+    Op(Op<A, T>),
+    Run(InsertRun<A, T>),
+}
+
+impl<A: Clone, T> CoalescedOp<A, T> {
+    /// Returns the number of ops this stands for.
+    pub fn len(&self) -> usize {
+        match self {
+            CoalescedOp::Op(_) => 1,
+            CoalescedOp::Run(run) => run.values.len(),
+        }
+    }
+
+    /// Returns `true` if this is an empty run.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Expands this into the original ops.
+    pub fn into_ops(self) -> impl Iterator<Item = Op<A, T>> {
+        let (single, run) = match self {
+            CoalescedOp::Op(op) => (Some(op), None),
+            CoalescedOp::Run(run) => (None, Some(run)),
+        };
+        let run = run.into_iter().flat_map(|run| {
+            let InsertRun {
+                id,
+                mut reference,
+                values,
+            } = run;
+            values.into_iter().enumerate().map(move |(i, value)| {
+                let mut op_id = id.clone();
+                op_id.idx.0 += i;
+                let op = Op::insert(op_id.clone(), reference.take(), value);
+                reference = Some(op_id);
+                op
+            })
+        });
+        single.into_iter().chain(run)
+    }
+}
+
+/// Merges consecutive inserts into `InsertRun`s.
+///
+/// An insert joins the previous one's run if it has the same author, the
+/// next author index and references the previous insert. This is what typing
+/// produces, so it usually shrinks the number of ops considerably.
+pub fn coalesce_ops<A, T>(ops: impl IntoIterator<Item = Op<A, T>>) -> Vec<CoalescedOp<A, T>>
+where
+    A: PartialEq + Clone,
+{
+    let mut result: Vec<CoalescedOp<A, T>> = Vec::new();
+    for op in ops {
+        let op = match (result.last_mut(), op.payload) {
+            (Some(CoalescedOp::Run(run)), OpPayload::Insert(Some(reference), value))
+                if continues(&run.id, run.values.len(), &reference, &op.id) =>
+            {
+                run.values.push(value);
+                continue;
+            }
+            (_, OpPayload::Insert(reference, value)) => CoalescedOp::Run(InsertRun {
+                id: op.id,
+                reference,
+                values: vec![value],
+            }),
+            (_, payload) => CoalescedOp::Op(Op::new(op.id, payload)),
+        };
+        result.push(op);
+    }
+    result
+        .into_iter()
+        .map(|op| match op {
+            // Don't wrap single inserts.
+            CoalescedOp::Run(mut run) if run.values.len() == 1 => {
+                CoalescedOp::Op(Op::insert(run.id, run.reference, run.values.pop().unwrap()))
+            }
+            op => op,
+        })
+        .collect()
+}
+
+/// Drops elements that were inserted and deleted within `ops`, which must be
+/// the ops of one session.
+///
+/// Ops inserted after a dropped element or its deletion are attached to the
+/// dropped element's reference instead, as long as this doesn't change the
+/// order of elements. The remaining ops' author indices are shifted to close
+/// the gaps, so runs that were interrupted by a dropped element continue
+/// each other and `coalesce_ops` merges them.
+///
+/// As ops get new timestamps, `ops` must be the newest ops of their author
+/// and must not have been applied anywhere. Make the edits on a clone of the
+/// chronofold, compress its new ops and apply them to the original before
+/// sending them:
+///
+/// ```
+/// # use chronofold::{compress_ops, Chronofold, LocalIndex, Op};
+/// let mut cfold = Chronofold::<u8, char>::default();
+/// let mut draft = cfold.clone();
+/// draft.session(1).extend("helo".chars());
+/// draft.session(1).remove(LocalIndex(4));
+/// draft.session(1).extend("lo".chars());
+///
+/// let ops: Vec<Op<u8, char>> = draft.iter_newer_ops(cfold.version()).map(Op::cloned).collect();
+/// let ops = compress_ops(ops);
+/// assert_eq!(5, ops.len());
+/// cfold.apply_all(ops).unwrap();
+/// assert_eq!("hello", format!("{}", cfold));
+/// ```
+pub fn compress_ops<A, T>(ops: impl IntoIterator<Item = Op<A, T>>) -> Vec<Op<A, T>>
+where
+    A: Ord + Clone,
+{
+    let ops: Vec<Op<A, T>> = ops.into_iter().collect();
+    let references: BTreeMap<&Timestamp<A>, &Timestamp<A>> = ops
+        .iter()
+        .filter_map(|op| Some((&op.id, op.payload.reference()?)))
+        .collect();
+    let mut children: BTreeMap<&Timestamp<A>, Vec<&Timestamp<A>>> = BTreeMap::new();
+    for (id, reference) in &references {
+        children.entry(reference).or_default().push(id);
+    }
+    let inserted: BTreeSet<&Timestamp<A>> = ops
+        .iter()
+        .filter(|op| matches!(op.payload, OpPayload::Insert(..)))
+        .map(|op| &op.id)
+        .collect();
+    let mut cancelled: BTreeSet<&Timestamp<A>> = ops
+        .iter()
+        .filter_map(|op| match &op.payload {
+            OpPayload::Delete(reference) => inserted.get(reference).copied(),
+            _ => None,
+        })
+        .collect();
+
+    let dropped_ops = |cancelled: &BTreeSet<&Timestamp<A>>| -> BTreeSet<&Timestamp<A>> {
+        ops.iter()
+            .filter(|op| element(op).is_some_and(|id| cancelled.contains(id)))
+            .map(|op| &op.id)
+            .collect()
+    };
+
+    // Keep elements whose dropping would move remaining ops, until nothing
+    // changes. Remaining ops are moved to the front of their new
+    // reference's children, which is only right if the dropped op was the
+    // newest child with remaining ops in its subtree.
+    let dropped = loop {
+        let dropped = dropped_ops(&cancelled);
+        let mut active: BTreeSet<&Timestamp<A>> = BTreeSet::new();
+        for op in ops.iter().filter(|op| !dropped.contains(&op.id)) {
+            let mut id = &op.id;
+            while let Some(reference) = references.get(id).filter(|r| dropped.contains(*r)) {
+                if !active.insert(*reference) {
+                    break;
+                }
+                id = reference;
+            }
+        }
+        let moved: Vec<&Timestamp<A>> = ops
+            .iter()
+            .filter(|op| active.contains(&op.id))
+            .filter(|op| {
+                let siblings = op
+                    .payload
+                    .reference()
+                    .and_then(|reference| children.get(reference));
+                siblings.into_iter().flatten().any(|sibling| {
+                    *sibling > &op.id && (!dropped.contains(sibling) || active.contains(sibling))
+                })
+            })
+            .filter_map(element)
+            .collect();
+        if moved.is_empty() {
+            break dropped;
+        }
+        for id in moved {
+            cancelled.remove(id);
+        }
+    };
+
+    let mut gaps: BTreeMap<&A, Vec<usize>> = BTreeMap::new();
+    for id in &dropped {
+        gaps.entry(&id.author).or_default().push(id.idx.0);
+    }
+    let shift = |t: &Timestamp<A>| {
+        let gap = gaps
+            .get(&t.author)
+            .map_or(0, |indices| indices.partition_point(|idx| *idx < t.idx.0));
+        Timestamp::new(AuthorIndex(t.idx.0 - gap), t.author.clone())
+    };
+    let attach = |reference: &Timestamp<A>| {
+        let mut reference = Some(reference);
+        while let Some(dropped_reference) = reference.filter(|r| dropped.contains(r)) {
+            reference = references.get(dropped_reference).copied();
+        }
+        reference.map(shift)
+    };
+
+    let rewritten: Vec<_> = ops
+        .iter()
+        .map(|op| {
+            let reference = op.payload.reference().and_then(attach);
+            Some((shift(&op.id), reference)).filter(|_| !dropped.contains(&op.id))
+        })
+        .collect();
+    ops.into_iter()
+        .zip(rewritten)
+        .filter_map(|(op, rewritten)| {
+            let (id, reference) = rewritten?;
+            let payload = match op.payload {
+                OpPayload::Root => OpPayload::Root,
+                OpPayload::Insert(_, value) => OpPayload::Insert(reference, value),
+                OpPayload::Delete(_) => {
+                    OpPayload::Delete(reference.expect("deletions of dropped elements are dropped"))
+                }
+            };
+            Some(Op::new(id, payload))
+        })
+        .collect()
+}
+
+/// Returns the element an insert or deletion is about.
+fn element<A, T>(op: &Op<A, T>) -> Option<&Timestamp<A>> {
+    match &op.payload {
+        OpPayload::Delete(reference) => Some(reference),
+        OpPayload::Insert(..) => Some(&op.id),
+        OpPayload::Root => None,
+    }
+}
+
+fn continues<A: PartialEq>(
+    start: &Timestamp<A>,
+    len: usize,
+    reference: &Timestamp<A>,
+    id: &Timestamp<A>,
+) -> bool {
+    let last = start.idx.0 + len - 1;
+    id.author == start.author
+        && reference.author == start.author
+        && reference.idx.0 == last
+        && id.idx.0 == last + 1
+}
+
+/// A batch of char ops, storing all inserted chars as one string.
+///
+/// This is much smaller than the ops themselves, both in memory and
+/// serialized, since typing produces long runs of single-char inserts.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CharBatch<A> {
+    /// The inserted chars of all runs, in order.
+    pub text: String,
+    pub entries: Vec<CharBatchEntry<A>>,
+}
+
+/// The structure of a `CharBatch`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CharBatchEntry<A> {
+    /// A run of `len` chars from the batch's text, see `InsertRun`.
+    Run {
+        id: Timestamp<A>,
+        reference: Option<Timestamp<A>>,
+        len: usize,
+    },
+    /// Any other op.
+    Op(Op<A, ()>),
+}
+
+impl<A: PartialEq + Clone> CharBatch<A> {
+    /// Encodes ops, coalescing inserts into runs.
+    pub fn from_ops(ops: impl IntoIterator<Item = Op<A, char>>) -> Self {
+        let mut text = String::new();
+        let mut entries = Vec::new();
+        for op in coalesce_ops(ops) {
+            let (id, reference, values) = match op {
+                CoalescedOp::Run(run) => (run.id, run.reference, run.values),
+                CoalescedOp::Op(Op {
+                    id,
+                    payload: OpPayload::Insert(reference, value),
+                }) => (id, reference, vec![value]),
+                CoalescedOp::Op(Op { id, payload }) => {
+                    let payload = match payload {
+                        OpPayload::Root => OpPayload::Root,
+                        OpPayload::Delete(reference) => OpPayload::Delete(reference),
+                        OpPayload::Insert(..) => unreachable!(),
+                    };
+                    entries.push(CharBatchEntry::Op(Op::new(id, payload)));
+                    continue;
+                }
+            };
+            text.extend(&values);
+            entries.push(CharBatchEntry::Run {
+                id,
+                reference,
+                len: values.len(),
+            });
+        }
+        Self { text, entries }
+    }
+
+    /// Decodes the ops.
+    ///
+    /// Returns `None` if the runs' lengths don't add up to the text or an
+    /// entry is an insert without value.
+    pub fn into_ops(self) -> Option<Vec<Op<A, char>>> {
+        let mut chars = self.text.chars();
+        let mut ops = Vec::new();
+        for entry in self.entries {
+            match entry {
+                CharBatchEntry::Run { id, reference, len } => {
+                    let values: Vec<char> = chars.by_ref().take(len).collect();
+                    if values.len() < len {
+                        return None;
+                    }
+                    let run = InsertRun {
+                        id,
+                        reference,
+                        values,
+                    };
+                    ops.extend(CoalescedOp::Run(run).into_ops());
+                }
+                CharBatchEntry::Op(Op { id, payload }) => ops.push(Op::new(
+                    id,
+                    match payload {
+                        OpPayload::Root => OpPayload::Root,
+                        OpPayload::Delete(reference) => OpPayload::Delete(reference),
+                        OpPayload::Insert(..) => return None,
+                    },
+                )),
+            }
+        }
+        Some(ops).filter(|_| chars.next().is_none())
+    }
+}
@@ -0,0 +1,361 @@
+use std::cmp::Ordering;
+use std::mem;
+
+use crate::costructures::Costructures;
+use crate::index::IndexShift;
+use crate::{Author, Change, Chronofold, LocalIndex, Timestamp, Tombstone, Version};
+
+/// Maps log indices from before a compaction to log indices after it.
+///
+/// This is returned by `compact`, so log indices held by an application
+/// survive compaction.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct IndexRemap {
+    /// For each old log index the new log index, and whether the entry was
+    /// kept. Removed entries map to the nearest kept element (or root)
+    /// before them in causal order.
+    entries: Vec<(LocalIndex, bool)>,
+}
+
+impl IndexRemap {
+    fn identity(len: usize) -> Self {
+        Self {
+            entries: (0..len).map(|idx| (LocalIndex(idx), true)).collect(),
+        }
+    }
+
+    /// Returns the number of removed log entries.
+    pub fn removed(&self) -> usize {
+        self.entries.iter().filter(|(_, kept)| !kept).count()
+    }
+
+    /// Returns the new log index of the entry at `old`, or `None` if it was
+    /// removed or `old` is out of bounds.
+    pub fn get(&self, old: LocalIndex) -> Option<LocalIndex> {
+        match self.entries.get(old.0) {
+            Some((new, true)) => Some(*new),
+            _ => None,
+        }
+    }
+
+    /// Translates a cursor placed after the entry at `old`, e.g. as the
+    /// reference of the next insertion.
+    ///
+    /// As removed entries are invisible, a cursor after one of them moves to
+    /// the nearest remaining element (or the root) before it. Returns `None` if `old` is out
+    /// of bounds.
+    pub fn translate_cursor(&self, old: LocalIndex) -> Option<LocalIndex> {
+        self.entries.get(old.0).map(|(new, _)| *new)
+    }
+
+    /// Translates cursors in place, see `translate_cursor`.
+    ///
+    /// Cursors out of bounds are left unchanged.
+    pub fn translate_cursors<'a>(&self, cursors: impl IntoIterator<Item = &'a mut LocalIndex>) {
+        for cursor in cursors {
+            if let Some(new) = self.translate_cursor(*cursor) {
+                *cursor = new;
+            }
+        }
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Removes deleted elements from the log, as far as the configured
+    /// `RetentionPolicy` allows, and returns how log indices changed.
+    ///
+    /// Only elements whose deletion is part of `stable` are considered. Every
+    /// replica must have seen `stable`, and all ops concurrent to it must have
+    /// been applied locally. Otherwise, ops referencing removed elements can't
+    /// be applied anymore.
+    ///
+    /// Elements inserted after a removed element are attached to its
+    /// reference instead, as long as this doesn't change the order of
+    /// elements. Remaining changes keep their timestamps, but their log
+    /// indices change.
+    pub fn compact(&mut self, stable: &Version<A>) -> IndexRemap {
+        let removed = self.find_removable(stable);
+        if removed.contains(&true) {
+            self.compacted_version.join(stable);
+            self.remove_entries(&removed)
+        } else {
+            IndexRemap::identity(removed.len())
+        }
+    }
+
+    /// Replaces the values of deleted elements with `T::default()` and
+    /// returns how many were replaced.
+    ///
+    /// Unlike `compact`, this keeps the structure of the log, so log indices
+    /// stay the same and elements can still be referenced. Only elements
+    /// whose insertion and deletion are part of `stable` are considered, as
+    /// far as the configured `RetentionPolicy` allows. Serializing the
+    /// chronofold afterwards leaves out the dropped values.
+    ///
+    /// **Note:** `history_hash` and `display_with_tombstones` see the
+    /// default values from then on.
+    pub fn drop_tombstone_payloads(&mut self, stable: &Version<A>) -> usize
+    where
+        T: Default,
+    {
+        let mut deleted_by: Vec<Option<Timestamp<A>>> = vec![None; self.log.len()];
+        for (idx, change) in self.log.iter().enumerate() {
+            let idx = LocalIndex(idx);
+            let timestamp = self.timestamp(idx).expect("log entries have timestamps");
+            if !matches!(change, Change::Delete) || !stable.contains(&timestamp) {
+                continue;
+            }
+            // Deletes might reference other deletes of the same element.
+            let mut target = self.get_reference(&idx).expect("deletes have references");
+            while let Change::Delete = self.log[target.0] {
+                target = self
+                    .get_reference(&target)
+                    .expect("deletes have references");
+            }
+            let earliest = &mut deleted_by[target.0];
+            *earliest = Some(match earliest.take() {
+                Some(t) => t.min(timestamp),
+                None => timestamp,
+            });
+        }
+
+        let mut dropped = 0;
+        for (idx, deleted_by) in deleted_by.into_iter().enumerate() {
+            let Some(deleted_by) = deleted_by else {
+                continue;
+            };
+            let element = self
+                .timestamp(LocalIndex(idx))
+                .expect("log entries have timestamps");
+            if stable.contains(&element)
+                && self.may_drop(
+                    &Tombstone {
+                        element: element.into(),
+                        deleted_by,
+                    },
+                    stable,
+                )
+            {
+                if let Change::Insert(value) = &mut self.log[idx] {
+                    *value = T::default();
+                    dropped += 1;
+                }
+            }
+        }
+        dropped
+    }
+
+    /// Returns which log entries can be removed.
+    pub(crate) fn find_removable(&self, stable: &Version<A>) -> Vec<bool> {
+        let len = self.log.len();
+        let timestamps: Vec<Timestamp<A>> = (0..len)
+            .map(|idx| {
+                self.timestamp(LocalIndex(idx))
+                    .expect("log entries have timestamps")
+            })
+            .collect();
+
+        // Children are always after their references in the log, so a
+        // reverse pass sees them first.
+        let mut deletes_stable = vec![true; len];
+        let mut deleted_by: Vec<Option<Timestamp<A>>> = vec![None; len];
+        let mut removed = vec![false; len];
+        for idx in (0..len).rev() {
+            let timestamp = &timestamps[idx];
+            let is_stable = stable.contains(timestamp);
+            match &self.log[idx] {
+                Change::Delete => {
+                    let reference = self
+                        .get_reference(&LocalIndex(idx))
+                        .expect("deletes have references");
+                    deletes_stable[reference.0] &= deletes_stable[idx] && is_stable;
+                    let earliest = &mut deleted_by[reference.0];
+                    *earliest = Some(match earliest.take() {
+                        Some(t) => t.min(timestamp.clone()),
+                        None => timestamp.clone(),
+                    });
+                }
+                Change::Insert(_) => {
+                    removed[idx] = is_stable
+                        && deletes_stable[idx]
+                        && deleted_by[idx].take().is_some_and(|deleted_by| {
+                            self.may_drop(
+                                &Tombstone {
+                                    element: timestamp.clone().into(),
+                                    deleted_by,
+                                },
+                                stable,
+                            )
+                        });
+                }
+                Change::Root => {}
+            }
+        }
+
+        // Keep elements until removing them doesn't reorder the remaining
+        // ones.
+        loop {
+            self.remove_deletes_of_removed(&mut removed);
+            let kept = self.find_reordering(&removed, &timestamps, stable);
+            if kept.is_empty() {
+                break removed;
+            }
+            for idx in kept {
+                removed[idx.0] = false;
+            }
+        }
+    }
+
+    /// Marks deletes of removed elements as removed and all others as kept.
+    fn remove_deletes_of_removed(&self, removed: &mut [bool]) {
+        for idx in 0..removed.len() {
+            if matches!(self.log[idx], Change::Delete) {
+                let reference = self
+                    .get_reference(&LocalIndex(idx))
+                    .expect("deletes have references");
+                removed[idx] = removed[reference.0];
+            }
+        }
+    }
+
+    /// Returns removed elements that have to be kept, because their children
+    /// would be placed differently among their new siblings.
+    fn find_reordering(
+        &self,
+        removed: &[bool],
+        timestamps: &[Timestamp<A>],
+        stable: &Version<A>,
+    ) -> Vec<LocalIndex> {
+        let parents = self.surviving_parents(removed);
+        // For removed entries, the removed ancestor that is a child of the
+        // surviving parent.
+        let mut topmost: Vec<LocalIndex> = (0..removed.len()).map(LocalIndex).collect();
+        for idx in 0..removed.len() {
+            if let Some(reference) = self.get_reference(&LocalIndex(idx)) {
+                if removed[idx] && removed[reference.0] {
+                    topmost[idx] = topmost[reference.0];
+                }
+            }
+        }
+        let moved_by = |idx: LocalIndex| {
+            let reference = self.get_reference(&idx)?;
+            if removed[reference.0] {
+                Some(topmost[reference.0])
+            } else {
+                None
+            }
+        };
+
+        // Siblings are visited in their order, i.e. the previous sibling has
+        // to compare `Greater`. Other replicas will compare new siblings with
+        // the removed elements, which only works for the timestamp order.
+        let custom_order = self.policies.sibling_order.is_some();
+        let mut last_child: Vec<Option<LocalIndex>> = vec![None; removed.len()];
+        let mut kept = Vec::new();
+        for (change, idx) in self.iter_log_indices_causal_range(..) {
+            if removed[idx.0] || !matches!(change, Change::Insert(_)) {
+                continue;
+            }
+            let parent = match parents[idx.0] {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let moved = moved_by(idx);
+            if let Some(ancestor) = moved {
+                if custom_order || !stable.contains(&timestamps[idx.0]) {
+                    kept.push(ancestor);
+                }
+            }
+            if let Some(previous) = last_child[parent.0] {
+                if self.cmp_siblings(&timestamps[previous.0], &timestamps[idx.0])
+                    != Ordering::Greater
+                {
+                    kept.extend(moved.or_else(|| moved_by(previous)));
+                }
+            }
+            last_child[parent.0] = Some(idx);
+        }
+        kept
+    }
+
+    /// Returns the nearest ancestor that isn't removed for each log entry.
+    fn surviving_parents(&self, removed: &[bool]) -> Vec<Option<LocalIndex>> {
+        let mut parents: Vec<Option<LocalIndex>> = vec![None; removed.len()];
+        for idx in 0..removed.len() {
+            parents[idx] = self.get_reference(&LocalIndex(idx)).and_then(|reference| {
+                if removed[reference.0] {
+                    parents[reference.0]
+                } else {
+                    Some(reference)
+                }
+            });
+        }
+        parents
+    }
+
+    /// Rewrites the log and co-structures without the `removed` entries.
+    pub(crate) fn remove_entries(&mut self, removed: &[bool]) -> IndexRemap {
+        self.clear_materialized();
+        let parents = self.surviving_parents(removed);
+        let mut new_indices = Vec::with_capacity(removed.len());
+        let mut count = 0;
+        for (idx, removed) in removed.iter().enumerate() {
+            new_indices.push(LocalIndex(idx - count));
+            if *removed {
+                count += 1;
+            }
+        }
+        let new_index = |idx: LocalIndex| new_indices[idx.0];
+
+        let mut remap = IndexRemap {
+            entries: new_indices
+                .iter()
+                .zip(removed)
+                .map(|(new, removed)| (*new, !removed))
+                .collect(),
+        };
+        let mut last_kept = new_index(self.root);
+        for (change, idx) in self.iter_log_indices_causal_range(..) {
+            if removed[idx.0] {
+                remap.entries[idx.0].0 = last_kept;
+            } else if !matches!(change, Change::Delete) {
+                last_kept = new_index(idx);
+            }
+        }
+
+        let log = mem::take(&mut self.log);
+        let mut costructures = Costructures::new();
+        for (idx, change) in log.into_iter().enumerate() {
+            if removed[idx] {
+                continue;
+            }
+            let old = LocalIndex(idx);
+            let new = new_index(old);
+            let timestamp = self.timestamp(old).expect("log entries have timestamps");
+
+            // Skip removed entries in causal order.
+            let mut next = self.get_next_index(&old);
+            while let Some(idx) = next.filter(|idx| removed[idx.0]) {
+                next = self.get_next_index(&idx);
+            }
+
+            costructures.set_next_index(new, next.map(new_index));
+            costructures.set_reference(new, parents[idx].map(new_index));
+            costructures.set_author(new, timestamp.author);
+            costructures.set_index_shift(new, IndexShift(new.0.wrapping_sub(timestamp.idx.0)));
+            self.log.push(change);
+        }
+        costructures.rebuild_previous_indices();
+
+        self.root = new_index(self.root);
+        self.costructures = costructures;
+        self.contributions.take();
+        self.timestamp_index.take();
+        self.compacted += count;
+        self.rebuild_visibility();
+        self.depths.clear();
+        self.extend_depths();
+        self.prune_conflicts();
+        remap
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::{Author, Change, Chronofold, ElementId, LocalIndex, Timestamp};
+
+/// Runs of elements inserted concurrently by different authors at the same
+/// place.
+///
+/// All replicas order such runs the same way, so they converge, but the
+/// result might not be what either author intended. UIs can use conflict
+/// regions to let users review these merges.
+///
+/// Conflicts are recorded while applying remote ops, once enabled by
+/// `set_conflict_tracking`. An insertion conflicts with a sibling by another
+/// author with an equal or greater author index, which its author can't have
+/// seen. Concurrent siblings with smaller author indices aren't detected.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ConflictRegion<A> {
+    /// The element the runs were inserted after.
+    reference: Timestamp<A>,
+    /// The first element of each run.
+    runs: BTreeSet<ElementId<A>>,
+}
+
+impl<A: Author> ConflictRegion<A> {
+    /// Returns the first element of each run.
+    pub fn runs(&self) -> impl Iterator<Item = &ElementId<A>> {
+        self.runs.iter()
+    }
+
+    /// Returns the authors of the runs.
+    pub fn authors(&self) -> BTreeSet<A> {
+        self.runs.iter().map(|id| id.author().clone()).collect()
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns the conflicts recorded so far, oldest first.
+    pub fn conflicts(&self) -> &[ConflictRegion<A>] {
+        &self.conflicts
+    }
+
+    /// Returns and forgets the conflicts recorded so far, e.g. once they
+    /// were reviewed.
+    pub fn take_conflicts(&mut self) -> Vec<ConflictRegion<A>> {
+        std::mem::take(&mut self.conflicts)
+    }
+
+    /// Returns the positions of the visible elements in a conflict region.
+    ///
+    /// The region spans the runs and everything inserted after them later,
+    /// so it grows as the runs are continued. Returns `None` if a run was
+    /// removed by compaction.
+    pub fn conflict_range(&self, region: &ConflictRegion<A>) -> Option<Range<usize>> {
+        let mut range: Option<Range<usize>> = None;
+        for run in &region.runs {
+            let first = self.resolve(run)?;
+            let last = self
+                .iter_subtree(first)
+                .last()
+                .expect("subtrees contain their root");
+            let start = self.visible_before(first);
+            let end = self.visible_before(last) + usize::from(self.is_visible_element(last));
+            range = Some(match range {
+                Some(range) => range.start.min(start)..range.end.max(end),
+                None => start..end,
+            });
+        }
+        range
+    }
+
+    /// Records a conflict if an insertion by `id` after `reference` has
+    /// siblings it can't have seen.
+    pub(crate) fn record_conflicts(&mut self, id: Timestamp<A>, reference: LocalIndex) {
+        let concurrent: Vec<ElementId<A>> = self
+            .iter_subtree(reference)
+            .filter(|idx| {
+                self.get_reference(idx) == Some(reference)
+                    && matches!(self.log[idx.0], Change::Insert(_))
+            })
+            .filter_map(|idx| self.timestamp(idx))
+            .filter(|t| t.author != id.author && t.idx >= id.idx)
+            .map(ElementId::from)
+            .collect();
+        if concurrent.is_empty() {
+            return;
+        }
+
+        let reference = self
+            .timestamp(reference)
+            .expect("log entries have timestamps");
+        let runs = concurrent.into_iter().chain(Some(id.into()));
+        match self
+            .conflicts
+            .iter_mut()
+            .find(|region| region.reference == reference)
+        {
+            Some(region) => region.runs.extend(runs),
+            None => self.conflicts.push(ConflictRegion {
+                reference,
+                runs: runs.collect(),
+            }),
+        }
+    }
+
+    /// Forgets runs removed by compaction, and regions left with fewer than
+    /// two runs.
+    pub(crate) fn prune_conflicts(&mut self) {
+        let mut conflicts = std::mem::take(&mut self.conflicts);
+        for region in &mut conflicts {
+            region.runs.retain(|run| self.resolve(run).is_some());
+        }
+        conflicts.retain(|region| region.runs.len() > 1);
+        self.conflicts = conflicts;
+    }
+}
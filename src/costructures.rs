@@ -1,74 +1,124 @@
 use std::collections::BTreeMap;
 use std::mem;
 
-use crate::{IndexShift, LocalIndex, RelativeNextIndex, RelativeReference, Author};
+use crate::index::MAX_LOG_INDEX;
 use crate::offsetmap::Offset;
+use crate::{
+    Author, IndexShift, LocalIndex, RelativeNextIndex, RelativePreviousIndex, RelativeReference,
+};
 use std::fmt::{Debug, Formatter};
-use std::marker::PhantomData;
+use std::ops::RangeBounds;
 
 macro_rules! costructures_get_btree_range {
-    ($cs:expr, $key:tt, $flag:expr, $shift:expr) => {
-        {
-            let key = $key.0 | $flag << $shift;
-            $cs.map.range(($flag << $shift)..=key).map(|(_, v)| v).next_back().cloned()
-        }
-    }
+    ($cs:expr, $key:tt, $flag:expr, $shift:expr) => {{
+        let key = $key.0 | $flag << $shift;
+        $cs.map
+            .range(($flag << $shift)..=key)
+            .map(|(_, v)| v)
+            .next_back()
+            .cloned()
+    }};
 }
 
 macro_rules! costructures_get_btree_exact {
-    ($cs:expr, $key:tt, $flag:expr, $shift:expr) => {
-        {
-            let key = $key.0 | $flag << $shift;
-            $cs.map.get(&key).cloned()
-        }
-    }
+    ($cs:expr, $key:tt, $flag:expr, $shift:expr) => {{
+        let key = $key.0 | $flag << $shift;
+        $cs.map.get(&key).cloned()
+    }};
 }
 
 macro_rules! costructures_set_btree_range {
-    ($cs:expr, $key:tt, $value:tt, $flag:expr, $shift:expr) => {
+    ($cs:expr, $key:tt, $value:tt, $flag:expr, $shift:expr) => {{
+        debug_assert!(
+            $key.0 & !Self::DEMASK == 0,
+            "log index {} collides with the flags",
+            $key
+        );
         if costructures_get_btree_range!($cs, $key, $flag, $shift) != Some($value) {
             let key = $key.0 | $flag << $shift;
             $cs.map.insert(key, $value);
         }
-    }
+    }};
 }
 
 macro_rules! costructures_set_btree_exact {
     ($cs:expr, $key:tt, $value:tt, $flag:expr, $shift:expr, $type:ident) => {
+        debug_assert!(
+            $key.0 & !Self::DEMASK == 0,
+            "log index {} collides with the flags",
+            $key
+        );
         let key = $key.0 | $flag << $shift;
 
         let value = match $value {
             Some(value) => {
                 if $type::default().add(&LocalIndex($key.0)) == value {
                     $cs.map.remove(&key);
-                    return
+                    return;
                 } else {
-                    let offset = $type::sub(&value, &$key);
-                    offset.0 as usize
+                    $type::sub(&value, &$key).to_bits()
                 }
-            },
+            }
             None => 0,
         };
 
         $cs.map.insert(key, value);
-    }
+    };
 }
 
 ///
 /// Optimization suggested in the original paper by storing all four metadata in one sorted map
 /// the types of values are discerned by the two most significant bits in the integer key
-#[derive(Clone, PartialEq, Eq)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+///
+/// The previous indices (causal order) are derived from the next indices.
+/// They're kept in a separate map, encoded like the next indices, and are
+/// neither compared nor serialized.
+///
+/// Authors are interned, i.e. the map stores compact ids in the order
+/// authors were first seen, so any author type fits into it.
+#[derive(Clone)]
 pub(crate) struct Costructures<A> {
     map: BTreeMap<usize, usize>,
-    dummy: PhantomData<A>,
+    previous: BTreeMap<usize, usize>,
+    authors: Vec<A>,
+    author_ids: BTreeMap<A, usize>,
+}
+
+// Author ids depend on the order ops were applied in, so they're compared by
+// the authors they stand for.
+impl<A: PartialEq> PartialEq for Costructures<A> {
+    fn eq(&self, other: &Self) -> bool {
+        let authors = Self::A_FLAG << Self::A_SHIFT..Self::II_FLAG << Self::II_SHIFT;
+        self.map
+            .range(..authors.start)
+            .eq(other.map.range(..authors.start))
+            && self
+                .map
+                .range(authors.end..)
+                .eq(other.map.range(authors.end..))
+            && self
+                .map
+                .range(authors.clone())
+                .map(|(k, v)| (k, &self.authors[*v]))
+                .eq(other
+                    .map
+                    .range(authors)
+                    .map(|(k, v)| (k, &other.authors[*v])))
+    }
 }
 
+impl<A: Eq> Eq for Costructures<A> {}
+
 impl<A> Costructures<A> {
     pub(crate) fn new() -> Self {
+        let mut previous = BTreeMap::new();
+        // The root comes first in causal order.
+        previous.insert(0, 0);
         Self {
             map: BTreeMap::new(),
-            dummy: PhantomData::default(),
+            previous,
+            authors: Vec::new(),
+            author_ids: BTreeMap::new(),
         }
     }
 
@@ -81,25 +131,29 @@ impl<A> Costructures<A> {
     const II_FLAG: usize = 3;
     const II_SHIFT: usize = mem::size_of::<usize>() * 8 - 2;
 
-    const DEMASK: usize = !(Self::II_FLAG << Self::II_SHIFT);
+    const DEMASK: usize = MAX_LOG_INDEX;
 
     pub(crate) fn get_next_index(&self, key: &LocalIndex) -> Option<LocalIndex> {
         let value = costructures_get_btree_exact!(self, key, Self::RNI_FLAG, Self::RNI_SHIFT);
-        Self::process_relative(key, value, RelativeNextIndex)
+        Self::process_relative(key, value, RelativeNextIndex::from_bits)
     }
 
     pub(crate) fn get_reference(&self, key: &LocalIndex) -> Option<LocalIndex> {
         let value = costructures_get_btree_exact!(self, key, Self::RR_FLAG, Self::RR_SHIFT);
-        Self::process_relative(key, value, RelativeReference)
+        Self::process_relative(key, value, RelativeReference::from_bits)
     }
 
-    fn process_relative<O>(key: &LocalIndex, value: Option<usize>, maker: impl FnOnce(isize) -> O) -> Option<LocalIndex>
-        where
-            O: Offset<LocalIndex>,
+    fn process_relative<O>(
+        key: &LocalIndex,
+        value: Option<usize>,
+        from_bits: impl FnOnce(usize) -> O,
+    ) -> Option<LocalIndex>
+    where
+        O: Offset<LocalIndex>,
     {
         let value = match value {
             Some(value) => value,
-            _ => return Some(O::default().add(&key)),
+            _ => return Some(O::default().add(key)),
         };
 
         // for some reason 0 isn't a valid value, for data compaction 0 is treated as None
@@ -107,16 +161,79 @@ impl<A> Costructures<A> {
             return None;
         }
 
-        let i = value as isize;
-        Some(maker(i).add(key))
+        Some(from_bits(value).add(key))
     }
 
+    pub(crate) fn get_previous_index(&self, key: &LocalIndex) -> Option<LocalIndex> {
+        let value = self.previous.get(&key.0).copied();
+        Self::process_relative(key, value, RelativePreviousIndex::from_bits)
+    }
+
+    /// Sets the next index of `key`, and `key` as the previous index of
+    /// `value`.
+    ///
+    /// The former next index of `key` has to be linked to another entry
+    /// afterwards, as its previous index is stale otherwise.
     pub(crate) fn set_next_index(&mut self, key: LocalIndex, value: Option<LocalIndex>) {
-        costructures_set_btree_exact!(self, key, value, Self::RNI_FLAG, Self::RNI_SHIFT, RelativeNextIndex);
+        if let Some(value) = value {
+            self.set_previous_index(value, Some(key));
+        }
+        costructures_set_btree_exact!(
+            self,
+            key,
+            value,
+            Self::RNI_FLAG,
+            Self::RNI_SHIFT,
+            RelativeNextIndex
+        );
     }
 
     pub(crate) fn set_reference(&mut self, key: LocalIndex, value: Option<LocalIndex>) {
-        costructures_set_btree_exact!(self, key, value, Self::RR_FLAG, Self::RR_SHIFT, RelativeReference);
+        costructures_set_btree_exact!(
+            self,
+            key,
+            value,
+            Self::RR_FLAG,
+            Self::RR_SHIFT,
+            RelativeReference
+        );
+    }
+
+    /// Sets the previous index of `key`, which is only needed for entries
+    /// without one, as `set_next_index` sets all others.
+    pub(crate) fn set_previous_index(&mut self, key: LocalIndex, value: Option<LocalIndex>) {
+        let bits = match value {
+            Some(value) if RelativePreviousIndex::default().checked_add(&key) == Ok(value) => {
+                self.previous.remove(&key.0);
+                return;
+            }
+            Some(value) => RelativePreviousIndex::sub(&value, &key).to_bits(),
+            None => 0,
+        };
+        self.previous.insert(key.0, bits);
+    }
+
+    /// Rebuilds the previous indices from the next indices.
+    ///
+    /// Entries are their successor's default previous index, unless they
+    /// have a next index other than the default. The successors of these
+    /// entries only have a previous index, if another entry links to them.
+    pub(crate) fn rebuild_previous_indices(&mut self) {
+        self.previous.clear();
+        self.previous.insert(0, 0);
+        let next: Vec<(usize, Option<LocalIndex>)> = self
+            .map
+            .range(..Self::RR_FLAG << Self::RR_SHIFT)
+            .map(|(k, _)| (*k, self.get_next_index(&LocalIndex(*k))))
+            .collect();
+        for (key, _) in &next {
+            self.previous.insert(key + 1, 0);
+        }
+        for (key, value) in next {
+            if let Some(value) = value {
+                self.set_previous_index(value, Some(LocalIndex(key)));
+            }
+        }
     }
 
     pub(crate) fn get_index_shift(&self, key: &LocalIndex) -> Option<IndexShift> {
@@ -130,39 +247,264 @@ impl<A> Costructures<A> {
     }
 }
 
+impl<A> Costructures<A> {
+    /// Removes the co-structures of all log indices from `index` on.
+    pub(crate) fn truncate(&mut self, index: LocalIndex) {
+        for &(flag, shift) in &[
+            (Self::RNI_FLAG, Self::RNI_SHIFT),
+            (Self::RR_FLAG, Self::RR_SHIFT),
+            (Self::A_FLAG, Self::A_SHIFT),
+            (Self::II_FLAG, Self::II_SHIFT),
+        ] {
+            self.remove_keys(index.0 | flag << shift..=Self::DEMASK | flag << shift);
+        }
+        self.previous.split_off(&index.0);
+    }
+
+    fn remove_keys(&mut self, keys: impl RangeBounds<usize>) {
+        let keys: Vec<usize> = self.map.range(keys).map(|(k, _)| *k).collect();
+        for key in keys {
+            self.map.remove(&key);
+        }
+    }
+}
+
 impl<A: Author> Costructures<A> {
-    pub(crate) fn get_author(&self, key: &LocalIndex) -> Option<A> {
-        costructures_get_btree_range!(self, key, Self::A_FLAG, Self::A_SHIFT).map(A::from)
+    pub(crate) fn get_author(&self, key: &LocalIndex) -> Option<&A> {
+        costructures_get_btree_range!(self, key, Self::A_FLAG, Self::A_SHIFT)
+            .map(|id| &self.authors[id])
     }
 
     pub(crate) fn set_author(&mut self, key: LocalIndex, value: A) {
-        let value = value.as_usize();
+        let value = self.intern(value);
         costructures_set_btree_range!(self, key, value, Self::A_FLAG, Self::A_SHIFT)
     }
+
+    /// Returns the id of `author`, assigning the next one if it's new.
+    fn intern(&mut self, author: A) -> usize {
+        if let Some(id) = self.author_ids.get(&author) {
+            return *id;
+        }
+        self.authors.push(author.clone());
+        self.author_ids.insert(author, self.authors.len() - 1);
+        self.authors.len() - 1
+    }
 }
 
 impl<A> Debug for Costructures<A> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_map()
-            .entries(self.map
-                .range(..Self::RR_FLAG << Self::RR_SHIFT)
-                .map(|(k, v)| (k, if *v != 0 { Some(RelativeNextIndex(*v as isize)) } else { None })))
-            .entries(self.map
-                .range(Self::RR_FLAG << Self::RR_SHIFT..Self::A_FLAG << Self::A_SHIFT)
-                .map(|(k, v)| (k & Self::DEMASK, if *v != 0 { Some(RelativeReference(*v as isize)) } else { None })))
-            .entries(self.map
-                .range(Self::A_FLAG << Self::A_SHIFT .. Self::II_FLAG << Self::II_SHIFT)
-                .map(|(k, v)| (k & Self::DEMASK, format!("Author({})", *v))))
-            .entries(self.map
-                .range(Self::II_FLAG << Self::II_SHIFT..)
-                .map(|(k, v)| (k & Self::DEMASK, IndexShift(*v))))
+            .entries(
+                self.map
+                    .range(..Self::RR_FLAG << Self::RR_SHIFT)
+                    .map(|(k, v)| {
+                        (
+                            k,
+                            if *v != 0 {
+                                Some(RelativeNextIndex::from_bits(*v))
+                            } else {
+                                None
+                            },
+                        )
+                    }),
+            )
+            .entries(
+                self.map
+                    .range(Self::RR_FLAG << Self::RR_SHIFT..Self::A_FLAG << Self::A_SHIFT)
+                    .map(|(k, v)| {
+                        (
+                            k & Self::DEMASK,
+                            if *v != 0 {
+                                Some(RelativeReference::from_bits(*v))
+                            } else {
+                                None
+                            },
+                        )
+                    }),
+            )
+            .entries(
+                self.map
+                    .range(Self::A_FLAG << Self::A_SHIFT..Self::II_FLAG << Self::II_SHIFT)
+                    .map(|(k, v)| (k & Self::DEMASK, format!("Author({})", *v))),
+            )
+            .entries(
+                self.map
+                    .range(Self::II_FLAG << Self::II_SHIFT..)
+                    .map(|(k, v)| (k & Self::DEMASK, IndexShift(*v))),
+            )
             .finish()
     }
 }
 
+/// Costructures are serialized as one sequence per co-structure, rather than
+/// the packed map with its format-dependent (and in some formats huge) keys.
+///
+/// As defaults are never stored, consecutive deletes or moves make up most
+/// of the stored relative indices. These usually share the same offset, so
+/// next indices and references are run-length encoded as
+/// `(first index, length, offset)`, where an offset of `None` means there is
+/// no next index or reference.
+///
+/// They are deserialized as `Repr` and only checked and expanded by
+/// `from_repr` once the length of the log is known. This fails if a log
+/// index or a relative index is out of range.
+#[cfg(feature = "serde")]
+pub(crate) use self::serde::Repr;
+
+#[cfg(feature = "serde")]
+mod serde {
+    use super::Costructures;
+    use crate::index::{OffsetOverflow, MAX_LOG_INDEX};
+    use crate::offsetmap::Offset;
+    use crate::{Author, LocalIndex, RelativeNextIndex, RelativeReference};
+    use serde::de::Error;
+    use serde::{Serialize, Serializer};
+    use std::ops::Range;
+
+    type Runs = Vec<(usize, usize, Option<isize>)>;
+
+    #[derive(Serialize, Deserialize)]
+    pub(crate) struct Repr<A> {
+        next: Runs,
+        references: Runs,
+        authors: Vec<(usize, A)>,
+        shifts: Vec<(usize, usize)>,
+    }
+
+    impl<A> Costructures<A> {
+        fn entries(&self, flag: usize, shift: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+            let start = flag << shift;
+            let end = Self::DEMASK | flag << shift;
+            self.map
+                .range(start..=end)
+                .map(|(k, v)| (k & Self::DEMASK, *v))
+        }
+
+        fn runs(&self, flag: usize, shift: usize) -> Runs {
+            let mut runs: Runs = Vec::new();
+            for (idx, value) in self.entries(flag, shift) {
+                let offset = if value == 0 {
+                    None
+                } else {
+                    Some(RelativeNextIndex::from_bits(value).0)
+                };
+                match runs.last_mut() {
+                    Some((start, len, o)) if *start + *len == idx && *o == offset => *len += 1,
+                    _ => runs.push((idx, 1, offset)),
+                }
+            }
+            runs
+        }
+
+        /// Inserts the entries of `runs`, which must lie in a log of length
+        /// `log_len`.
+        fn insert_runs<O>(
+            &mut self,
+            runs: Runs,
+            log_len: usize,
+            flag: usize,
+            shift: usize,
+            maker: impl Fn(isize) -> O,
+            to_bits: impl Fn(O) -> usize,
+        ) -> Result<(), OffsetOverflow>
+        where
+            O: Offset<LocalIndex> + Copy,
+        {
+            // Check all runs first, so a huge run can't keep us busy before
+            // an invalid one is found.
+            let out_of_log = |(start, len, _): &(usize, usize, _)| {
+                start.checked_add(*len).is_none_or(|end| end > log_len)
+            };
+            if runs.iter().any(out_of_log) {
+                return Err(OffsetOverflow);
+            }
+            for (start, len, offset) in runs {
+                let end = start + len;
+                let offset = offset.filter(|o| *o != 0).map(&maker);
+                for idx in (Range { start, end }) {
+                    let value = match offset {
+                        Some(offset) => {
+                            offset.checked_add(&LocalIndex(idx))?;
+                            to_bits(offset)
+                        }
+                        None => 0,
+                    };
+                    self.map.insert(idx | flag << shift, value);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl<A> Serialize for Costructures<A>
+    where
+        A: Serialize + Author,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            Repr {
+                next: self.runs(Self::RNI_FLAG, Self::RNI_SHIFT),
+                references: self.runs(Self::RR_FLAG, Self::RR_SHIFT),
+                authors: self
+                    .entries(Self::A_FLAG, Self::A_SHIFT)
+                    .map(|(idx, v)| (idx, self.authors[v].clone()))
+                    .collect(),
+                shifts: self.entries(Self::II_FLAG, Self::II_SHIFT).collect(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<A: Author> Costructures<A> {
+        /// Checks and expands the costructures of a log of length `log_len`.
+        pub(crate) fn from_repr<E: Error>(repr: Repr<A>, log_len: usize) -> Result<Self, E> {
+            let mut costructures = Self::new();
+            costructures
+                .insert_runs(
+                    repr.next,
+                    log_len,
+                    Self::RNI_FLAG,
+                    Self::RNI_SHIFT,
+                    RelativeNextIndex,
+                    RelativeNextIndex::to_bits,
+                )
+                .map_err(E::custom)?;
+            costructures
+                .insert_runs(
+                    repr.references,
+                    log_len,
+                    Self::RR_FLAG,
+                    Self::RR_SHIFT,
+                    RelativeReference,
+                    RelativeReference::to_bits,
+                )
+                .map_err(E::custom)?;
+            let out_of_range = |idx: &usize| *idx > MAX_LOG_INDEX;
+            if repr.authors.iter().map(|(idx, _)| idx).any(out_of_range)
+                || repr.shifts.iter().map(|(idx, _)| idx).any(out_of_range)
+            {
+                return Err(E::custom("log index out of range"));
+            }
+            for (idx, author) in repr.authors {
+                costructures.set_author(LocalIndex(idx), author);
+            }
+            for (idx, shift) in repr.shifts {
+                costructures
+                    .map
+                    .insert(idx | Self::II_FLAG << Self::II_SHIFT, shift);
+            }
+            costructures.rebuild_previous_indices();
+            Ok(costructures)
+        }
+    }
+}
+
 #[cfg(test)]
 mod costructures_tests {
     use super::*;
+    use crate::index::OffsetOverflow;
 
     type Map = Costructures<usize>;
 
@@ -171,8 +513,8 @@ mod costructures_tests {
         let mut map = Map::new();
         map.set_author(LocalIndex(10), 0);
         assert_eq!(None, map.get_author(&LocalIndex(5)));
-        assert_eq!(Some(0), map.get_author(&LocalIndex(10)));
-        assert_eq!(Some(0), map.get_author(&LocalIndex(15)));
+        assert_eq!(Some(&0), map.get_author(&LocalIndex(10)));
+        assert_eq!(Some(&0), map.get_author(&LocalIndex(15)));
         assert_eq!(None, map.get_index_shift(&LocalIndex(15)));
 
         map.set_next_index(LocalIndex(42), None);
@@ -186,6 +528,76 @@ mod costructures_tests {
         assert_eq!(Some(LocalIndex(1)), map.get_next_index(&LocalIndex(0)));
     }
 
+    #[test]
+    fn truncate() {
+        let mut map = Map::new();
+        map.set_author(LocalIndex(0), 0);
+        map.set_author(LocalIndex(10), 1);
+        map.set_index_shift(LocalIndex(0), IndexShift(0));
+        map.set_index_shift(LocalIndex(12), IndexShift(3));
+        map.set_next_index(LocalIndex(12), Some(LocalIndex(20)));
+        map.set_reference(LocalIndex(15), None);
+
+        map.truncate(LocalIndex(10));
+        assert_eq!(Some(LocalIndex(13)), map.get_next_index(&LocalIndex(12)));
+        assert_eq!(Some(LocalIndex(14)), map.get_reference(&LocalIndex(15)));
+        assert_eq!(Some(&0), map.get_author(&LocalIndex(20)));
+        assert_eq!(Some(IndexShift(0)), map.get_index_shift(&LocalIndex(20)));
+        assert_eq!(2, map.map.len());
+    }
+
+    #[test]
+    fn checked_offsets() {
+        let max = LocalIndex(MAX_LOG_INDEX);
+        assert_eq!(
+            Ok(max),
+            RelativeNextIndex(1).checked_add(&LocalIndex(MAX_LOG_INDEX - 1))
+        );
+        assert_eq!(Err(OffsetOverflow), RelativeNextIndex(1).checked_add(&max));
+        assert_eq!(
+            Err(OffsetOverflow),
+            RelativeReference(-1).checked_add(&LocalIndex(0))
+        );
+        assert_eq!(
+            Err(OffsetOverflow),
+            RelativeNextIndex(isize::MAX).checked_add(&LocalIndex(1))
+        );
+        assert_eq!(
+            Ok(RelativeReference(-(MAX_LOG_INDEX as isize))),
+            RelativeReference::checked_sub(&LocalIndex(0), &max)
+        );
+        assert_eq!(
+            Err(OffsetOverflow),
+            RelativeReference::checked_sub(&LocalIndex(0), &LocalIndex(usize::MAX))
+        );
+
+        let mut map = Map::new();
+        map.set_reference(max, Some(LocalIndex(0)));
+        assert_eq!(Some(LocalIndex(0)), map.get_reference(&max));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_out_of_range() {
+        let from_str = |s| {
+            let repr = serde_json::from_str(s).unwrap();
+            Map::from_repr::<serde_json::Error>(repr, 8)
+        };
+        let valid = r#"{"next":[[0,1,4]],"references":[],"authors":[],"shifts":[]}"#;
+        assert!(from_str(valid).is_ok());
+        for invalid in &[
+            r#"{"next":[[0,1,-1]],"references":[],"authors":[],"shifts":[]}"#,
+            r#"{"next":[],"references":[[1,1,-9223372036854775808]],"authors":[],"shifts":[]}"#,
+            r#"{"next":[[1,18446744073709551615,null]],"references":[],"authors":[],"shifts":[]}"#,
+            r#"{"next":[],"references":[],"authors":[[18446744073709551615,0]],"shifts":[]}"#,
+            // Runs must not exceed the log, so they can't take long to expand.
+            r#"{"next":[[0,9,null]],"references":[],"authors":[],"shifts":[]}"#,
+            r#"{"next":[],"references":[[0,4611686018427387903,1]],"authors":[],"shifts":[]}"#,
+        ] {
+            assert!(from_str(invalid).is_err(), "{}", invalid);
+        }
+    }
+
     #[test]
     fn test_missing_compaction() {
         let mut m1 = Map::new();
@@ -200,4 +612,4 @@ mod costructures_tests {
         m2.set_index_shift(LocalIndex(10), IndexShift(1));
         assert_ne!(m1, m2);
     }
-}
\ No newline at end of file
+}
@@ -1,33 +1,18 @@
 //! Distributed primitives.
 
 use std::fmt;
+use std::str::FromStr;
 
-use crate::{AuthorIndex, Chronofold};
+use crate::{AuthorIndex, Chronofold, ParseTimestampError};
 
 /// A trait alias to reduce redundancy in type declarations.
-pub trait Author:
-    PartialEq + Eq + PartialOrd + Ord + Clone + Copy + fmt::Debug + fmt::Display
-{
-    fn from(raw: usize) -> Self;
-    fn as_usize(&self) -> usize;
-}
-
-macro_rules! impl_for_author {
-    ($type:ident) => {
-        impl Author for $type {
-            fn from(raw: usize) -> Self {
-                raw as Self
-            }
-
-            fn as_usize(&self) -> usize {
-                *self as usize
-            }
-        }
-    };
-}
+///
+/// Any ordered type can be used as author, e.g. integers, UUIDs, strings or
+/// public keys. Chronofolds intern authors internally, so their size doesn't
+/// matter for the size of the log's metadata.
+pub trait Author: PartialEq + Eq + PartialOrd + Ord + Clone + fmt::Debug {}
 
-impl_for_author!(u8);
-impl_for_author!(usize);
+impl<A> Author for A where A: PartialEq + Eq + PartialOrd + Ord + Clone + fmt::Debug {}
 
 /// An ordered pair of the author's index and the author.
 ///
@@ -35,7 +20,7 @@ impl_for_author!(usize);
 /// is consistent with cause-effect ordering. That is, if a timestamp is
 /// greater than another, its associated event either happened after the other
 /// or was concurrent.
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Timestamp<A> {
     pub idx: AuthorIndex,
@@ -54,12 +39,31 @@ impl<A: fmt::Display> fmt::Display for Timestamp<A> {
     }
 }
 
+/// Parses the format written by `Display`, e.g. `<42, 1>`.
+impl<A: FromStr> FromStr for Timestamp<A> {
+    type Err = ParseTimestampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseTimestampError::new(s);
+        let (idx, author) = s
+            .trim()
+            .strip_prefix('<')
+            .and_then(|s| s.strip_suffix('>'))
+            .and_then(|s| s.split_once(','))
+            .ok_or_else(err)?;
+        Ok(Timestamp::new(
+            AuthorIndex(idx.trim().parse().map_err(|_| err())?),
+            author.trim().parse().map_err(|_| err())?,
+        ))
+    }
+}
+
 /// An operation is the unit of change in the distributed context.
 ///
 /// Ops are independent of the subjective orders in the chronofolds'
 /// logs. Different authors exchange ops to keep their local replicas
 /// synchronized.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Op<A, T> {
     pub id: Timestamp<A>,
@@ -100,7 +104,7 @@ impl<A, T: Clone> Op<A, &T> {
 /// that is only meaningful within the context of the local chronofold. E.g. a
 /// change may refer to another change by log index, which has to be replaced
 /// by a timestamp in the distributed operation.
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum OpPayload<A, T> {
     Root,
@@ -0,0 +1,83 @@
+//! Documents made of several chronofolds.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    AppliedSummary, Author, Chronofold, DocOp, FromLocalValue, IntoLocalValue, Op, Version,
+};
+
+/// A document whose fields are chronofolds, e.g. a title and a body.
+///
+/// The fields are replicated together: Ops are addressed to fields as
+/// `DocOp`s, and a batch of them is the document's sync message.
+///
+/// With the `derive` feature, this can be implemented by
+/// `#[derive(ChronoDoc)]` for structs whose fields all have the same type
+/// `Chronofold<A, T>`. The derive also generates an enum `<Struct>Field`
+/// identifying the fields.
+pub trait ChronoDoc<A: Author, T> {
+    /// Identifies a field.
+    type Field: Ord + Copy;
+
+    /// Returns the fields and their chronofolds.
+    fn fields(&self) -> Vec<(Self::Field, &Chronofold<A, T>)>;
+
+    /// Returns the chronofold of `field`.
+    fn field_mut(&mut self, field: Self::Field) -> &mut Chronofold<A, T>;
+
+    /// Returns the versions of all fields.
+    ///
+    /// Sent to a peer, this is the counterpart to `newer_doc_ops`.
+    fn versions(&self) -> BTreeMap<Self::Field, Version<A>> {
+        self.fields()
+            .into_iter()
+            .map(|(field, cfold)| (field, cfold.version().clone()))
+            .collect()
+    }
+
+    /// Returns the ops newer than `versions` as a batch of `DocOp`s.
+    ///
+    /// Fields missing from `versions` are included with their whole history.
+    fn newer_doc_ops<'a, V>(
+        &'a self,
+        versions: &BTreeMap<Self::Field, Version<A>>,
+    ) -> Vec<DocOp<Self::Field, A, V>>
+    where
+        V: FromLocalValue<'a, A, T>,
+        A: 'a,
+        T: 'a,
+    {
+        let mut doc_ops = Vec::new();
+        for (field, cfold) in self.fields() {
+            let version = versions.get(&field);
+            doc_ops.extend(
+                cfold
+                    .iter_ops(..)
+                    .filter(|op: &Op<A, V>| {
+                        !version.is_some_and(|version| version.contains(&op.id))
+                    })
+                    .map(|op| DocOp::new(field, op)),
+            );
+        }
+        doc_ops
+    }
+
+    /// Applies a batch of `DocOp`s and returns a summary per field.
+    ///
+    /// See `apply_iter` for details.
+    fn apply_doc_ops<V>(
+        &mut self,
+        doc_ops: impl IntoIterator<Item = DocOp<Self::Field, A, V>>,
+    ) -> BTreeMap<Self::Field, AppliedSummary<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        let mut ops: BTreeMap<Self::Field, Vec<Op<A, V>>> = BTreeMap::new();
+        for DocOp { doc_id, op } in doc_ops {
+            ops.entry(doc_id).or_default().push(op);
+        }
+        ops.into_iter()
+            .map(|(field, ops)| (field, self.field_mut(field).apply_iter(ops)))
+            .collect()
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Author, Change, Chronofold, LocalIndex, ParseTimestampError, Session, Timestamp};
+
+/// Identifies an element the same way on all replicas.
+///
+/// Log indices are only meaningful in the local log and positions shift with
+/// every edit, so anchors, cursors and references that are stored or sent to
+/// peers should use element ids instead. An element id is the timestamp of
+/// the op that inserted the element.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct ElementId<A>(Timestamp<A>);
+
+impl<A> ElementId<A> {
+    /// Returns the timestamp of the op that inserted the element.
+    pub fn timestamp(&self) -> &Timestamp<A> {
+        &self.0
+    }
+
+    /// Returns the author who inserted the element.
+    pub fn author(&self) -> &A {
+        &self.0.author
+    }
+}
+
+impl<A> From<Timestamp<A>> for ElementId<A> {
+    fn from(timestamp: Timestamp<A>) -> Self {
+        Self(timestamp)
+    }
+}
+
+impl<A> From<ElementId<A>> for Timestamp<A> {
+    fn from(id: ElementId<A>) -> Self {
+        id.0
+    }
+}
+
+/// Formats the id like its timestamp, e.g. `<42, 1>`.
+impl<A: fmt::Display> fmt::Display for ElementId<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Parses the format written by `Display`.
+impl<A: FromStr> FromStr for ElementId<A> {
+    type Err = ParseTimestampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns the id of the element at log index `index`.
+    ///
+    /// Returns `None` if `index` is out of bounds or not an element, e.g. a
+    /// delete. Deleted elements still have ids.
+    pub fn element_id(&self, index: LocalIndex) -> Option<ElementId<A>> {
+        match self.log.get(index.0) {
+            Some(Change::Insert(_)) => self.timestamp(index).map(ElementId),
+            _ => None,
+        }
+    }
+
+    /// Returns the log index of the element with the given id, or `None` if
+    /// it isn't known (or was removed by compaction).
+    pub fn resolve(&self, id: &ElementId<A>) -> Option<LocalIndex> {
+        self.log_index(&id.0)
+            .filter(|idx| matches!(self.log[idx.0], Change::Insert(_)))
+    }
+
+    /// Returns the value of the element with the given id, unless it is
+    /// unknown or deleted.
+    pub fn get_by_id(&self, id: &ElementId<A>) -> Option<&T> {
+        let index = self.resolve(id).filter(|idx| !self.is_invisible(*idx))?;
+        match &self.log[index.0] {
+            Change::Insert(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the id of the visible element at `position`.
+    pub fn id_at(&self, position: usize) -> Option<ElementId<A>> {
+        self.position_to_index(position)
+            .and_then(|idx| self.element_id(idx))
+    }
+
+    /// Returns the position of the element with the given id among the
+    /// visible elements, unless it is unknown or deleted.
+    pub fn position_of(&self, id: &ElementId<A>) -> Option<usize> {
+        self.index_to_position(self.resolve(id)?)
+    }
+}
+
+impl<A: Author, T> Session<'_, A, T> {
+    /// Inserts an element after the element with the given id, or at the
+    /// beginning if `after` is `None`, and returns the new element's id.
+    ///
+    /// Returns `None` if no element with this id is known.
+    pub fn insert_after_id(
+        &mut self,
+        after: Option<&ElementId<A>>,
+        value: T,
+    ) -> Option<ElementId<A>> {
+        let reference = match after {
+            Some(id) => self.as_ref().resolve(id)?,
+            None => self.as_ref().root,
+        };
+        let index = self.insert_after(reference, value);
+        self.as_ref().element_id(index)
+    }
+
+    /// Removes the element with the given id.
+    ///
+    /// Returns `false` if no element with this id is known.
+    pub fn remove_by_id(&mut self, id: &ElementId<A>) -> bool {
+        match self.as_ref().resolve(id) {
+            Some(index) => {
+                self.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Encrypted snapshots and op logs.
+//!
+//! The crate doesn't ship a cipher. Instead, an `Aead` implementation (e.g.
+//! wrapping AES-GCM or ChaCha20-Poly1305) is passed in.
+//!
+//! An encrypted snapshot wraps a regular snapshot:
+//!
+//! | bytes | content                                   |
+//! |-------|-------------------------------------------|
+//! | 4     | magic bytes `CFLE`                        |
+//! | 1     | format version                            |
+//! | 4     | key id, LE                                |
+//! | 8     | ciphertext length in bytes, LE            |
+//!
+//! An encrypted op log is a sequence of records, each holding a batch of ops
+//! encoded as CBOR:
+//!
+//! | bytes | content                                   |
+//! |-------|-------------------------------------------|
+//! | 4     | key id, LE                                |
+//! | 8     | ciphertext length in bytes, LE            |
+//!
+//! In both cases the header up to the length is authenticated along with
+//! the ciphertext.
+//!
+//! Keys are rotated by writing with a new key while reading with a `Keyring`
+//! that still holds the old ones. Records of an op log may use different
+//! keys, and existing data can be re-encrypted with `rotate_snapshot_key` and
+//! `rotate_op_log_key`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{
+    from_cbor_slice, to_cbor_bytes, AppliedSummary, Author, Chronofold, Compression, Op,
+    SnapshotError, Version,
+};
+
+const MAGIC: &[u8; 4] = b"CFLE";
+const FORMAT_VERSION: u8 = 1;
+
+/// An authenticated cipher with associated data.
+///
+/// Implementations are responsible for nonces, e.g. by generating a random
+/// one in `seal` and prepending it to the ciphertext.
+pub trait Aead {
+    /// Identifies the key. It is stored in plaintext next to the ciphertext.
+    fn key_id(&self) -> u32;
+
+    /// Encrypts `plaintext` and authenticates it together with `aad`.
+    fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Vec<u8>;
+
+    /// Decrypts `ciphertext`, returning `None` if it or `aad` don't
+    /// authenticate.
+    fn open(&self, ciphertext: &[u8], aad: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// Looks up ciphers by key id, for reading data encrypted with old keys.
+pub trait Keyring {
+    type Cipher: Aead;
+
+    /// Returns the cipher for `key_id`, if known.
+    fn get(&self, key_id: u32) -> Option<&Self::Cipher>;
+}
+
+/// A single cipher is a keyring holding only its own key.
+impl<C: Aead> Keyring for C {
+    type Cipher = C;
+
+    fn get(&self, key_id: u32) -> Option<&C> {
+        Some(self).filter(|cipher| cipher.key_id() == key_id)
+    }
+}
+
+impl<C: Aead> Keyring for BTreeMap<u32, C> {
+    type Cipher = C;
+
+    fn get(&self, key_id: u32) -> Option<&C> {
+        BTreeMap::get(self, &key_id)
+    }
+}
+
+impl<C: Aead> Keyring for HashMap<u32, C> {
+    type Cipher = C;
+
+    fn get(&self, key_id: u32) -> Option<&C> {
+        HashMap::get(self, &key_id)
+    }
+}
+
+/// Re-encrypts a snapshot read from `reader` with `cipher`, writing it to
+/// `writer`.
+///
+/// The snapshot itself is neither decoded nor changed.
+pub fn rotate_snapshot_key(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    keys: &impl Keyring,
+    cipher: &impl Aead,
+) -> Result<(), SnapshotError> {
+    let snapshot = read_encrypted_snapshot_bytes(&mut reader, keys)?;
+    write_encrypted_snapshot_bytes(&mut writer, cipher, &snapshot)
+}
+
+/// Re-encrypts all records of an op log read from `reader` with `cipher`,
+/// writing them to `writer`, and returns the number of records.
+pub fn rotate_op_log_key(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    keys: &impl Keyring,
+    cipher: &impl Aead,
+) -> Result<usize, SnapshotError> {
+    let mut records = 0;
+    while let Some(record) = read_record(&mut reader, keys)? {
+        write_sealed(&mut writer, cipher, &cipher.key_id().to_le_bytes(), &record)?;
+        records += 1;
+    }
+    Ok(records)
+}
+
+impl<A, T> Chronofold<A, T>
+where
+    A: Author + Serialize,
+    T: Serialize,
+{
+    /// Like `save_to_with`, but encrypts the snapshot using `cipher`.
+    pub fn save_encrypted_to(
+        &self,
+        path: impl AsRef<Path>,
+        compression: Compression,
+        cipher: &impl Aead,
+    ) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        self.write_encrypted_snapshot(&mut file, compression, cipher)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Writes a snapshot encrypted using `cipher` to `writer`.
+    pub fn write_encrypted_snapshot(
+        &self,
+        mut writer: impl Write,
+        compression: Compression,
+        cipher: &impl Aead,
+    ) -> Result<(), SnapshotError> {
+        let mut snapshot = Vec::new();
+        self.write_snapshot(&mut snapshot, compression)?;
+        write_encrypted_snapshot_bytes(&mut writer, cipher, &snapshot)
+    }
+
+    /// Appends the ops newer than `version` to an encrypted op log as a
+    /// single record, and returns the number of ops written.
+    ///
+    /// Nothing is written if there are no newer ops.
+    pub fn write_encrypted_ops_since(
+        &self,
+        version: &Version<A>,
+        mut writer: impl Write,
+        cipher: &impl Aead,
+    ) -> Result<usize, SnapshotError> {
+        let ops: Vec<Op<A, &T>> = self.iter_newer_ops(version).collect();
+        if ops.is_empty() {
+            return Ok(0);
+        }
+        let header = cipher.key_id().to_le_bytes();
+        write_sealed(&mut writer, cipher, &header, &to_cbor_bytes(&ops)?)?;
+        Ok(ops.len())
+    }
+}
+
+impl<A, T> Chronofold<A, T>
+where
+    A: Author + DeserializeOwned,
+    T: DeserializeOwned,
+{
+    /// Loads an encrypted snapshot from the file at `path`, decrypting it
+    /// with the matching key from `keys`.
+    pub fn load_encrypted_from(
+        path: impl AsRef<Path>,
+        keys: &impl Keyring,
+    ) -> Result<Self, SnapshotError> {
+        Self::read_encrypted_snapshot(io::BufReader::new(fs::File::open(path)?), keys)
+    }
+
+    /// Reads an encrypted snapshot from `reader`, decrypting it with the
+    /// matching key from `keys`.
+    pub fn read_encrypted_snapshot(
+        mut reader: impl Read,
+        keys: &impl Keyring,
+    ) -> Result<Self, SnapshotError> {
+        let snapshot = read_encrypted_snapshot_bytes(&mut reader, keys)?;
+        Self::read_snapshot(&snapshot[..])
+    }
+
+    /// Applies all ops of an encrypted op log, decrypting each record with
+    /// the matching key from `keys`.
+    ///
+    /// Records may be in any order, as ops are applied as one batch (see
+    /// `apply_iter`).
+    pub fn apply_encrypted_ops(
+        &mut self,
+        mut reader: impl Read,
+        keys: &impl Keyring,
+    ) -> Result<AppliedSummary<A, T>, SnapshotError> {
+        let mut ops: Vec<Op<A, T>> = Vec::new();
+        while let Some(record) = read_record(&mut reader, keys)? {
+            ops.extend(from_cbor_slice::<Vec<Op<A, T>>>(&record)?);
+        }
+        Ok(self.apply_iter(ops))
+    }
+}
+
+fn write_encrypted_snapshot_bytes(
+    writer: &mut impl Write,
+    cipher: &impl Aead,
+    snapshot: &[u8],
+) -> Result<(), SnapshotError> {
+    let mut header = MAGIC.to_vec();
+    header.push(FORMAT_VERSION);
+    header.extend_from_slice(&cipher.key_id().to_le_bytes());
+    write_sealed(writer, cipher, &header, snapshot)
+}
+
+fn read_encrypted_snapshot_bytes(
+    reader: &mut impl Read,
+    keys: &impl Keyring,
+) -> Result<Vec<u8>, SnapshotError> {
+    let mut header = [0; 9];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != MAGIC {
+        return Err(SnapshotError::InvalidMagic);
+    }
+    if header[4] > FORMAT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(header[4]));
+    }
+    read_sealed(reader, keys, &header, &header[5..])
+}
+
+/// Reads and decrypts the next record of an op log, or returns `None` at the
+/// end of the log.
+fn read_record(
+    reader: &mut impl Read,
+    keys: &impl Keyring,
+) -> Result<Option<Vec<u8>>, SnapshotError> {
+    let mut header = [0; 4];
+    match reader.read(&mut header[..1])? {
+        0 => return Ok(None),
+        _ => reader.read_exact(&mut header[1..])?,
+    }
+    read_sealed(reader, keys, &header, &header).map(Some)
+}
+
+/// Writes `header`, the ciphertext's length and the ciphertext of
+/// `plaintext`, authenticating `header`.
+fn write_sealed(
+    writer: &mut impl Write,
+    cipher: &impl Aead,
+    header: &[u8],
+    plaintext: &[u8],
+) -> Result<(), SnapshotError> {
+    let ciphertext = cipher.seal(plaintext, header);
+    writer.write_all(header)?;
+    writer.write_all(&(ciphertext.len() as u64).to_le_bytes())?;
+    writer.write_all(&ciphertext)?;
+    Ok(())
+}
+
+/// Reads the ciphertext's length and the ciphertext following `header`, and
+/// decrypts it.
+///
+/// `key_id` is the part of `header` holding the key id.
+fn read_sealed(
+    reader: &mut impl Read,
+    keys: &impl Keyring,
+    header: &[u8],
+    key_id: &[u8],
+) -> Result<Vec<u8>, SnapshotError> {
+    let key_id = u32::from_le_bytes(key_id.try_into().expect("4 bytes"));
+    let cipher = keys.get(key_id).ok_or(SnapshotError::UnknownKey(key_id))?;
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+
+    let mut ciphertext = Vec::new();
+    reader.take(len).read_to_end(&mut ciphertext)?;
+    if (ciphertext.len() as u64) < len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    cipher
+        .open(&ciphertext, header)
+        .ok_or(SnapshotError::DecryptionFailed)
+}
@@ -1,6 +1,21 @@
 use std::fmt;
 
-use crate::{Op, OpPayload};
+use crate::{Op, OpPayload, Timestamp};
+
+/// The kind of problem an error stands for, to decide how to handle it.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum ErrorCategory {
+    /// Ops or data arrived out of order, twice, or in an unsupported format.
+    /// Usually resolved by fetching missing ops or upgrading.
+    Protocol,
+    /// A resource failed or a limit was exceeded, e.g. on I/O errors.
+    Capacity,
+    /// Data is corrupt, inconsistent or doesn't authenticate. Retrying won't
+    /// help.
+    Integrity,
+    /// An op or key was refused by the application's rules.
+    Policy,
+}
 
 /// Represents errors that can occur when applying an op.
 ///
@@ -8,21 +23,106 @@ use crate::{Op, OpPayload};
 /// as the contents of changes are omitted from any output.
 #[derive(PartialEq, Eq, Clone)]
 pub enum ChronofoldError<A, T> {
-    UnknownReference(Op<A, T>),
+    /// The op references an unknown change.
+    ///
+    /// Also contains the newest known timestamp of the referenced author, if
+    /// any. The missing ops are those of this author after that timestamp.
+    UnknownReference(Op<A, T>, Option<Timestamp<A>>),
     FutureTimestamp(Op<A, T>),
+    /// A different op with the same timestamp was applied before.
     ExistingTimestamp(Op<A, T>),
+    /// The op was applied before, e.g. because a client resent it after a
+    /// timeout.
+    AlreadyApplied(Op<A, T>),
+    /// The op references a change its author can't have seen when creating
+    /// it. Only returned in strict mode, see `Chronofold::set_strict`.
+    ImplausibleReference(Op<A, T>),
+    /// The op with the given id was rejected by the chronofold's `Validator`
+    /// or a region lock, for the given reason.
+    ///
+    /// The op itself is not kept, as its value may already be converted for
+    /// validation.
+    Rejected(Timestamp<A>, String),
+    /// The op's signature didn't verify, see `Chronofold::apply_signed`.
+    Unverified(Op<A, T>, VerifyError),
+}
+
+impl<A, T> ChronofoldError<A, T> {
+    /// Returns the category of this error.
+    ///
+    /// Missing causal history and duplicates are `Protocol` errors, a reused
+    /// timestamp, an implausible reference or a failed signature check is an
+    /// `Integrity` error and a rejected op a `Policy` error.
+    pub fn category(&self) -> ErrorCategory {
+        use ChronofoldError::*;
+        match self {
+            UnknownReference(..) | FutureTimestamp(_) | AlreadyApplied(_) => {
+                ErrorCategory::Protocol
+            }
+            ExistingTimestamp(_) | ImplausibleReference(_) | Unverified(..) => {
+                ErrorCategory::Integrity
+            }
+            Rejected(..) => ErrorCategory::Policy,
+        }
+    }
+
+    /// Returns a reference to the op that caused this error, if it was kept.
+    pub fn op(&self) -> Option<&Op<A, T>> {
+        use ChronofoldError::*;
+        match self {
+            UnknownReference(op, _)
+            | FutureTimestamp(op)
+            | ExistingTimestamp(op)
+            | AlreadyApplied(op)
+            | ImplausibleReference(op)
+            | Unverified(op, _) => Some(op),
+            Rejected(..) => None,
+        }
+    }
+
+    /// Consumes the error, returning the op that caused it, if it was kept.
+    pub fn into_op(self) -> Option<Op<A, T>> {
+        use ChronofoldError::*;
+        match self {
+            UnknownReference(op, _)
+            | FutureTimestamp(op)
+            | ExistingTimestamp(op)
+            | AlreadyApplied(op)
+            | ImplausibleReference(op)
+            | Unverified(op, _) => Some(op),
+            Rejected(..) => None,
+        }
+    }
 }
 
 impl<A, T> fmt::Debug for ChronofoldError<A, T>
 where
-    A: fmt::Debug + fmt::Display + Copy,
+    A: fmt::Debug + Clone,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use ChronofoldError::*;
         let (name, op) = match self {
-            UnknownReference(op) => ("UnknownReference", op),
+            UnknownReference(op, known) => {
+                return f
+                    .debug_tuple("UnknownReference")
+                    .field(&op.omit_value())
+                    .field(known)
+                    .finish();
+            }
             FutureTimestamp(op) => ("FutureTimestamp", op),
             ExistingTimestamp(op) => ("ExistingTimestamp", op),
+            AlreadyApplied(op) => ("AlreadyApplied", op),
+            ImplausibleReference(op) => ("ImplausibleReference", op),
+            Rejected(id, reason) => {
+                return f.debug_tuple("Rejected").field(id).field(reason).finish();
+            }
+            Unverified(op, err) => {
+                return f
+                    .debug_tuple("Unverified")
+                    .field(&op.omit_value())
+                    .field(err)
+                    .finish();
+            }
         };
         f.debug_tuple(name).field(&op.omit_value()).finish()
     }
@@ -30,42 +130,220 @@ where
 
 impl<A, T> fmt::Display for ChronofoldError<A, T>
 where
-    A: fmt::Debug + fmt::Display + Copy,
+    A: fmt::Display,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use ChronofoldError::*;
         match self {
-            UnknownReference(op) => write!(
-                f,
-                "unknown reference {}",
-                op.payload
+            UnknownReference(op, known) => {
+                let reference = op
+                    .payload
                     .reference()
-                    .expect("reference must not be `None`")
-            ),
+                    .expect("reference must not be `None`");
+                write!(f, "unknown reference {}", reference)?;
+                match known {
+                    Some(known) => write!(f, " (known up to {})", known),
+                    None => write!(f, " (no ops by {} known)", reference.author),
+                }
+            }
             FutureTimestamp(op) => write!(f, "future timestamp {}", op.id),
             ExistingTimestamp(op) => write!(f, "existing timestamp {}", op.id),
+            AlreadyApplied(op) => write!(f, "already applied {}", op.id),
+            ImplausibleReference(op) => {
+                let reference = op
+                    .payload
+                    .reference()
+                    .expect("reference must not be `None`");
+                write!(f, "implausible reference {} of {}", reference, op.id)
+            }
+            Rejected(id, reason) => write!(f, "rejected {}: {}", id, reason),
+            Unverified(op, err) => write!(f, "unverified {}: {}", op.id, err),
         }
     }
 }
 
-impl<A, T> std::error::Error for ChronofoldError<A, T> where A: fmt::Debug + fmt::Display + Copy {}
+impl<A, T> std::error::Error for ChronofoldError<A, T>
+where
+    A: fmt::Debug + fmt::Display + Clone,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ChronofoldError::Unverified(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// An error returned when verifying a signed op fails.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum VerifyError {
+    /// The author has no key for the op, or it was revoked.
+    NoKey,
+    /// The signature doesn't match the op and the author's key.
+    InvalidSignature,
+}
+
+impl VerifyError {
+    /// Returns the category of this error, which is always `Integrity`.
+    pub fn category(&self) -> ErrorCategory {
+        ErrorCategory::Integrity
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::NoKey => write!(f, "no valid key"),
+            VerifyError::InvalidSignature => write!(f, "invalid signature"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Represents an error that occurred while applying a batch of ops.
+///
+/// The error keeps the failing op and all ops after it, so callers can
+/// persist or retry them without keeping a copy of the batch around.
+#[derive(PartialEq, Eq, Clone)]
+pub struct BatchError<A, T> {
+    /// The number of ops that were applied before the error occurred.
+    pub applied: usize,
+    /// The error caused by the failing op.
+    pub error: ChronofoldError<A, T>,
+    /// The ops following the failing op, which were not applied.
+    pub remaining: Vec<Op<A, T>>,
+}
+
+impl<A, T> BatchError<A, T> {
+    /// Returns the category of the error caused by the failing op.
+    pub fn category(&self) -> ErrorCategory {
+        self.error.category()
+    }
+
+    /// Consumes the error, returning all unapplied ops starting with the
+    /// failing one (unless it was rejected).
+    pub fn into_unapplied(self) -> Vec<Op<A, T>> {
+        let mut ops = Vec::with_capacity(self.remaining.len() + 1);
+        ops.extend(self.error.into_op());
+        ops.extend(self.remaining);
+        ops
+    }
+}
+
+impl<A, T> fmt::Debug for BatchError<A, T>
+where
+    A: fmt::Debug + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BatchError")
+            .field("applied", &self.applied)
+            .field("error", &self.error)
+            .field(
+                "remaining",
+                &self
+                    .remaining
+                    .iter()
+                    .map(Op::omit_value)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<A, T> fmt::Display for BatchError<A, T>
+where
+    A: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} after applying {} ops ({} remaining)",
+            self.error,
+            self.applied,
+            self.remaining.len()
+        )
+    }
+}
+
+impl<A, T> std::error::Error for BatchError<A, T>
+where
+    A: fmt::Debug + fmt::Display + Clone + 'static,
+    T: 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// An error returned when parsing a `Version` fails.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ParseVersionError {
+    entry: String,
+}
+
+impl ParseVersionError {
+    pub(crate) fn new(entry: &str) -> Self {
+        Self {
+            entry: entry.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for ParseVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid version entry {:?}, expected \"author:index\"",
+            self.entry
+        )
+    }
+}
+
+impl std::error::Error for ParseVersionError {}
+
+/// An error returned when parsing a `Timestamp` fails.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ParseTimestampError {
+    input: String,
+}
+
+impl ParseTimestampError {
+    pub(crate) fn new(input: &str) -> Self {
+        Self {
+            input: input.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for ParseTimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid timestamp {:?}, expected \"<index, author>\"",
+            self.input
+        )
+    }
+}
+
+impl std::error::Error for ParseTimestampError {}
 
 impl<A, T> Op<A, T>
 where
-    A: Copy,
+    A: Clone,
 {
-    fn omit_value(&self) -> Op<A, Omitted> {
+    pub(crate) fn omit_value(&self) -> Op<A, Omitted> {
         use OpPayload::*;
         Op {
-            id: self.id,
-            payload: match self.payload {
+            id: self.id.clone(),
+            payload: match &self.payload {
                 Root => Root,
-                Insert(t, _) => Insert(t, Omitted),
-                Delete(t) => Delete(t),
+                Insert(t, _) => Insert(t.clone(), Omitted),
+                Delete(t) => Delete(t.clone()),
             },
         }
     }
 }
 
 #[derive(Debug)]
-struct Omitted;
+pub(crate) struct Omitted;
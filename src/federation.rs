@@ -0,0 +1,146 @@
+//! Moving an author's contributions between servers.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    AppliedSummary, Author, Chronofold, IntoLocalValue, LocalIndex, Op, OpPayload, Version,
+};
+
+/// The ops an author contributed after a version, along with the ops they
+/// build on.
+///
+/// This struct is created by the `export_contributions` method on
+/// `Chronofold`. It's self-contained, so it can be imported into a replica
+/// of the same chronofold as well as into a new one, e.g. when a user
+/// migrates their contributions to another home server.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "A: serde::Serialize, T: serde::Serialize, Version<A>: serde::Serialize",
+        deserialize = "A: Author + serde::Deserialize<'de>, T: serde::Deserialize<'de>, Version<A>: serde::Deserialize<'de>"
+    ))
+)]
+pub struct ContributionBundle<A, T> {
+    /// The author of the contributions.
+    pub author: A,
+    /// The version the contributions are newer than.
+    pub since: Version<A>,
+    /// The ops referenced by the contributions, directly or indirectly, that
+    /// aren't contributions themselves, in log order. The first one is the
+    /// root.
+    pub ancestry: Vec<Op<A, T>>,
+    /// The author's ops newer than `since`, in log order.
+    pub ops: Vec<Op<A, T>>,
+}
+
+impl<A: Author, T: Clone> Chronofold<A, T> {
+    /// Exports the ops by `author` newer than `since`, along with their
+    /// ancestry.
+    pub fn export_contributions(&self, author: A, since: &Version<A>) -> ContributionBundle<A, T> {
+        let contributed = |idx: LocalIndex| {
+            self.timestamp(idx)
+                .is_some_and(|t| t.author == author && !since.contains(&t))
+        };
+
+        // References always precede their ops in the log, so a reverse pass
+        // collects all ancestors.
+        let mut needed = BTreeSet::new();
+        let mut ancestry = BTreeSet::new();
+        for idx in (0..self.log.len()).rev().map(LocalIndex) {
+            let is_contribution = contributed(idx);
+            if !is_contribution && !needed.contains(&idx) {
+                continue;
+            }
+            if !is_contribution {
+                ancestry.insert(idx);
+            }
+            needed.extend(self.get_reference(&idx));
+        }
+        ancestry.insert(self.root);
+
+        let mut bundle = ContributionBundle {
+            author: author.clone(),
+            since: since.clone(),
+            ancestry: Vec::new(),
+            ops: Vec::new(),
+        };
+        for op in self.iter_ops::<&T>(..) {
+            let idx = self.log_index(&op.id).expect("ops are in the log");
+            if ancestry.contains(&idx) {
+                bundle.ancestry.push(op.cloned());
+            } else if contributed(idx) {
+                bundle.ops.push(op.cloned());
+            }
+        }
+        bundle
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Imports the ops of a bundle exported by `export_contributions`.
+    ///
+    /// Ops that were applied before are skipped. See `apply_batch` for
+    /// details.
+    ///
+    /// The contributions may build on ops by other authors that aren't
+    /// needed to apply them, and aren't part of the bundle. Like for
+    /// baselines, these count as compacted, so the contributions' timestamps
+    /// aren't in the future and local ops stay newer than all others.
+    pub fn import_contributions<V>(
+        &mut self,
+        bundle: ContributionBundle<A, V>,
+    ) -> AppliedSummary<A, V>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        // Make room for all timestamps first, and shrink to the timestamps
+        // actually applied afterwards.
+        let compacted = self.compacted;
+        let first = self.log.len();
+        let newest = bundle
+            .ancestry
+            .iter()
+            .chain(&bundle.ops)
+            .map(|op| op.id.idx.0)
+            .max();
+        self.compacted = compacted.max(newest.unwrap_or(0));
+        let summary = self.apply_batch(bundle.ancestry.into_iter().chain(bundle.ops));
+
+        let len = self.log.len();
+        let shifts = (first..len).filter_map(|idx| {
+            let timestamp = self.timestamp(LocalIndex(idx))?;
+            Some(timestamp.idx.0.saturating_sub(idx))
+        });
+        let newest = self
+            .version
+            .iter()
+            .map(|timestamp| (timestamp.idx.0 + 1).saturating_sub(len));
+        self.compacted = shifts.chain(newest).fold(compacted, usize::max);
+        summary
+    }
+}
+
+impl<A: Author, T> ContributionBundle<A, T> {
+    /// Creates a new chronofold containing only the ops of the bundle.
+    ///
+    /// Timestamps are kept, so ops created on the new chronofold can be
+    /// exchanged with other replicas. Its version covers ops it doesn't
+    /// contain, though, so it can't catch up with full replicas by version.
+    ///
+    /// Returns `None` if the bundle doesn't start with a root or contains
+    /// rejected ops.
+    pub fn into_chronofold(self) -> Option<Chronofold<A, T>> {
+        let root = &self
+            .ancestry
+            .first()
+            .filter(|op| matches!(op.payload, OpPayload::Root))?
+            .id;
+        let mut chronofold = Chronofold::new(root.author.clone());
+        if !chronofold.import_contributions(self).rejected.is_empty() {
+            return None;
+        }
+        Some(chronofold)
+    }
+}
@@ -1,8 +1,8 @@
-use crate::{Author, Chronofold};
+use crate::{Author, Change, Chronofold, LocalIndex};
 
 use std::fmt;
 
-impl<A: Author, T: fmt::Display> fmt::Display for Chronofold<A, T> {
+impl<A, T: fmt::Display> fmt::Display for Chronofold<A, T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
@@ -12,3 +12,191 @@ impl<A: Author, T: fmt::Display> fmt::Display for Chronofold<A, T> {
         )
     }
 }
+
+/// Formats the log of a chronofold as a table.
+///
+/// This struct is created by the `log_table` method on `Chronofold`. See its
+/// documentation for more.
+pub struct LogTable<'a, A, T> {
+    cfold: &'a Chronofold<A, T>,
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns a formatter printing the log as a table like the ones in the
+    /// chronofold paper.
+    ///
+    /// There is one row per log entry, with its local index, value, author,
+    /// author index, next index and reference. Deletes are shown as `⌫`.
+    pub fn log_table(&self) -> LogTable<'_, A, T> {
+        LogTable { cfold: self }
+    }
+}
+
+impl<A: Author + fmt::Display, T: fmt::Debug> fmt::Display for LogTable<'_, A, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cfold = self.cfold;
+        let index_or_dash =
+            |idx: Option<LocalIndex>| idx.map_or("-".to_owned(), |idx| idx.to_string());
+        let mut rows = vec![["idx", "val", "author", "andx", "next", "ref"].map(str::to_owned)];
+        for (idx, change) in cfold.log.iter().enumerate() {
+            let idx = LocalIndex(idx);
+            let timestamp = cfold.timestamp(idx).expect("log entries have timestamps");
+            rows.push([
+                idx.to_string(),
+                match change {
+                    Change::Root => "root".to_owned(),
+                    Change::Insert(value) => format!("{:?}", value),
+                    Change::Delete => "⌫".to_owned(),
+                },
+                timestamp.author.to_string(),
+                timestamp.idx.to_string(),
+                index_or_dash(cfold.get_next_index(&idx)),
+                index_or_dash(cfold.get_reference(&idx)),
+            ]);
+        }
+
+        let mut widths = [0; 6];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = usize::max(*width, cell.chars().count());
+            }
+        }
+        for row in rows {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl<A: Author + fmt::Display, T: fmt::Debug> fmt::Debug for LogTable<'_, A, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Displays a chronofold including deleted elements.
+///
+/// This struct is created by the `display_with_tombstones` method on
+/// `Chronofold`. See its documentation for more.
+pub struct DisplayWithTombstones<'a, A, T> {
+    cfold: &'a Chronofold<A, T>,
+    open: &'a str,
+    close: &'a str,
+}
+
+impl<'a, A, T> DisplayWithTombstones<'a, A, T> {
+    /// Sets the markers surrounding runs of deleted elements.
+    pub fn with_markers(self, open: &'a str, close: &'a str) -> Self {
+        Self {
+            open,
+            close,
+            ..self
+        }
+    }
+}
+
+impl<A, T> Chronofold<A, T> {
+    /// Returns a wrapper displaying deleted elements inline.
+    ///
+    /// Runs of deleted elements are surrounded by `[-` and `-]` unless other
+    /// markers are set with `with_markers`.
+    pub fn display_with_tombstones(&self) -> DisplayWithTombstones<'_, A, T> {
+        DisplayWithTombstones {
+            cfold: self,
+            open: "[-",
+            close: "-]",
+        }
+    }
+}
+
+impl<A, T: fmt::Display> fmt::Display for DisplayWithTombstones<'_, A, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut in_deleted = false;
+        for (value, _, deleted) in self.cfold.iter_with_tombstones() {
+            if deleted != in_deleted {
+                f.write_str(if deleted { self.open } else { self.close })?;
+                in_deleted = deleted;
+            }
+            write!(f, "{}", value)?;
+        }
+        if in_deleted {
+            f.write_str(self.close)?;
+        }
+        Ok(())
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns an iterator over custom renderings of the visible elements in
+    /// causal order.
+    ///
+    /// `render` is called with each element, its log index and its author.
+    pub fn render_with<'a, F>(&'a self, mut render: F) -> impl Iterator<Item = String> + 'a
+    where
+        F: FnMut(&T, LocalIndex, A) -> String + 'a,
+    {
+        self.iter().map(move |(value, idx)| {
+            let author = self.get_author(&idx).expect("log entries have authors");
+            render(value, idx, author.clone())
+        })
+    }
+}
+
+impl<A: Author + fmt::Display> Chronofold<A, char> {
+    /// Renders the text as HTML, wrapping runs by the same author in
+    /// `<span data-author="…">`.
+    pub fn to_html(&self) -> String {
+        self.render_html(false)
+    }
+
+    /// Like `to_html`, but also includes deleted runs as
+    /// `<del data-author="…">`.
+    pub fn to_html_with_deletions(&self) -> String {
+        self.render_html(true)
+    }
+
+    fn render_html(&self, deletions: bool) -> String {
+        let mut html = String::new();
+        let mut current: Option<(&A, bool)> = None;
+        for (c, idx, deleted) in self.iter_with_tombstones() {
+            if deleted && !deletions {
+                continue;
+            }
+            let author = self.get_author(&idx).expect("log entries have authors");
+            if current != Some((author, deleted)) {
+                if let Some((_, was_deleted)) = current {
+                    html.push_str(if was_deleted { "</del>" } else { "</span>" });
+                }
+                let tag = if deleted { "del" } else { "span" };
+                html.push_str(&format!("<{} data-author=\"", tag));
+                push_escaped(&mut html, &author.to_string());
+                html.push_str("\">");
+                current = Some((author, deleted));
+            }
+            push_escaped(&mut html, c.encode_utf8(&mut [0; 4]));
+        }
+        if let Some((_, deleted)) = current {
+            html.push_str(if deleted { "</del>" } else { "</span>" });
+        }
+        html
+    }
+}
+
+fn push_escaped(html: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+}
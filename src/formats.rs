@@ -0,0 +1,75 @@
+//! Helpers for binary serialization formats.
+//!
+//! These are thin wrappers around the respective serde implementations, but
+//! unlike using those directly, the round trip of this crate's types through
+//! each format is covered by our tests.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::ErrorCategory;
+
+/// Represents errors that can occur when encoding or decoding a binary
+/// format.
+#[derive(Debug)]
+pub struct FormatError {
+    format: &'static str,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl FormatError {
+    fn new(format: &'static str, source: impl Error + Send + Sync + 'static) -> Self {
+        Self {
+            format,
+            source: Box::new(source),
+        }
+    }
+}
+
+impl FormatError {
+    /// Returns the category of this error, which is always `Integrity`.
+    pub fn category(&self) -> ErrorCategory {
+        ErrorCategory::Integrity
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} error: {}", self.format, self.source)
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// Serializes `value` as CBOR.
+#[cfg(feature = "cbor")]
+pub fn to_cbor_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, FormatError> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(value, &mut bytes).map_err(|e| FormatError::new("CBOR", e))?;
+    Ok(bytes)
+}
+
+/// Deserializes a value from CBOR.
+#[cfg(feature = "cbor")]
+pub fn from_cbor_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, FormatError> {
+    ciborium::de::from_reader(bytes).map_err(|e| FormatError::new("CBOR", e))
+}
+
+/// Serializes `value` as MessagePack.
+#[cfg(feature = "msgpack")]
+pub fn to_msgpack_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, FormatError> {
+    rmp_serde::to_vec(value).map_err(|e| FormatError::new("MessagePack", e))
+}
+
+/// Deserializes a value from MessagePack.
+#[cfg(feature = "msgpack")]
+pub fn from_msgpack_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, FormatError> {
+    rmp_serde::from_slice(bytes).map_err(|e| FormatError::new("MessagePack", e))
+}
@@ -0,0 +1,104 @@
+use std::hash::{Hash, Hasher};
+
+use crate::{Author, Chronofold};
+
+impl<A: Author + Hash, T: Hash> Chronofold<A, T> {
+    /// Returns a hash of the visible elements.
+    ///
+    /// Replicas with the same visible content produce the same hash, even if
+    /// their histories differ. See `history_hash` for a stricter check.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = StableHasher::default();
+        for value in self.iter_elements() {
+            value.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Returns a hash of the complete history, including deleted elements.
+    ///
+    /// Ops are hashed in timestamp order, so replicas that applied the same
+    /// ops in a different order still produce the same hash.
+    pub fn history_hash(&self) -> u64 {
+        let mut hasher = StableHasher::default();
+        self.canonical_ops().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// A 64-bit FNV-1a hasher.
+///
+/// Unlike `DefaultHasher` its output doesn't depend on the Rust version or the
+/// platform, so hashes can be compared between replicas.
+pub(crate) struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a() {
+        let mut hasher = StableHasher::default();
+        hasher.write(b"a");
+        assert_eq!(0xaf63_dc4c_8601_ec8c, hasher.finish());
+    }
+}
@@ -0,0 +1,168 @@
+//! Binding authors to public keys, for verifying signed ops.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    Author, AuthorIndex, Chronofold, ChronofoldError, IntoLocalValue, Op, Timestamp, VerifyError,
+};
+
+/// Verifies signatures of ops.
+///
+/// The crate doesn't ship a signature scheme. Implementations wrap one (e.g.
+/// Ed25519) and decide how ops are encoded for signing.
+pub trait SignatureScheme<A, T> {
+    type PublicKey;
+
+    /// Returns `true` if `signature` is a valid signature of `op` for `key`.
+    fn verify(&self, key: &Self::PublicKey, op: &Op<A, T>, signature: &[u8]) -> bool;
+}
+
+/// An op together with its author's signature.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SignedOp<A, T> {
+    pub op: Op<A, T>,
+    pub signature: Vec<u8>,
+}
+
+impl<A, T> SignedOp<A, T> {
+    pub fn new(op: Op<A, T>, signature: Vec<u8>) -> Self {
+        Self { op, signature }
+    }
+}
+
+/// The public keys of authors.
+///
+/// Each of an author's keys is valid for the author's ops from some author
+/// index on, until the next key takes over. Rotating keys therefore keeps
+/// older ops verifiable, while revoking a key rejects all ops signed with it.
+#[derive(Clone, Debug)]
+pub struct AuthorKeys<A, K> {
+    keys: BTreeMap<A, Vec<AuthorKey<K>>>,
+}
+
+#[derive(Clone, Debug)]
+struct AuthorKey<K> {
+    key: K,
+    valid_from: AuthorIndex,
+    revoked: bool,
+}
+
+impl<A, K> Default for AuthorKeys<A, K> {
+    fn default() -> Self {
+        Self {
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl<A: Author, K> AuthorKeys<A, K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the first key of `author`, valid for all their ops.
+    ///
+    /// Returns `false` if the author already has a key, use `rotate` to
+    /// replace it.
+    pub fn register(&mut self, author: A, key: K) -> bool {
+        if self.keys.contains_key(&author) {
+            return false;
+        }
+        self.keys
+            .insert(author, vec![AuthorKey::new(key, AuthorIndex(0))]);
+        true
+    }
+
+    /// Replaces the current key of `author` for ops from author index `from`
+    /// on.
+    ///
+    /// Returns `false` if the author has no key yet or `from` doesn't come
+    /// after the current key's first op.
+    pub fn rotate(&mut self, author: A, key: K, from: AuthorIndex) -> bool {
+        match self.keys.get_mut(&author) {
+            Some(keys) if keys.last().is_some_and(|last| last.valid_from < from) => {
+                keys.push(AuthorKey::new(key, from));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Revokes `key` of `author`, so ops signed with it don't verify anymore.
+    ///
+    /// Returns `false` if the key isn't registered for the author.
+    pub fn revoke(&mut self, author: &A, key: &K) -> bool
+    where
+        K: PartialEq,
+    {
+        let entry = self
+            .keys
+            .get_mut(author)
+            .and_then(|keys| keys.iter_mut().find(|entry| entry.key == *key));
+        match entry {
+            Some(entry) => {
+                entry.revoked = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the key the op with the given id has to be signed with, or
+    /// `None` if the author is unknown or the key was revoked.
+    pub fn key_for(&self, id: &Timestamp<A>) -> Option<&K> {
+        self.keys
+            .get(&id.author)?
+            .iter()
+            .rev()
+            .find(|entry| entry.valid_from <= id.idx)
+            .filter(|entry| !entry.revoked)
+            .map(|entry| &entry.key)
+    }
+
+    /// Checks the signature of `signed` against its author's key.
+    pub fn verify<T, S>(&self, scheme: &S, signed: &SignedOp<A, T>) -> Result<(), VerifyError>
+    where
+        S: SignatureScheme<A, T, PublicKey = K>,
+    {
+        let key = self.key_for(&signed.op.id).ok_or(VerifyError::NoKey)?;
+        if scheme.verify(key, &signed.op, &signed.signature) {
+            Ok(())
+        } else {
+            Err(VerifyError::InvalidSignature)
+        }
+    }
+}
+
+impl<K> AuthorKey<K> {
+    fn new(key: K, valid_from: AuthorIndex) -> Self {
+        Self {
+            key,
+            valid_from,
+            revoked: false,
+        }
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Applies a signed op, if its signature verifies with the author's key.
+    ///
+    /// Ops failing verification are rejected with
+    /// `ChronofoldError::Unverified`.
+    pub fn apply_signed<V, S>(
+        &mut self,
+        signed: SignedOp<A, V>,
+        keys: &AuthorKeys<A, S::PublicKey>,
+        scheme: &S,
+    ) -> Result<(), ChronofoldError<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+        S: SignatureScheme<A, V>,
+    {
+        if let Err(err) = keys.verify(scheme, &signed) {
+            return Err(ChronofoldError::Unverified(signed.op, err));
+        }
+        self.apply(signed.op)
+    }
+}
@@ -1,8 +1,9 @@
+use std::error::Error;
 use std::fmt;
 use std::ops::{Add, Index, Sub};
 
 use crate::offsetmap::Offset;
-use crate::{Author, Change, Chronofold};
+use crate::{Change, Chronofold};
 
 /// An index in the log of the chronofold.
 ///
@@ -15,6 +16,12 @@ pub struct LocalIndex(pub usize);
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AuthorIndex(pub usize);
 
+/// The largest log index.
+///
+/// `Costructures` packs the kind of co-structure into the two most
+/// significant bits of its keys, so log indices have to fit into the rest.
+pub(crate) const MAX_LOG_INDEX: usize = usize::MAX >> 2;
+
 pub trait LogIndex: fmt::Display + Copy {
     fn index(&self) -> usize;
 
@@ -39,13 +46,35 @@ macro_rules! impl_for_log_index {
                 write!(f, "{}", self.0)
             }
         }
+
+        impl $type {
+            /// Adds `rhs`, returning `None` on overflow.
+            pub fn checked_add(self, rhs: usize) -> Option<Self> {
+                self.0.checked_add(rhs).map($type)
+            }
+
+            /// Subtracts `rhs`, returning `None` on underflow.
+            pub fn checked_sub(self, rhs: usize) -> Option<Self> {
+                self.0.checked_sub(rhs).map($type)
+            }
+
+            /// Adds `rhs`, saturating at the maximum index.
+            pub fn saturating_add(self, rhs: usize) -> Self {
+                $type(self.0.saturating_add(rhs))
+            }
+
+            /// Subtracts `rhs`, saturating at zero.
+            pub fn saturating_sub(self, rhs: usize) -> Self {
+                $type(self.0.saturating_sub(rhs))
+            }
+        }
     };
 }
 
 impl_for_log_index!(LocalIndex);
 impl_for_log_index!(AuthorIndex);
 
-impl<A: Author, T> Index<LocalIndex> for Chronofold<A, T> {
+impl<A, T> Index<LocalIndex> for Chronofold<A, T> {
     type Output = Change<T>;
 
     fn index(&self, index: LocalIndex) -> &Self::Output {
@@ -53,7 +82,7 @@ impl<A: Author, T> Index<LocalIndex> for Chronofold<A, T> {
     }
 }
 
-impl<A: Author, T> Chronofold<A, T> {
+impl<A, T> Chronofold<A, T> {
     /// Returns the index of the last log entry (in log order).
     pub fn last_index(&self) -> Option<LocalIndex> {
         if !self.log.is_empty() {
@@ -70,12 +99,8 @@ impl<A: Author, T> Chronofold<A, T> {
     ///   1. `index` is the first index (causal order).
     ///   2. `index` is out of bounds.
     pub(crate) fn index_before(&self, index: LocalIndex) -> Option<LocalIndex> {
-        if matches!(self.log.get(index.0), Some(Change::Root)) {
-            Some(index)
-        } else if let Some(reference) = self.references.get(&index) {
-            self.iter_log_indices_causal_range(reference..index)
-                .map(|(_, idx)| idx)
-                .last()
+        if index.0 < self.log.len() {
+            self.costructures.get_previous_index(&index)
         } else {
             None
         }
@@ -92,15 +117,49 @@ impl<A: Author, T> Chronofold<A, T> {
     }
 }
 
+/// A relative index points outside of the range of log indices.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) struct OffsetOverflow;
+
+impl fmt::Display for OffsetOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "relative index out of range")
+    }
+}
+
+impl Error for OffsetOverflow {}
+
 macro_rules! impl_for_offset {
     ($type:ident) => {
         impl Offset<LocalIndex> for $type {
-            fn add(&self, value: &LocalIndex) -> LocalIndex {
-                LocalIndex((value.0 as isize + self.0) as usize)
+            fn checked_add(&self, value: &LocalIndex) -> Result<LocalIndex, OffsetOverflow> {
+                value
+                    .0
+                    .checked_add_signed(self.0)
+                    .filter(|idx| *idx <= MAX_LOG_INDEX)
+                    .map(LocalIndex)
+                    .ok_or(OffsetOverflow)
             }
 
-            fn sub(a: &LocalIndex, b: &LocalIndex) -> Self {
-                $type(a.0 as isize - b.0 as isize)
+            fn checked_sub(a: &LocalIndex, b: &LocalIndex) -> Result<Self, OffsetOverflow> {
+                // As both indices are at most `MAX_LOG_INDEX`, their
+                // difference always fits into `isize`.
+                if a.0 > MAX_LOG_INDEX || b.0 > MAX_LOG_INDEX {
+                    return Err(OffsetOverflow);
+                }
+                Ok($type(a.0 as isize - b.0 as isize))
+            }
+        }
+
+        impl $type {
+            /// Encodes the offset as `usize`, in two's complement.
+            pub(crate) fn to_bits(self) -> usize {
+                self.0 as usize
+            }
+
+            /// Decodes an offset encoded by `to_bits`.
+            pub(crate) fn from_bits(bits: usize) -> Self {
+                $type(bits as isize)
             }
         }
     };
@@ -130,16 +189,30 @@ impl Default for RelativeReference {
 
 impl_for_offset!(RelativeReference);
 
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub(crate) struct RelativePreviousIndex(pub isize);
+
+impl Default for RelativePreviousIndex {
+    fn default() -> Self {
+        RelativePreviousIndex(-1)
+    }
+}
+
+impl_for_offset!(RelativePreviousIndex);
+
+/// The difference between a change's log index and its author index.
+///
+/// After compaction, log indices can be smaller than author indices. The
+/// shift is negative then, which is represented by wrapping around.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(crate) struct IndexShift(pub usize);
 
-
 impl Add<&IndexShift> for &LocalIndex {
     type Output = LocalIndex;
 
     fn add(self, other: &IndexShift) -> LocalIndex {
-        LocalIndex(self.0 + other.0)
+        LocalIndex(self.0.wrapping_add(other.0))
     }
 }
 
@@ -147,11 +220,6 @@ impl Sub<&IndexShift> for &LocalIndex {
     type Output = AuthorIndex;
 
     fn sub(self, other: &IndexShift) -> Self::Output {
-        AuthorIndex(self.0 - other.0)
+        AuthorIndex(self.0.wrapping_sub(other.0))
     }
 }
-
-// TODO: Does it make sense to introduce a `Position` type for indexing into
-// the chronofold? This would be slower as we have to access the nth element of
-// the linked list. If we do so, we should return `(LogIndex, T)` to allow
-// editing of the accessed value.
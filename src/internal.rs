@@ -1,7 +1,8 @@
 use crate::index::{IndexShift, RelativeNextIndex};
 use crate::offsetmap::Offset;
-use crate::{Author, Change, Chronofold, LocalIndex, Timestamp, AuthorIndex};
+use crate::{Author, AuthorIndex, Change, Chronofold, LocalIndex, Timestamp};
 
+use std::cmp::Ordering;
 use std::matches;
 
 impl<A: Author, T> Chronofold<A, T> {
@@ -9,15 +10,41 @@ impl<A: Author, T> Chronofold<A, T> {
         LocalIndex(self.log.len())
     }
 
+    /// Returns the author index of a local change at log index `index`.
+    ///
+    /// This equals the log index, unless entries were removed by compaction.
+    pub(crate) fn local_author_index(&self, index: LocalIndex) -> AuthorIndex {
+        AuthorIndex(index.0 + self.compacted)
+    }
+
+    /// Returns the index shift of local changes.
+    pub(crate) fn local_index_shift(&self) -> IndexShift {
+        IndexShift(0usize.wrapping_sub(self.compacted))
+    }
+
     /// find the would-be reference for this change to be inserted
     pub(crate) fn find_predecessor(
         &self,
-        id: Timestamp<A>,
+        id: &Timestamp<A>,
         reference: Option<LocalIndex>,
         change: &Change<T>,
     ) -> Option<LocalIndex> {
         match (reference, change) {
-            (_, Change::Delete) => reference, // deletes have priority
+            (None, Change::Delete) => reference,
+            (Some(reference), Change::Delete) => {
+                // Deletes are placed before other siblings. Concurrent deletes
+                // of the same change are ordered by their timestamps, so all
+                // replicas agree on the order of their subtrees. Only the
+                // first delete directly follows the deleted change, later
+                // ones follow the subtrees of earlier ones.
+                self.iter_log_indices_causal_range(reference..)
+                    .filter(|(_, i)| self.get_reference(i) == Some(reference))
+                    .take_while(|(c, i)| {
+                        matches!(c, Change::Delete) && self.timestamp(*i).unwrap() > *id
+                    })
+                    .last()
+                    .map_or_else(|| Some(reference), |(_, idx)| self.iter_subtree(idx).last())
+            }
             (None, Change::Root) => reference,
             (_, Change::Root) => {
                 // Roots cannot reference other entries.
@@ -28,13 +55,13 @@ impl<A: Author, T> Chronofold<A, T> {
                 self.iter_log_indices_causal_range(reference..)
                     // finding preemptive siblings
                     .filter(|(_, i)| self.get_reference(i) == Some(reference))
-                    .filter(|(c, i)|
-                        matches!(c, Change::Delete) || self.timestamp(*i).unwrap() > id
-                    )
+                    .filter(|(c, i)| {
+                        matches!(c, Change::Delete)
+                            || self.cmp_siblings(&self.timestamp(*i).unwrap(), id)
+                                == Ordering::Greater
+                    })
                     .last()
-                    .map_or_else(|| Some(reference),
-                                 |(_, idx)| self.iter_subtree(idx).last(),
-                    )
+                    .map_or_else(|| Some(reference), |(_, idx)| self.iter_subtree(idx).last())
             }
             (None, _change) => {
                 // Non-roots have to reference another entry.
@@ -50,8 +77,15 @@ impl<A: Author, T> Chronofold<A, T> {
         reference: Option<LocalIndex>,
         change: Change<T>,
     ) -> LocalIndex {
+        self.prepare_visibility();
+        self.invalidate_materialized();
+
+        let deletes_element = reference.is_some_and(|idx| self.is_visible_element(idx));
+        self.count_change(&change, deletes_element);
+        self.count_contribution(&id.author, &change, reference.filter(|_| deletes_element));
+
         // Find the predecessor to `op`.
-        let predecessor = self.find_predecessor(id, reference, &change);
+        let predecessor = self.find_predecessor(&id, reference, &change);
 
         // Set the predecessor's next index to our new change's index while
         // keeping its previous next index for ourselves.
@@ -66,13 +100,26 @@ impl<A: Author, T> Chronofold<A, T> {
 
         // Append to the chronofold's log and secondary logs.
         self.log.push(change);
+        self.index_timestamp(id.clone(), new_index);
+        if predecessor.is_none() {
+            self.costructures.set_previous_index(new_index, None);
+        }
         self.set_next_index(new_index, next_index);
-        self.set_author(new_index, id.author);
-        self.set_index_shift(new_index, IndexShift(new_index.0 - (id.idx).0));
+        self.set_author(new_index, id.author.clone());
+        self.set_index_shift(new_index, IndexShift(new_index.0.wrapping_sub(id.idx.0)));
         self.set_reference(new_index, reference);
+        if let Some(predecessor) = predecessor {
+            self.link_visibility(predecessor, new_index, new_index);
+        }
 
-        // Increment version.
-        self.version.inc(&id);
+        self.extend_depths();
+
+        // Increment version, unless a remote batch does so when it ends.
+        if self.remote_batch.is_none() {
+            self.version.inc(&id);
+        }
+        self.version_changed();
+        self.notify_change(new_index, deletes_element);
 
         new_index
     }
@@ -81,54 +128,191 @@ impl<A: Author, T> Chronofold<A, T> {
     ///
     /// For local changes the following optimizations can be applied:
     /// - id equals (log index, author)
-    /// - predecessor always equals reference (no preemptive siblings)
+    /// - predecessor always equals reference (no preemptive siblings), as
+    ///   long as the default `TimestampOrder` is used
     /// - next index has to be set only for the first and the last change
     pub(crate) fn apply_local_changes(
         &mut self,
         author: A,
         reference: LocalIndex,
         changes: impl IntoIterator<Item = Change<T>>,
-    ) -> Option<LocalIndex>
-    {
+    ) -> Option<LocalIndex> {
+        self.apply_local_changes_after(author, reference, false, changes)
+    }
+
+    /// Applies consecutive local changes after the last log entry.
+    ///
+    /// As nothing can reference the last log entry yet, there are no deletes
+    /// or preemptive siblings to skip, so the changes are simply appended.
+    pub(crate) fn append_local_changes(
+        &mut self,
+        author: A,
+        changes: impl IntoIterator<Item = Change<T>>,
+    ) -> Option<LocalIndex> {
+        let reference = self.last_index()?;
+        self.apply_local_changes_after(author, reference, true, changes)
+    }
+
+    fn apply_local_changes_after(
+        &mut self,
+        author: A,
+        reference: LocalIndex,
+        appending: bool,
+        changes: impl IntoIterator<Item = Change<T>>,
+    ) -> Option<LocalIndex> {
         let mut last_id = None;
         let mut last_next_index = None;
+        let mut first = None;
+        self.prepare_visibility();
+        self.invalidate_materialized();
 
-        let mut predecessor = self.find_last_delete(reference).unwrap_or(reference);
+        let reference = if appending {
+            reference
+        } else {
+            self.find_last_delete(reference).unwrap_or(reference)
+        };
+        let mut predecessor = reference;
 
         let mut changes = changes.into_iter();
         if let Some(first_change) = changes.next() {
             let new_index = LocalIndex(self.log.len());
-            let id = Timestamp::new(AuthorIndex(new_index.0), author);
-            last_id = Some(id);
+            let id = Timestamp::new(self.local_author_index(new_index), author.clone());
+
+            // With a custom sibling order, even local changes might have to
+            // be placed after preemptive siblings.
+            if !appending && self.policies.sibling_order.is_some() {
+                predecessor = self
+                    .find_predecessor(&id, Some(reference), &first_change)
+                    .unwrap_or(reference);
+            }
 
             // Set the predecessors next index to our new change's index while
             // keeping it's previous next index for ourselves.
             last_next_index = self.get_next_index(&predecessor);
             self.set_next_index(predecessor, Some(new_index));
 
+            // The reference is the element's last delete, if there is one.
+            let deletes_element = matches!(self.log[reference.0], Change::Insert(_));
+            self.count_change(&first_change, deletes_element);
+            self.count_contribution(
+                &author,
+                &first_change,
+                Some(reference).filter(|_| deletes_element),
+            );
             self.log.push(first_change);
-            self.set_author(new_index, author);
-            self.set_index_shift(new_index, IndexShift(0));
-            self.set_reference(new_index, Some(predecessor));
+            self.index_timestamp(id.clone(), new_index);
+            self.set_author(new_index, author.clone());
+            last_id = Some(id);
+            self.set_index_shift(new_index, self.local_index_shift());
+            self.set_reference(new_index, Some(reference));
 
+            first = Some((predecessor, new_index));
             predecessor = new_index;
         }
 
         for change in changes {
             let new_index = RelativeNextIndex::default().add(&predecessor);
-            let id = Timestamp::new(AuthorIndex(new_index.0), author);
-            last_id = Some(id);
+            let id = Timestamp::new(self.local_author_index(new_index), author.clone());
 
             // Append to the chronofold's log and secondary logs.
+            let deletes_element = matches!(self.log[predecessor.0], Change::Insert(_));
+            self.count_change(&change, deletes_element);
+            self.count_contribution(
+                &author,
+                &change,
+                Some(predecessor).filter(|_| deletes_element),
+            );
             self.log.push(change);
+            self.index_timestamp(id.clone(), new_index);
+            last_id = Some(id);
 
             predecessor = new_index;
         }
-        
+
         let id = last_id?;
-        self.set_next_index(LocalIndex(id.idx.0), last_next_index);
+        self.set_next_index(predecessor, last_next_index);
+        if let Some((first_predecessor, first_index)) = first {
+            self.link_visibility(first_predecessor, first_index, predecessor);
+        }
+        self.extend_depths();
         self.version.inc(&id);
-        Some(LocalIndex(id.idx.0))
+        self.version_changed();
+        if let Some((_, first_index)) = first {
+            // Deletes in a chain hide the element they reference, if any.
+            for idx in (first_index.0..=predecessor.0).map(LocalIndex) {
+                let reference = self
+                    .get_reference(&idx)
+                    .expect("local changes have references");
+                self.notify_change(idx, matches!(self.log[reference.0], Change::Insert(_)));
+            }
+        }
+        Some(predecessor)
+    }
+
+    /// Removes the last log entry, if it is an element inserted locally by
+    /// `author` at log index `index`, and returns its reference.
+    ///
+    /// This undoes `apply_local_changes` for that element, as if it had never
+    /// been inserted.
+    pub(crate) fn cancel_last_insert(
+        &mut self,
+        author: A,
+        index: LocalIndex,
+    ) -> Option<LocalIndex> {
+        if index.0 + 1 != self.log.len()
+            || !matches!(self.log[index.0], Change::Insert(_))
+            || self.timestamp(index).as_ref()
+                != Some(&Timestamp::new(
+                    self.local_author_index(index),
+                    author.clone(),
+                ))
+            || self.version.get(&author) != Some(self.local_author_index(index))
+        {
+            return None;
+        }
+        let reference = self.get_reference(&index)?;
+
+        // Reconnect the element's predecessor with its successor.
+        let predecessor = self.index_before(index)?;
+        self.prepare_visibility();
+        // The log index is reused, so chunks of the last snapshot can't be.
+        self.clear_materialized();
+        self.notify_removal(index, author.clone());
+        let next_index = self.get_next_index(&index);
+        self.set_next_index(predecessor, next_index);
+        self.unlink_visibility(predecessor, index);
+
+        self.unindex_timestamp(&Timestamp::new(
+            self.local_author_index(index),
+            author.clone(),
+        ));
+        self.log.pop();
+        if let Some(len) = &mut self.len {
+            *len -= 1;
+        }
+        if let Some(contribution) = self
+            .contributions
+            .get_mut()
+            .and_then(|c| c.get_mut(&author))
+        {
+            contribution.inserted -= 1;
+            contribution.visible -= 1;
+        }
+        self.costructures.truncate(index);
+        self.depths.truncate(index.0);
+
+        // Restore the author's version from their previous change, if any.
+        let previous = (0..index.0)
+            .rev()
+            .map(LocalIndex)
+            .find(|idx| self.get_author(idx) == Some(&author))
+            .and_then(|idx| self.timestamp(idx));
+        self.version.reset(&author, previous);
+        // The observer may have reported the cancelled insert, and the next
+        // local change reuses its author index.
+        self.version_changed();
+
+        Some(reference)
     }
 
     pub(crate) fn find_last_delete(&self, reference: LocalIndex) -> Option<LocalIndex> {
@@ -1,19 +1,23 @@
 use std::collections::HashSet;
+use std::iter;
 use std::marker::PhantomData;
 use std::matches;
+use std::mem;
 use std::ops::{Bound, Range, RangeBounds};
 
 use crate::{Author, Change, Chronofold, FromLocalValue, LocalIndex, Op, OpPayload};
 
-impl<A: Author, T> Chronofold<A, T> {
+impl<A, T> Chronofold<A, T> {
     /// Returns an iterator over the log indices in causal order.
     ///
     /// Use this function to find preemptive siblings
     ///
     /// TODO: The name is a bit unwieldy. I'm reluctant to add it to the public
     /// API before giving it more thought.
-    pub(crate) fn iter_log_indices_causal_range(&self, range: impl RangeBounds<LocalIndex>) -> CausalIter<'_, A, T>
-    {
+    pub(crate) fn iter_log_indices_causal_range(
+        &self,
+        range: impl RangeBounds<LocalIndex>,
+    ) -> CausalIter<'_, A, T> {
         let mut current = match range.start_bound() {
             Bound::Unbounded => self.index_after(self.root),
             Bound::Included(idx) => Some(*idx),
@@ -31,41 +35,89 @@ impl<A: Author, T> Chronofold<A, T> {
             cfold: self,
             current,
             first_excluded,
+            back: None,
         }
     }
 
     /// Returns an iterator over a subtree.
     ///
-    /// The first item is always `root`.
+    /// The first item is always `root`. Causal order is a depth-first
+    /// traversal of the causal tree, so the subtree ends at the first entry
+    /// outside of it.
     pub(crate) fn iter_subtree(&self, root: LocalIndex) -> impl Iterator<Item = LocalIndex> + '_ {
-        let mut subtree: HashSet<LogIndex> = HashSet::new();
-        self.iter_log_indices_causal_range(root..)
-            .filter_map(move |(_, idx)| {
-                if idx == root || subtree.contains(&self.references.get(&idx)?) {
-                    subtree.insert(idx);
-                    Some(idx)
-                } else {
-                    None
+        let mut subtree: HashSet<LocalIndex> = HashSet::new();
+        iter::once(root)
+            .chain(
+                self.iter_log_indices_causal_range((Bound::Excluded(root), Bound::Unbounded))
+                    .map(|(_, idx)| idx),
+            )
+            .take_while(move |idx| {
+                let in_subtree = *idx == root
+                    || self
+                        .get_reference(idx)
+                        .is_some_and(|reference| subtree.contains(&reference));
+                if in_subtree {
+                    subtree.insert(*idx);
                 }
+                in_subtree
             })
     }
 
     /// Returns an iterator over elements and their log indices in causal order.
-    pub fn iter(&self) -> Iter<A, T> {
+    pub fn iter(&self) -> Iter<'_, A, T> {
         self.iter_range(..)
     }
 
+    /// Returns an iterator over elements and their log indices in reverse
+    /// causal order.
+    pub fn iter_rev(&self) -> impl Iterator<Item = (&T, LocalIndex)> {
+        let mut causal_iter = self.iter_log_indices_causal_range(..);
+        let mut following: Option<LocalIndex> = None;
+        iter::from_fn(move || loop {
+            let (change, idx) = causal_iter.next_back()?;
+            // An element's first delete directly follows it.
+            let next = following.replace(idx);
+            if let Change::Insert(value) = change {
+                if !next.is_some_and(|next| self.is_delete_of(next, idx)) {
+                    break Some((value, idx));
+                }
+            }
+        })
+    }
+
     /// Returns an iterator over elements and their log indices in causal order.
-    pub fn iter_range(&self, range: impl RangeBounds<LocalIndex>) -> Iter<A, T>
-    {
+    pub fn iter_range(&self, range: impl RangeBounds<LocalIndex>) -> Iter<'_, A, T> {
         let mut causal_iter = self.iter_log_indices_causal_range(range);
         let current = causal_iter.next();
+        // Runs of invisible entries can only be skipped as a whole if none of
+        // them is cut off by the end of the range.
+        let skip_runs = causal_iter
+            .first_excluded
+            .is_none_or(|idx| !self.is_invisible(idx));
         Iter {
             causal_iter,
             current,
+            skip_runs,
         }
     }
 
+    /// Returns an iterator over all elements, including deleted ones, in
+    /// causal order.
+    ///
+    /// Items are the element, its log index and whether it is deleted.
+    pub(crate) fn iter_with_tombstones(&self) -> impl Iterator<Item = (&T, LocalIndex, bool)> {
+        let mut causal_iter = self.iter_log_indices_causal_range(..).peekable();
+        std::iter::from_fn(move || loop {
+            if let (Change::Insert(value), idx) = causal_iter.next()? {
+                // An element's first delete directly follows it.
+                let deleted = causal_iter
+                    .peek()
+                    .is_some_and(|(_, next)| self.is_delete_of(*next, idx));
+                break Some((value, idx, deleted));
+            }
+        })
+    }
+
     /// Returns an iterator over elements in causal order.
     pub fn iter_elements(&self) -> impl Iterator<Item = &T> {
         self.iter().map(|(v, _)| v)
@@ -102,19 +154,39 @@ impl<A: Author, T> Chronofold<A, T> {
     }
 }
 
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns all ops in timestamp order.
+    ///
+    /// Timestamp order is a causal order: An op's author index is always
+    /// greater than the author index of the op it references. Replicas that
+    /// applied the same ops in a different order return the same list.
+    pub fn canonical_ops(&self) -> Vec<Op<A, &T>> {
+        let mut ops: Vec<Op<A, &T>> = self.iter_ops(..).collect();
+        ops.sort_by(|a, b| a.id.cmp(&b.id));
+        ops
+    }
+}
+
 pub(crate) struct CausalIter<'a, A, T> {
     cfold: &'a Chronofold<A, T>,
     current: Option<LocalIndex>,
     first_excluded: Option<LocalIndex>,
+    /// The next index from the back, found on the first call to
+    /// `next_back`.
+    back: Option<Option<LocalIndex>>,
 }
 
-impl<'a, A: Author, T> Iterator for CausalIter<'a, A, T> {
+impl<'a, A, T> Iterator for CausalIter<'a, A, T> {
     type Item = (&'a Change<T>, LocalIndex);
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.current.take() {
             Some(current) if Some(current) != self.first_excluded => {
-                self.current = self.cfold.index_after(current);
+                if self.back == Some(Some(current)) {
+                    self.back = Some(None);
+                } else {
+                    self.current = self.cfold.index_after(current);
+                }
                 Some((&self.cfold.log[current.0], current))
             }
             _ => None,
@@ -122,6 +194,37 @@ impl<'a, A: Author, T> Iterator for CausalIter<'a, A, T> {
     }
 }
 
+impl<A, T> DoubleEndedIterator for CausalIter<'_, A, T> {
+    /// Returns the previous item from the back.
+    ///
+    /// Without an end bound, the first call walks to the last index.
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let back = match self.back {
+            Some(back) => back,
+            None => self.last_index(),
+        }?;
+        if Some(back) == self.current {
+            self.current = None;
+            self.back = Some(None);
+        } else {
+            self.back = Some(self.cfold.index_before(back));
+        }
+        Some((&self.cfold.log[back.0], back))
+    }
+}
+
+impl<A, T> CausalIter<'_, A, T> {
+    /// Returns the last index of the remaining range.
+    fn last_index(&self) -> Option<LocalIndex> {
+        let current = self.current?;
+        match self.first_excluded {
+            Some(first_excluded) if first_excluded == current => None,
+            Some(first_excluded) => self.cfold.index_before(first_excluded),
+            None => iter::successors(Some(current), |idx| self.cfold.index_after(*idx)).last(),
+        }
+    }
+}
+
 /// An iterator over the elements of a chronofold.
 ///
 /// This struct is created by the `iter` and `iter_range` methods on
@@ -129,33 +232,102 @@ impl<'a, A: Author, T> Iterator for CausalIter<'a, A, T> {
 pub struct Iter<'a, A, T> {
     causal_iter: CausalIter<'a, A, T>,
     current: Option<(&'a Change<T>, LocalIndex)>,
+    skip_runs: bool,
 }
 
-impl<'a, A: Author, T> Iterator for Iter<'a, A, T> {
+impl<'a, A, T> Iterator for Iter<'a, A, T> {
     type Item = (&'a T, LocalIndex);
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let (skipped, next) =
-                skip_while(&mut self.causal_iter, |(c, _)| matches!(c, Change::Delete));
-            if skipped == 0 {
-                // the current item is not deleted
-                break match self.current.take() {
-                    None => None,
-                    Some((Change::Insert(v), idx)) => {
-                        self.current = next;
-                        Some((v, idx))
-                    }
-                    _ => unreachable!(),
+            if let Some(end) = self.run_end() {
+                self.causal_iter.current = self.causal_iter.cfold.index_after(end);
+                self.current = self.causal_iter.next();
+                continue;
+            }
+            let mut first_delete = None;
+            let (_, next) = skip_while(&mut self.causal_iter, |(c, idx)| {
+                let is_delete = matches!(c, Change::Delete);
+                if is_delete && first_delete.is_none() {
+                    first_delete = Some(*idx);
+                }
+                is_delete
+            });
+            let cfold = self.causal_iter.cfold;
+            match mem::replace(&mut self.current, next) {
+                None => break None,
+                // The current item is deleted if the deletes following it
+                // start with one of its own.
+                Some((Change::Insert(v), idx))
+                    if !first_delete.is_some_and(|delete| cfold.is_delete_of(delete, idx)) =>
+                {
+                    break Some((v, idx))
                 }
-            } else {
-                // the current item is deleted
-                self.current = next;
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn count(self) -> usize {
+        match self.positions() {
+            Some((start, end)) => end - start,
+            None => self.fold(0, |count, _| count + 1),
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let (start, end) = match self.positions() {
+            Some(positions) => positions,
+            None => {
+                for _ in 0..n {
+                    self.next()?;
+                }
+                return self.next();
+            }
+        };
+        let cfold = self.causal_iter.cfold;
+        match cfold.nth_visible(start + n).filter(|_| start + n < end) {
+            Some(idx) => {
+                self.causal_iter.current = cfold.index_after(idx);
+                self.current = Some((&cfold.log[idx.0], idx));
+                self.next()
+            }
+            None => {
+                self.current = None;
+                None
             }
         }
     }
 }
 
+impl<A, T> Iter<'_, A, T> {
+    /// Returns the end of the run of invisible entries starting at the
+    /// current item, if it can be skipped.
+    fn run_end(&self) -> Option<LocalIndex> {
+        let (_, idx) = self.current?;
+        let runs = &self.causal_iter.cfold.visibility.as_ref()?.runs;
+        runs.end(idx).filter(|_| self.skip_runs)
+    }
+
+    /// Returns the positions of the remaining elements, if they can be
+    /// counted without iterating.
+    fn positions(&self) -> Option<(usize, usize)> {
+        if !self.skip_runs {
+            return None;
+        }
+        let cfold = self.causal_iter.cfold;
+        let start = match self.current {
+            Some((_, idx)) => cfold.count_visible_before(idx)?,
+            None => return Some((0, 0)),
+        };
+        let end = match self.causal_iter.first_excluded {
+            Some(idx) => cfold.count_visible_before(idx)?,
+            None => cfold.count_visible()?,
+        };
+        Some((start, end.max(start)))
+    }
+}
+
 /// An iterator over ops representing a chronofold's changes.
 ///
 /// This struct is created by the `iter_ops` method on `Chronofold`. See its
@@ -197,17 +369,18 @@ where
 ///
 /// Note that while this works like `Iterator::skip_while`, it does not create
 /// a new iterator. Instead `iter` is modified.
-fn skip_while<I>(iter: &mut I, predicate: impl Fn(&I::Item) -> bool) -> (usize, Option<I::Item>)
+fn skip_while<I>(
+    iter: &mut I,
+    mut predicate: impl FnMut(&I::Item) -> bool,
+) -> (usize, Option<I::Item>)
 where
     I: Iterator,
 {
     let mut skipped = 0;
     loop {
         match iter.next() {
-            Some(item) if !predicate(&item) =>
-                break (skipped, Some(item)),
-            None =>
-                break (skipped, None),
+            Some(item) if !predicate(&item) => break (skipped, Some(item)),
+            None => break (skipped, None),
             _ => skipped += 1,
         }
     }
@@ -226,7 +399,7 @@ mod tests {
         cfold.session(1).insert_after(LocalIndex(2), '2');
         assert_eq!(
             vec![LocalIndex(2), LocalIndex(4), LocalIndex(3)],
-            cfold.iter_casual_tree(LocalIndex(2)).collect::<Vec<_>>()
+            cfold.iter_subtree(LocalIndex(2)).collect::<Vec<_>>()
         );
     }
 
@@ -260,6 +433,78 @@ mod tests {
         );
     }
 
+    /// Asserts that `index_before` inverts `index_after` in causal order.
+    fn assert_linked<A: Author, T>(cfold: &Chronofold<A, T>) {
+        let mut previous = cfold.root;
+        for (_, idx) in cfold.iter_log_indices_causal_range(..) {
+            assert_eq!(Some(previous), cfold.index_before(idx));
+            previous = idx;
+        }
+        assert_eq!(None, cfold.index_before(cfold.root));
+        assert_eq!(None, cfold.index_before(LocalIndex(cfold.log.len())));
+    }
+
+    #[test]
+    fn index_before() {
+        let mut cfold = Chronofold::<u8, char>::default();
+        cfold.session(1).extend("abc".chars());
+        assert_linked(&cfold);
+        let mut other = cfold.clone();
+        other.session(2).insert_after(LocalIndex(1), 'x');
+        cfold.session(1).remove(LocalIndex(2));
+        cfold.session(1).insert_after(LocalIndex(1), 'y');
+        cfold.session(1).extend("de".chars());
+        for op in other.iter_ops(LocalIndex(4)..) {
+            cfold.apply(op.cloned()).unwrap();
+        }
+        assert_linked(&cfold);
+
+        let version = cfold.version().clone();
+        cfold.compact(&version);
+        assert_linked(&cfold);
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&cfold).unwrap();
+            let deserialized: Chronofold<u8, char> = serde_json::from_str(&json).unwrap();
+            assert_linked(&deserialized);
+        }
+    }
+
+    #[test]
+    fn causal_iter_double_ended() {
+        let mut cfold = Chronofold::<u8, char>::default();
+        cfold.session(1).extend("abc".chars());
+        cfold.session(1).insert_after(LocalIndex(1), 'x');
+        let rev: Vec<usize> = cfold
+            .iter_log_indices_causal_range(..)
+            .rev()
+            .map(|(_, idx)| idx.0)
+            .collect();
+        assert_eq!(vec![3, 2, 4, 1], rev);
+        let rev: Vec<usize> = cfold
+            .iter_log_indices_causal_range(LocalIndex(4)..LocalIndex(3))
+            .rev()
+            .map(|(_, idx)| idx.0)
+            .collect();
+        assert_eq!(vec![2, 4], rev);
+
+        let mut iter = cfold.iter_log_indices_causal_range(..);
+        assert_eq!(Some(1), iter.next().map(|(_, idx)| idx.0));
+        assert_eq!(Some(3), iter.next_back().map(|(_, idx)| idx.0));
+        assert_eq!(Some(4), iter.next().map(|(_, idx)| idx.0));
+        assert_eq!(Some(2), iter.next_back().map(|(_, idx)| idx.0));
+        assert_eq!(None, iter.next());
+        assert_eq!(None, iter.next_back());
+        assert_eq!(
+            0,
+            cfold
+                .iter_log_indices_causal_range(LocalIndex(4)..LocalIndex(4))
+                .rev()
+                .count()
+        );
+    }
+
     #[test]
     fn skip_while() {
         let mut iter = 2..10;
@@ -18,22 +18,37 @@
 //!
 //! [paper]: https://arxiv.org/abs/2002.09511
 //!
+//! # Features
+//!
+//! By default only the chronofold itself is compiled. Optional subsystems are
+//! enabled by features, or all at once by `full`:
+//!
+//! - `sync`: Bookkeeping for peers, relays, stability, locks, signed ops,
+//!   paginated op transfer and contribution bundles for federation.
+//! - `persistence`: Asynchronous storage and, with `serde`, incremental
+//!   persistence.
+//! - `documents`: Documents made of several chronofolds and document stores.
+//! - `interop`: Deterministic canonical exports.
+//!
 //! # Example usage
 //!
 //! ```rust
 //! use chronofold::{Chronofold, LocalIndex, Op};
 //!
-//! type AuthorId = &'static str;
+//! type AuthorId = u8;
+//!
+//! const ALICE: AuthorId = 1;
+//! const BOB: AuthorId = 2;
 //!
 //! // Alice creates a chronofold on her machine, makes some initial changes
 //! // and sends a copy to Bob.
 //! let mut cfold_a = Chronofold::<AuthorId, char>::default();
-//! cfold_a.session("alice").extend("Hello chronfold!".chars());
+//! cfold_a.session(ALICE).extend("Hello chronfold!".chars());
 //! let mut cfold_b = cfold_a.clone();
 //!
 //! // Alice adds some more text, ...
 //! let ops_a: Vec<Op<AuthorId, char>> = {
-//!     let mut session = cfold_a.session("alice");
+//!     let mut session = cfold_a.session(ALICE);
 //!     session.splice(
 //!         LocalIndex(16)..LocalIndex(16),
 //!         " - a data structure for versioned text".chars(),
@@ -43,7 +58,7 @@
 //!
 //! // ... while Bob fixes a typo.
 //! let ops_b: Vec<Op<AuthorId, char>> = {
-//!     let mut session = cfold_b.session("bob");
+//!     let mut session = cfold_b.session(BOB);
 //!     session.insert_after(LocalIndex(11), 'o');
 //!     session.iter_ops().map(Op::cloned).collect()
 //! };
@@ -71,37 +86,153 @@
 // everything in the crate root and keep our internal module structure
 // private. This keeps things simple for our users and gives us more
 // flexibility in restructuring the crate.
+mod baseline;
+mod batch;
+#[cfg(feature = "interop")]
+mod canonical;
 mod change;
+mod coalesce;
+mod compaction;
+mod conflict;
 mod distributed;
+#[cfg(feature = "documents")]
+mod document;
+mod element_id;
+#[cfg(feature = "encryption")]
+mod encryption;
 mod error;
+#[cfg(feature = "sync")]
+mod federation;
 mod fmt;
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+mod formats;
+mod hash;
+#[cfg(feature = "sync")]
+mod identity;
 mod index;
 mod internal;
 mod iter;
+#[cfg(feature = "sync")]
+mod locks;
+mod lookup;
+#[cfg(feature = "mmap")]
+mod mapped;
+mod materialize;
+#[cfg(feature = "uniffi")]
+mod mobile;
+mod observer;
+#[cfg(feature = "sync")]
+mod pagination;
+#[cfg(all(feature = "persistence", feature = "serde"))]
+mod persistence;
+mod policy;
+mod position;
+#[cfg(feature = "python")]
+mod python;
+// The standalone maps predate `Costructures`, which packs all co-structures
+// into a single map. They're kept around for reference and their tests.
+mod costructures;
+#[allow(dead_code)]
 mod offsetmap;
+#[allow(dead_code)]
 mod rangemap;
+#[cfg(feature = "sync")]
+mod relay;
 mod session;
+#[cfg(feature = "snapshot")]
+mod snapshot;
+#[cfg(feature = "sync")]
+mod stability;
+mod stats;
+#[cfg(feature = "persistence")]
+mod storage;
+#[cfg(feature = "documents")]
+mod store;
+#[cfg(feature = "sync")]
+mod sync;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+mod text;
+mod tree;
 mod version;
-mod costructures;
+mod visibility;
 
+pub use crate::baseline::*;
+pub use crate::batch::*;
+#[cfg(feature = "interop")]
+pub use crate::canonical::*;
 pub use crate::change::*;
+pub use crate::coalesce::*;
+pub use crate::compaction::*;
+pub use crate::conflict::*;
 use crate::costructures::Costructures;
 pub use crate::distributed::*;
+#[cfg(feature = "documents")]
+pub use crate::document::*;
+pub use crate::element_id::*;
+#[cfg(feature = "encryption")]
+pub use crate::encryption::*;
 pub use crate::error::*;
+#[cfg(feature = "sync")]
+pub use crate::federation::*;
 pub use crate::fmt::*;
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+pub use crate::formats::*;
+#[cfg(feature = "sync")]
+pub use crate::identity::*;
 pub use crate::index::*;
 pub use crate::iter::*;
+#[cfg(feature = "sync")]
+pub use crate::locks::*;
+use crate::lookup::TimestampIndex;
+#[cfg(feature = "mmap")]
+pub use crate::mapped::*;
+pub use crate::materialize::*;
+use crate::observer::Observers;
+pub use crate::observer::{ChangeEvent, Subscription};
+#[cfg(feature = "sync")]
+pub use crate::pagination::*;
+use crate::policy::Policies;
+pub use crate::policy::*;
+#[cfg(feature = "sync")]
+pub use crate::relay::*;
 pub use crate::session::*;
+#[cfg(feature = "snapshot")]
+pub use crate::snapshot::*;
+#[cfg(feature = "sync")]
+pub use crate::stability::*;
+pub use crate::stats::*;
+#[cfg(feature = "persistence")]
+pub use crate::storage::*;
+#[cfg(feature = "documents")]
+pub use crate::store::*;
+#[cfg(feature = "sync")]
+pub use crate::sync::*;
+pub use crate::text::*;
 pub use crate::version::*;
+use crate::visibility::Visibility;
+#[cfg(feature = "derive")]
+pub use chronofold_derive::ChronoDoc;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+use crate::index::{IndexShift, RelativeNextIndex, RelativePreviousIndex, RelativeReference};
 
-use crate::index::{IndexShift, RelativeNextIndex, RelativeReference};
-use crate::offsetmap::OffsetMap;
-use crate::rangemap::RangeFromMap;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
 
 #[cfg(feature = "serde")]
 #[macro_use]
 extern crate serde;
 
+#[cfg(feature = "serde")]
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
 /// A conflict-free replicated data structure for versioned sequences.
 ///
 /// # Terminology
@@ -131,21 +262,170 @@ extern crate serde;
 ///
 /// [`Vec`]: https://doc.rust-lang.org/std/vec/struct.Vec.html
 /// [`Index`]: https://doc.rust-lang.org/std/ops/trait.Index.html
-#[derive(PartialEq, Eq, Clone, Debug)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Chronofold<A, T> {
     log: Vec<Change<T>>,
     root: LocalIndex,
     #[cfg_attr(
         feature = "serde",
-        serde(bound(
-            serialize = "Version<A>: serde::Serialize",
-            deserialize = "Version<A>: serde::Deserialize<'de>"
-        ))
+        serde(bound(serialize = "Version<A>: serde::Serialize"))
     )]
     version: Version<A>,
 
+    #[cfg_attr(
+        feature = "serde",
+        serde(bound(serialize = "Costructures<A>: serde::Serialize"))
+    )]
     costructures: Costructures<A>,
+
+    /// The number of log entries removed by compaction.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "is_zero"))]
+    compacted: usize,
+
+    /// The stable versions entries were removed at, combined. All ops it
+    /// contains were applied, even if they aren't in the log anymore.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            skip_serializing_if = "Version::is_initial",
+            bound(serialize = "Version<A>: serde::Serialize")
+        )
+    )]
+    compacted_version: Version<A>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    policies: Policies<A, T>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    observers: Observers<A, T>,
+
+    /// Derived from the log and built on demand, e.g. after deserialization.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    visibility: Option<Visibility>,
+
+    /// The number of visible elements, counted along with the visibility
+    /// structures but also kept up to date while they're missing.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    len: Option<usize>,
+
+    /// The depth of each log entry in the causal tree, possibly missing the
+    /// newest entries, e.g. after deserialization.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    depths: Vec<usize>,
+
+    /// The visible elements, collected by `as_vec_cached`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    materialized: OnceLock<Vec<T>>,
+
+    /// The visible elements as shared chunks, collected by `freeze`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    frozen: OnceLock<Frozen<T>>,
+
+    /// The last snapshot taken by `freeze` before the log changed, to share
+    /// unchanged chunks with.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stale_frozen: Option<Frozen<T>>,
+
+    /// The contributions of each author, collected by `contributions` and
+    /// kept up to date from then on.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    contributions: OnceLock<BTreeMap<A, Contribution>>,
+
+    /// Maps timestamps to log indices, built by `log_index` and kept up to
+    /// date from then on.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    timestamp_index: OnceLock<TimestampIndex<A>>,
+
+    /// The conflicts recorded while applying ops, see `conflicts`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    conflicts: Vec<ConflictRegion<A>>,
+
+    /// Ends the `RemoteBatch` in progress, if any. `RemoteBatch` can't
+    /// require `A: Author` when it's dropped, so this is set when it begins.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    remote_batch: Option<RemoteBatchEnd<A, T>>,
+}
+
+// Policies and observers are configuration rather than state, and the
+// remaining fields are derived from the log, so they're excluded here.
+impl<A: PartialEq, T: PartialEq> PartialEq for Chronofold<A, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.log == other.log
+            && self.root == other.root
+            && self.version == other.version
+            && self.costructures == other.costructures
+            && self.compacted == other.compacted
+            && self.compacted_version == other.compacted_version
+    }
+}
+
+impl<A: Eq, T: Eq> Eq for Chronofold<A, T> {}
+
+/// The co-structures can only be checked against the log once both are
+/// known, so the serialized fields are deserialized first.
+#[cfg(feature = "serde")]
+impl<'de, A, T> serde::Deserialize<'de> for Chronofold<A, T>
+where
+    A: Author + serde::Deserialize<'de>,
+    T: serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(
+            rename = "Chronofold",
+            bound(deserialize = "A: Author + serde::Deserialize<'de>, T: serde::Deserialize<'de>")
+        )]
+        struct Repr<A, T> {
+            log: Vec<Change<T>>,
+            root: LocalIndex,
+            version: Version<A>,
+            costructures: costructures::Repr<A>,
+            #[serde(default)]
+            compacted: usize,
+            #[serde(default = "Version::empty")]
+            compacted_version: Version<A>,
+        }
+
+        let repr: Repr<A, T> = serde::Deserialize::deserialize(deserializer)?;
+        let costructures = Costructures::from_repr(repr.costructures, repr.log.len())?;
+        Ok(Self {
+            log: repr.log,
+            root: repr.root,
+            version: repr.version,
+            costructures,
+            compacted: repr.compacted,
+            compacted_version: repr.compacted_version,
+            policies: Policies::default(),
+            observers: Observers::default(),
+            visibility: None,
+            len: None,
+            depths: Vec::new(),
+            materialized: OnceLock::new(),
+            frozen: OnceLock::new(),
+            stale_frozen: None,
+            contributions: OnceLock::new(),
+            timestamp_index: OnceLock::new(),
+            conflicts: Vec::new(),
+            remote_batch: None,
+        })
+    }
+}
+
+/// Compares the visible text.
+impl<A> PartialEq<str> for Chronofold<A, char> {
+    fn eq(&self, other: &str) -> bool {
+        self.iter_elements().copied().eq(other.chars())
+    }
+}
+
+impl<A> PartialEq<&str> for Chronofold<A, char> {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
 }
 
 impl<A: Author, T> Chronofold<A, T> {
@@ -153,7 +433,7 @@ impl<A: Author, T> Chronofold<A, T> {
     pub fn new(author: A) -> Self {
         let root_idx = LocalIndex(0);
         let mut version = Version::default();
-        version.inc(&Timestamp::new(AuthorIndex(0), author));
+        version.inc(&Timestamp::new(AuthorIndex(0), author.clone()));
         let mut costructures = Costructures::new();
         costructures.set_next_index(root_idx, None);
         costructures.set_author(root_idx, author);
@@ -164,17 +444,29 @@ impl<A: Author, T> Chronofold<A, T> {
             root: LocalIndex(0),
             version,
             costructures,
+            compacted: 0,
+            compacted_version: Version::default(),
+            policies: Policies::default(),
+            observers: Observers::default(),
+            visibility: None,
+            len: Some(0),
+            depths: vec![0],
+            materialized: OnceLock::new(),
+            frozen: OnceLock::new(),
+            stale_frozen: None,
+            contributions: OnceLock::new(),
+            timestamp_index: OnceLock::new(),
+            conflicts: Vec::new(),
+            remote_batch: None,
         }
     }
+}
 
+impl<A, T> Chronofold<A, T> {
     fn get_next_index(&self, index: &LocalIndex) -> Option<LocalIndex> {
         self.costructures.get_next_index(index)
     }
 
-    fn get_author(&self, index: &LocalIndex) -> Option<A> {
-        self.costructures.get_author(index)
-    }
-
     fn get_index_shift(&self, index: &LocalIndex) -> Option<IndexShift> {
         self.costructures.get_index_shift(index)
     }
@@ -183,22 +475,6 @@ impl<A: Author, T> Chronofold<A, T> {
         self.costructures.get_reference(index)
     }
 
-    fn set_next_index(&mut self, index: LocalIndex, value: Option<LocalIndex>) {
-        self.costructures.set_next_index(index, value);
-    }
-
-    fn set_author(&mut self, index: LocalIndex, value: A) {
-        self.costructures.set_author(index, value);
-    }
-
-    fn set_index_shift(&mut self, index: LocalIndex, value: IndexShift) {
-        self.costructures.set_index_shift(index, value);
-    }
-
-    fn set_reference(&mut self, index: LocalIndex, value: Option<LocalIndex>) {
-        self.costructures.set_reference(index, value);
-    }
-
     /// Returns `true` if the chronofold contains no elements.
     pub fn is_empty(&self) -> bool {
         self.len() == 0
@@ -206,7 +482,9 @@ impl<A: Author, T> Chronofold<A, T> {
 
     /// Returns the number of elements in the chronofold.
     pub fn len(&self) -> usize {
-        self.iter().count()
+        self.len
+            .or_else(|| self.count_visible())
+            .unwrap_or_else(|| self.iter().count())
     }
 
     /// Returns a reference to a change in the chronofold's log.
@@ -215,24 +493,39 @@ impl<A: Author, T> Chronofold<A, T> {
     pub fn get(&self, index: LocalIndex) -> Option<&Change<T>> {
         self.log.get(index.0)
     }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    fn get_author(&self, index: &LocalIndex) -> Option<&A> {
+        self.costructures.get_author(index)
+    }
+
+    fn set_next_index(&mut self, index: LocalIndex, value: Option<LocalIndex>) {
+        self.costructures.set_next_index(index, value);
+    }
+
+    fn set_author(&mut self, index: LocalIndex, value: A) {
+        self.costructures.set_author(index, value);
+    }
+
+    fn set_index_shift(&mut self, index: LocalIndex, value: IndexShift) {
+        self.costructures.set_index_shift(index, value);
+    }
+
+    fn set_reference(&mut self, index: LocalIndex, value: Option<LocalIndex>) {
+        self.costructures.set_reference(index, value);
+    }
 
     /// Creates an editing session for a single author.
     pub fn session(&mut self, author: A) -> Session<'_, A, T> {
         Session::new(author, self)
     }
 
-    /// ndxᵅ, (ß, ɣ) -> j
-    pub fn log_index(&self, timestamp: &Timestamp<A>) -> Option<LocalIndex> {
-        (timestamp.idx.0 .. self.log.len())
-            .map(LocalIndex)
-            .find(|&index| self.timestamp(index).as_ref() == Some(timestamp))
-    }
-
     /// ndxᵅ-1, j -> (ß, ɣ)
     pub fn timestamp(&self, index: LocalIndex) -> Option<Timestamp<A>> {
         let shift = self.get_index_shift(&index)?;
         let author = self.get_author(&index)?;
-        Some(Timestamp::new(&index - &shift, author))
+        Some(Timestamp::new(&index - &shift, author.clone()))
     }
 
     /// Applies an op to the chronofold.
@@ -240,50 +533,134 @@ impl<A: Author, T> Chronofold<A, T> {
     where
         V: IntoLocalValue<A, T>,
     {
-        // Check if an op with the same id was applied already.
+        // Check if an op with the same id was applied already. Ops newer than
+        // the version can be told apart without looking up the timestamp,
+        // unless they were applied during the current remote batch.
         // TODO: Consider adding an `apply_unchecked` variant to skip this
         // check.
-        if self.log_index(&op.id).is_some() {
-            return Err(ChronofoldError::ExistingTimestamp(op));
+        if self.remote_batch.is_some() || self.version.contains(&op.id) {
+            if let Some(idx) = self.log_index(&op.id) {
+                return Err(if self.is_resend(idx, &op) {
+                    ChronofoldError::AlreadyApplied(op)
+                } else {
+                    ChronofoldError::ExistingTimestamp(op)
+                });
+            }
+            // Ops removed by compaction can't be compared anymore.
+            if self.compacted_version.contains(&op.id) {
+                return Err(ChronofoldError::AlreadyApplied(op));
+            }
         }
 
         // We rely on indices in timestamps being smaller or equal than their
         // indices in every local log. This means we cannot apply an op not
         // matching this constraint, even if we know the reference.
         // i.e. andx(βᵏ) ⪬ k; andx(t) ⪬ ndxᵦ(t) for all β ∈ proc(R)
-        if op.id.idx.0 > self.log.len() {
+        // Entries removed by compaction still count.
+        if op.id.idx.0 > self.log.len() + self.compacted {
             return Err(ChronofoldError::FutureTimestamp(op));
         }
+        if self.policies.strict && op.payload.reference().is_some_and(|t| t.idx >= op.id.idx) {
+            return Err(ChronofoldError::ImplausibleReference(op));
+        }
 
         use OpPayload::*;
         // transform author index to local index before adding entry to the log
-        let (reference, change) = match op.payload {
-            Root => {
-                (None, Change::Root)
-            }
-            Insert(Some(t), value) => match self.log_index(&t) {
-                Some(reference) =>
-                    (Some(reference),
-                        Change::Insert(value.into_local_value(self))),
-                None => return Err(ChronofoldError::UnknownReference(Op::insert(
-                    op.id,
-                    Some(t),
-                    value,
-                ))),
-            },
-            Insert(None, value) => {
-                (None, Change::Insert(value.into_local_value(self)))
-            }
-            Delete(t) => match self.log_index(&t) {
-                Some(reference) =>
-                    (Some(reference), Change::Delete),
-                None => return Err(ChronofoldError::UnknownReference(op)),
+        let reference = match op.payload.reference() {
+            Some(t) => match self.log_index(t) {
+                Some(reference) => Some(reference),
+                None => {
+                    let known = self.known_timestamp(&t.author);
+                    return Err(ChronofoldError::UnknownReference(op, known));
+                }
             },
+            None => None,
         };
 
+        let reference_id = op.payload.reference().cloned();
+        let change = match op.payload {
+            Root => Change::Root,
+            Insert(_, value) => Change::Insert(value.into_local_value(self)),
+            Delete(_) => Change::Delete,
+        };
+
+        if let Some(validator) = &self.policies.validator {
+            let payload = match &change {
+                Change::Root => Root,
+                Change::Insert(value) => Insert(reference_id, value),
+                Change::Delete => Delete(reference_id.expect("deletes must have a reference")),
+            };
+            if let Err(reason) = validator.validate(&Op::new(op.id.clone(), payload)) {
+                return Err(ChronofoldError::Rejected(op.id, reason));
+            }
+        }
+
+        if let (Some(reference), Change::Insert(_)) = (reference, &change) {
+            if self.policies.track_conflicts {
+                self.record_conflicts(op.id.clone(), reference);
+            }
+        }
         self.apply_change(op.id, reference, change);
         Ok(())
     }
+
+    /// Returns `true` if `op` matches the change at `idx` with the same
+    /// timestamp.
+    ///
+    /// Values aren't compared, as that would require `T: PartialEq`. A
+    /// reference removed by compaction is assumed to match.
+    fn is_resend<V>(&self, idx: LocalIndex, op: &Op<A, V>) -> bool {
+        let same_kind = matches!(
+            (&self.log[idx.0], &op.payload),
+            (Change::Root, OpPayload::Root)
+                | (Change::Insert(_), OpPayload::Insert(..))
+                | (Change::Delete, OpPayload::Delete(_))
+        );
+        same_kind
+            && match op.payload.reference() {
+                Some(reference) => match self.log_index(reference) {
+                    Some(reference) => self.get_reference(&idx) == Some(reference),
+                    None => self.version.contains(reference),
+                },
+                None => self.get_reference(&idx).is_none(),
+            }
+    }
+
+    /// Applies an op to the chronofold, ignoring it if it was applied before.
+    ///
+    /// Transports with at-least-once delivery make duplicate ops a normal
+    /// occurrence rather than an exceptional one. This works like `apply`, but
+    /// silently skips ops that were applied before instead of returning
+    /// `ChronofoldError::AlreadyApplied`. Reusing the timestamp of a
+    /// different op is still an error.
+    pub fn apply_idempotent<V>(&mut self, op: Op<A, V>) -> Result<(), ChronofoldError<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        match self.apply(op) {
+            Err(ChronofoldError::AlreadyApplied(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Rebuilds all structures derived from the log and its co-structures.
+    ///
+    /// These are usually updated along with every change, or built on
+    /// demand. Rebuilding them from scratch helps after loading data of an
+    /// older format, if they are suspected to be corrupted, or to compare
+    /// them with their incrementally updated counterparts in tests.
+    ///
+    /// Authors, references, next indices and index shifts are part of the
+    /// chronofold's data and are kept as they are.
+    pub fn rebuild_indices(&mut self) {
+        self.costructures.rebuild_previous_indices();
+        self.clear_materialized();
+        self.contributions.take();
+        self.timestamp_index.take();
+        self.rebuild_visibility();
+        self.depths.clear();
+        self.extend_depths();
+    }
 }
 
 impl<A: Author + Default, T> Default for Chronofold<A, T> {
@@ -0,0 +1,234 @@
+//! Replicated locks on regions of a chronofold.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    Author, Chronofold, ChronofoldError, ElementId, IntoLocalValue, LocalIndex, Op, OpPayload,
+    Session,
+};
+
+/// Whether a region lock is enforced.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LockMode {
+    /// Other authors are asked not to edit the region, e.g. by the UI.
+    Advisory,
+    /// Edits of other authors inside the region are rejected by
+    /// `apply_respecting_locks`.
+    Enforced,
+}
+
+/// Identifies a lock by its owner and a sequence number per owner.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LockId<A> {
+    pub owner: A,
+    pub seq: u64,
+}
+
+/// A locked region, from the element `start` to the element `end` in causal
+/// order.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RegionLock<A> {
+    pub start: ElementId<A>,
+    pub end: ElementId<A>,
+    pub mode: LockMode,
+}
+
+/// An op locking or unlocking a region, to be sent to other replicas.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LockOp<A> {
+    Lock(LockId<A>, RegionLock<A>),
+    Unlock(LockId<A>),
+}
+
+/// The region locks of a chronofold.
+///
+/// Locks are replicated by exchanging `LockOp`s, which can be applied in any
+/// order and more than once. An unlock wins over its lock, even if it
+/// arrives first.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "A: serde::Serialize",
+        deserialize = "A: Author + serde::Deserialize<'de>"
+    ))
+)]
+pub struct RegionLocks<A> {
+    locks: BTreeMap<LockId<A>, RegionLock<A>>,
+    unlocked: BTreeSet<LockId<A>>,
+}
+
+impl<A> Default for RegionLocks<A> {
+    fn default() -> Self {
+        Self {
+            locks: BTreeMap::new(),
+            unlocked: BTreeSet::new(),
+        }
+    }
+}
+
+impl<A: Author> RegionLocks<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Locks the region from `start` to `end` for `owner` and returns the op
+    /// to send to other replicas.
+    ///
+    /// `start` must not come after `end` in causal order.
+    pub fn lock(
+        &mut self,
+        owner: A,
+        start: ElementId<A>,
+        end: ElementId<A>,
+        mode: LockMode,
+    ) -> LockOp<A> {
+        let seq = self
+            .locks
+            .keys()
+            .chain(&self.unlocked)
+            .filter(|id| id.owner == owner)
+            .map(|id| id.seq + 1)
+            .max()
+            .unwrap_or(0);
+        let op = LockOp::Lock(LockId { owner, seq }, RegionLock { start, end, mode });
+        self.apply(op.clone());
+        op
+    }
+
+    /// Unlocks a region and returns the op to send to other replicas, or
+    /// `None` if it isn't locked.
+    pub fn unlock(&mut self, id: LockId<A>) -> Option<LockOp<A>> {
+        self.locks.get(&id)?;
+        let op = LockOp::Unlock(id);
+        self.apply(op.clone());
+        Some(op)
+    }
+
+    /// Applies a lock op received from another replica.
+    pub fn apply(&mut self, op: LockOp<A>) {
+        match op {
+            LockOp::Lock(id, lock) => {
+                if !self.unlocked.contains(&id) {
+                    self.locks.insert(id, lock);
+                }
+            }
+            LockOp::Unlock(id) => {
+                self.locks.remove(&id);
+                self.unlocked.insert(id);
+            }
+        }
+    }
+
+    /// Returns the lock with the given id, unless it was unlocked.
+    pub fn get(&self, id: &LockId<A>) -> Option<&RegionLock<A>> {
+        self.locks.get(id)
+    }
+
+    /// Returns an iterator over the current locks.
+    pub fn iter(&self) -> impl Iterator<Item = (&LockId<A>, &RegionLock<A>)> {
+        self.locks.iter()
+    }
+
+    /// Returns the ops recreating the current state, e.g. for a new replica.
+    pub fn ops(&self) -> impl Iterator<Item = LockOp<A>> + '_ {
+        self.locks
+            .iter()
+            .map(|(id, lock)| LockOp::Lock(id.clone(), lock.clone()))
+            .chain(self.unlocked.iter().cloned().map(LockOp::Unlock))
+    }
+
+    /// Returns an iterator over the locks whose region contains the element
+    /// at log index `index`.
+    pub fn covering<'a, T>(
+        &'a self,
+        cfold: &'a Chronofold<A, T>,
+        index: LocalIndex,
+    ) -> impl Iterator<Item = (&'a LockId<A>, &'a RegionLock<A>)> + 'a {
+        self.locks
+            .iter()
+            .filter(move |(_, lock)| cfold.region_contains(lock, index))
+    }
+
+    /// Returns the enforced lock of another author that an edit by `author`
+    /// would violate.
+    ///
+    /// Inserts after a region's last element are placed outside of it, so
+    /// they're allowed.
+    fn violated<'a, T>(
+        &'a self,
+        cfold: &'a Chronofold<A, T>,
+        author: &A,
+        reference: LocalIndex,
+        insert: bool,
+    ) -> Option<(&'a LockId<A>, &'a RegionLock<A>)> {
+        let end = cfold.element_id(reference);
+        self.covering(cfold, reference).find(|(id, lock)| {
+            lock.mode == LockMode::Enforced
+                && id.owner != *author
+                && !(insert && end.as_ref() == Some(&lock.end))
+        })
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns `true` if `index` lies in `lock`'s region.
+    fn region_contains(&self, lock: &RegionLock<A>, index: LocalIndex) -> bool {
+        match (self.resolve(&lock.start), self.resolve(&lock.end)) {
+            (Some(start), Some(end)) => self
+                .iter_log_indices_causal_range(start..=end)
+                .any(|(_, idx)| idx == index),
+            _ => false,
+        }
+    }
+
+    /// Applies an op, unless it edits a region locked by another author with
+    /// `LockMode::Enforced`.
+    ///
+    /// Such ops are rejected with `ChronofoldError::Rejected`.
+    pub fn apply_respecting_locks<V>(
+        &mut self,
+        op: Op<A, V>,
+        locks: &RegionLocks<A>,
+    ) -> Result<(), ChronofoldError<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        let (reference, insert) = match &op.payload {
+            OpPayload::Root => (None, false),
+            OpPayload::Insert(reference, _) => (reference.as_ref(), true),
+            OpPayload::Delete(reference) => (Some(reference), false),
+        };
+        let violated = reference
+            .and_then(|reference| self.log_index(reference))
+            .and_then(|reference| locks.violated(self, &op.id.author, reference, insert));
+        if let Some((id, _)) = violated {
+            let reason = format!("region is locked by {:?}", id.owner);
+            return Err(ChronofoldError::Rejected(op.id, reason));
+        }
+        self.apply(op)
+    }
+}
+
+impl<A: Author, T> Session<'_, A, T> {
+    /// Returns `false` if inserting after `index` would edit a region locked
+    /// by another author with `LockMode::Enforced`.
+    pub fn can_insert_after(&self, index: LocalIndex, locks: &RegionLocks<A>) -> bool {
+        locks
+            .violated(self.as_ref(), &self.author(), index, true)
+            .is_none()
+    }
+
+    /// Returns `false` if removing the element at `index` would edit a region
+    /// locked by another author with `LockMode::Enforced`.
+    pub fn can_remove(&self, index: LocalIndex, locks: &RegionLocks<A>) -> bool {
+        locks
+            .violated(self.as_ref(), &self.author(), index, false)
+            .is_none()
+    }
+}
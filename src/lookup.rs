@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+use crate::{Author, AuthorIndex, Chronofold, LocalIndex, Timestamp};
+
+/// Maps timestamps to log indices, so `log_index` doesn't have to search
+/// the log.
+///
+/// Log indices are kept in one vector per author, sorted by author index.
+/// An author's ops mostly arrive in order, so new entries are usually
+/// appended.
+#[derive(Clone, Debug)]
+pub(crate) struct TimestampIndex<A> {
+    authors: BTreeMap<A, Vec<(AuthorIndex, LocalIndex)>>,
+}
+
+impl<A: Author> TimestampIndex<A> {
+    fn new() -> Self {
+        Self {
+            authors: BTreeMap::new(),
+        }
+    }
+
+    fn get(&self, timestamp: &Timestamp<A>) -> Option<LocalIndex> {
+        let entries = self.authors.get(&timestamp.author)?;
+        let i = entries
+            .binary_search_by_key(&timestamp.idx, |(idx, _)| *idx)
+            .ok()?;
+        Some(entries[i].1)
+    }
+
+    fn insert(&mut self, timestamp: Timestamp<A>, index: LocalIndex) {
+        let Timestamp {
+            idx: author_idx,
+            author,
+        } = timestamp;
+        let entries = self.authors.entry(author).or_default();
+        match entries.last() {
+            Some((last, _)) if *last >= author_idx => {
+                let i = entries.partition_point(|(idx, _)| *idx < author_idx);
+                entries.insert(i, (author_idx, index));
+            }
+            _ => entries.push((author_idx, index)),
+        }
+    }
+
+    fn remove(&mut self, timestamp: &Timestamp<A>) {
+        if let Some(entries) = self.authors.get_mut(&timestamp.author) {
+            if let Ok(i) = entries.binary_search_by_key(&timestamp.idx, |(idx, _)| *idx) {
+                entries.remove(i);
+            }
+        }
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// ndxᵅ, (ß, ɣ) -> j
+    pub fn log_index(&self, timestamp: &Timestamp<A>) -> Option<LocalIndex> {
+        self.timestamp_index
+            .get_or_init(|| {
+                let mut index = TimestampIndex::new();
+                for idx in (0..self.log.len()).map(LocalIndex) {
+                    let timestamp = self.timestamp(idx).expect("log entries have timestamps");
+                    index.insert(timestamp, idx);
+                }
+                index
+            })
+            .get(timestamp)
+    }
+
+    /// Adds a new log entry to the timestamp index, if it was built.
+    pub(crate) fn index_timestamp(&mut self, timestamp: Timestamp<A>, index: LocalIndex) {
+        if let Some(timestamp_index) = self.timestamp_index.get_mut() {
+            timestamp_index.insert(timestamp, index);
+        }
+    }
+
+    /// Removes a log entry from the timestamp index, if it was built.
+    pub(crate) fn unindex_timestamp(&mut self, timestamp: &Timestamp<A>) {
+        if let Some(timestamp_index) = self.timestamp_index.get_mut() {
+            timestamp_index.remove(timestamp);
+        }
+    }
+}
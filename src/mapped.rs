@@ -0,0 +1,228 @@
+//! Chunked op logs for memory-mapped, read-only access.
+//!
+//! Unlike snapshots, op log files store the ops in independently decodable
+//! chunks, so a reader only decodes the ranges it needs:
+//!
+//! | bytes  | content                                            |
+//! |--------|----------------------------------------------------|
+//! | 4      | magic bytes `CFOL`                                 |
+//! | 1      | format version                                     |
+//! | 3      | reserved                                           |
+//! | 8      | ops per chunk, LE                                  |
+//! | 8      | number of ops, LE                                  |
+//! | …      | chunks, each a CBOR sequence of ops                |
+//! | 24 × n | per chunk: offset, length and FNV-1a checksum, LE  |
+//! | 8      | offset of the chunk table, LE                      |
+
+use std::convert::TryInto;
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::Path;
+
+use memmap2::Mmap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::hash::StableHasher;
+use crate::{from_cbor_slice, to_cbor_bytes, Author, Chronofold, Op, OpPayload, SnapshotError};
+
+const MAGIC: &[u8; 4] = b"CFOL";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = 24;
+
+impl<A, T> Chronofold<A, T>
+where
+    A: Author + Serialize,
+    T: Serialize + Clone,
+{
+    /// Writes all ops to the file at `path` in chunks of `chunk_size` ops,
+    /// to be opened with `MappedOpLog`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn save_op_log_to(
+        &self,
+        path: impl AsRef<Path>,
+        chunk_size: usize,
+    ) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let mut file = io::BufWriter::new(fs::File::create(&tmp_path)?);
+        self.write_op_log(&mut file, chunk_size)?;
+        file.into_inner()
+            .map_err(io::IntoInnerError::into_error)?
+            .sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Writes all ops to `writer` in chunks of `chunk_size` ops.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn write_op_log(
+        &self,
+        mut writer: impl Write,
+        chunk_size: usize,
+    ) -> Result<(), SnapshotError> {
+        assert!(chunk_size > 0, "chunks must not be empty");
+        let op_count = self.log.len();
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION, 0, 0, 0])?;
+        writer.write_all(&(chunk_size as u64).to_le_bytes())?;
+        writer.write_all(&(op_count as u64).to_le_bytes())?;
+
+        let mut offset = HEADER_LEN as u64;
+        let mut table = Vec::new();
+        let mut ops = self.iter_ops::<&T>(..).map(Op::cloned);
+        for _ in (0..op_count).step_by(chunk_size) {
+            let chunk: Vec<Op<A, T>> = ops.by_ref().take(chunk_size).collect();
+            let bytes = to_cbor_bytes(&chunk)?;
+            let mut hasher = StableHasher::default();
+            hasher.write(&bytes);
+            writer.write_all(&bytes)?;
+            for n in [offset, bytes.len() as u64, hasher.finish()] {
+                table.extend_from_slice(&n.to_le_bytes());
+            }
+            offset += bytes.len() as u64;
+        }
+        writer.write_all(&table)?;
+        writer.write_all(&offset.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+/// A read-only, memory-mapped op log written by `save_op_log_to`.
+///
+/// Opening only reads the header and the chunk table. Ops are decoded when
+/// they are accessed, one chunk at a time.
+pub struct MappedOpLog<A, T> {
+    mmap: Mmap,
+    chunk_size: usize,
+    op_count: usize,
+    chunks: Vec<(Range<usize>, u64)>,
+    _op: PhantomData<fn() -> Op<A, T>>,
+}
+
+impl<A, T> MappedOpLog<A, T>
+where
+    A: Author + DeserializeOwned,
+    T: DeserializeOwned,
+{
+    /// Maps the op log at `path` into memory.
+    ///
+    /// The file must not be modified while it is mapped.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let file = fs::File::open(path)?;
+        // SAFETY: The mapping is read-only and we document that the file
+        // must not be modified concurrently. Corrupted data is detected by
+        // the checks below and the chunk checksums.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let invalid = || SnapshotError::Io(io::ErrorKind::InvalidData.into());
+        let read_u64 = |offset: usize| -> Result<u64, SnapshotError> {
+            let bytes = mmap.get(offset..offset + 8).ok_or_else(invalid)?;
+            Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+        };
+
+        if mmap.get(..4) != Some(&MAGIC[..]) {
+            return Err(SnapshotError::InvalidMagic);
+        }
+        let version = mmap.get(4).copied().ok_or_else(invalid)?;
+        if version > FORMAT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let chunk_size = read_u64(8)? as usize;
+        let op_count = read_u64(16)? as usize;
+        let table_start = read_u64(mmap.len().checked_sub(8).ok_or_else(invalid)?)? as usize;
+        if chunk_size == 0 {
+            return Err(invalid());
+        }
+        let chunk_count = op_count.div_ceil(chunk_size);
+        let mut chunks = Vec::with_capacity(chunk_count);
+        for i in 0..chunk_count {
+            let entry = table_start + i * 24;
+            let offset = read_u64(entry)? as usize;
+            let len = read_u64(entry + 8)? as usize;
+            if offset.checked_add(len).is_none_or(|end| end > table_start) {
+                return Err(invalid());
+            }
+            chunks.push((offset..offset + len, read_u64(entry + 16)?));
+        }
+        Ok(Self {
+            mmap,
+            chunk_size,
+            op_count,
+            chunks,
+            _op: PhantomData,
+        })
+    }
+
+    /// Returns the number of ops.
+    pub fn len(&self) -> usize {
+        self.op_count
+    }
+
+    /// Returns `true` if the log contains no ops.
+    pub fn is_empty(&self) -> bool {
+        self.op_count == 0
+    }
+
+    /// Decodes the ops at the positions in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn ops(&self, range: Range<usize>) -> Result<Vec<Op<A, T>>, SnapshotError> {
+        assert!(
+            range.start <= range.end && range.end <= self.op_count,
+            "range out of bounds"
+        );
+        let mut ops = Vec::with_capacity(range.len());
+        if range.is_empty() {
+            return Ok(ops);
+        }
+        for chunk in range.start / self.chunk_size..=(range.end - 1) / self.chunk_size {
+            let first = chunk * self.chunk_size;
+            let decoded = self.decode_chunk(chunk)?;
+            let start = range.start.saturating_sub(first);
+            let end = usize::min(range.end - first, decoded.len());
+            ops.extend(decoded.into_iter().take(end).skip(start));
+        }
+        Ok(ops)
+    }
+
+    /// Decodes all ops and applies them to a new chronofold.
+    pub fn load(&self) -> Result<Chronofold<A, T>, SnapshotError> {
+        let invalid = || SnapshotError::Io(io::ErrorKind::InvalidData.into());
+        let mut cfold: Option<Chronofold<A, T>> = None;
+        for chunk in 0..self.chunks.len() {
+            for op in self.decode_chunk(chunk)? {
+                match &mut cfold {
+                    Some(cfold) => cfold.apply(op).map_err(|_| invalid())?,
+                    None if matches!(op.payload, OpPayload::Root) => {
+                        cfold = Some(Chronofold::new(op.id.author));
+                    }
+                    None => return Err(invalid()),
+                }
+            }
+        }
+        cfold.ok_or_else(invalid)
+    }
+
+    fn decode_chunk(&self, chunk: usize) -> Result<Vec<Op<A, T>>, SnapshotError> {
+        let (range, checksum) = &self.chunks[chunk];
+        let bytes = &self.mmap[range.clone()];
+        let mut hasher = StableHasher::default();
+        hasher.write(bytes);
+        if hasher.finish() != *checksum {
+            return Err(SnapshotError::ChecksumMismatch);
+        }
+        Ok(from_cbor_slice(bytes)?)
+    }
+}
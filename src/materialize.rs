@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::iter;
+use std::sync::Arc;
+
+use crate::{Chronofold, LocalIndex};
+
+impl<A, T: Clone> Chronofold<A, T> {
+    /// Returns the visible elements as a slice.
+    ///
+    /// The elements are collected on the first call and cached until the
+    /// chronofold is changed, so repeated reads, e.g. by a syntax
+    /// highlighter, don't have to walk the log.
+    pub fn as_vec_cached(&self) -> &[T] {
+        self.materialized
+            .get_or_init(|| self.iter_elements().cloned().collect())
+    }
+
+    /// Returns an iterator over runs of visible elements that were inserted
+    /// consecutively, as slices.
+    ///
+    /// The slices borrow from `as_vec_cached`, so renderers can process the
+    /// document in large pieces instead of per element.
+    pub fn iter_chunks(&self) -> impl Iterator<Item = &[T]> {
+        let elements = self.as_vec_cached();
+        let mut indices = self.iter().map(|(_, idx)| idx).peekable();
+        let mut start = 0;
+        iter::from_fn(move || {
+            let mut idx = indices.next()?;
+            let mut end = start + 1;
+            while let Some(next) = indices.next_if(|next| next.0 == idx.0 + 1) {
+                idx = next;
+                end += 1;
+            }
+            let chunk = &elements[start..end];
+            start = end;
+            Some(chunk)
+        })
+    }
+}
+
+/// Log indices where chunks of a `Frozen` are split, so typing at the end
+/// of a document only rebuilds the last chunk.
+const FROZEN_CHUNK_LEN: usize = 64;
+
+/// An immutable snapshot of the visible elements of a chronofold.
+///
+/// This struct is created by the `freeze` method on `Chronofold`. The
+/// elements are stored in shared chunks, so snapshots are cheap to clone
+/// and can be sent to another thread while the chronofold is changed.
+#[derive(Debug)]
+pub struct Frozen<T> {
+    /// Chunks of consecutively inserted elements, with the log index of
+    /// their first element.
+    chunks: Arc<Vec<(LocalIndex, Arc<[T]>)>>,
+    len: usize,
+}
+
+impl<T> Clone for Frozen<T> {
+    fn clone(&self) -> Self {
+        Self {
+            chunks: Arc::clone(&self.chunks),
+            len: self.len,
+        }
+    }
+}
+
+impl<T> Frozen<T> {
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the elements in causal order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.chunks().flatten()
+    }
+
+    /// Returns an iterator over the chunks of elements in causal order.
+    pub fn chunks(&self) -> impl Iterator<Item = &[T]> {
+        self.chunks.iter().map(|(_, chunk)| &chunk[..])
+    }
+}
+
+impl<A, T: Clone> Chronofold<A, T> {
+    /// Returns an immutable snapshot of the visible elements.
+    ///
+    /// Unlike `as_vec_cached`, the snapshot doesn't borrow the chronofold,
+    /// so e.g. a background thread can keep iterating it while new ops are
+    /// applied. Chunks that didn't change since the last snapshot are shared
+    /// with it instead of cloning their elements again.
+    pub fn freeze(&self) -> Frozen<T> {
+        self.frozen.get_or_init(|| self.build_frozen()).clone()
+    }
+
+    fn build_frozen(&self) -> Frozen<T> {
+        let mut runs: Vec<(LocalIndex, Vec<&T>)> = Vec::new();
+        for (value, idx) in self.iter() {
+            match runs.last_mut() {
+                Some((start, values))
+                    if start.0 + values.len() == idx.0 && idx.0 % FROZEN_CHUNK_LEN != 0 =>
+                {
+                    values.push(value)
+                }
+                _ => runs.push((idx, vec![value])),
+            }
+        }
+
+        let stale: HashMap<(LocalIndex, usize), &Arc<[T]>> = self
+            .stale_frozen
+            .iter()
+            .flat_map(|frozen| frozen.chunks.iter())
+            .map(|(start, chunk)| ((*start, chunk.len()), chunk))
+            .collect();
+        let mut len = 0;
+        let chunks = runs
+            .into_iter()
+            .map(|(start, values)| {
+                len += values.len();
+                let chunk = match stale.get(&(start, values.len())) {
+                    Some(chunk) => Arc::clone(chunk),
+                    None => values.into_iter().cloned().collect(),
+                };
+                (start, chunk)
+            })
+            .collect();
+        Frozen {
+            chunks: Arc::new(chunks),
+            len,
+        }
+    }
+}
+
+impl<A, T> Chronofold<A, T> {
+    /// Drops the cached visible elements before the log is changed.
+    ///
+    /// The last snapshot is kept, so `freeze` can share its chunks.
+    pub(crate) fn invalidate_materialized(&mut self) {
+        self.materialized.take();
+        if let Some(frozen) = self.frozen.take() {
+            self.stale_frozen = Some(frozen);
+        }
+    }
+
+    /// Drops all cached visible elements, e.g. when log indices change.
+    pub(crate) fn clear_materialized(&mut self) {
+        self.materialized.take();
+        self.frozen.take();
+        self.stale_frozen = None;
+    }
+}
@@ -0,0 +1,243 @@
+//! Bindings for Kotlin and Swift, generated by UniFFI.
+//!
+//! Exposes a chronofold of text edited by a single local author, as in a
+//! mobile editor. Ops are exchanged as CBOR-encoded batches, and remote
+//! changes are reported as `TextDelta`s to update the editor's text.
+//!
+//! Generate the bindings with `uniffi-bindgen generate --library` from a
+//! build with the `uniffi` feature.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::{from_cbor_slice, to_cbor_bytes, Chronofold, FormatError, LocalIndex, Op, Version};
+
+/// Represents errors that can occur in the bindings.
+#[derive(Debug, uniffi::Error)]
+pub enum FfiError {
+    /// Ops or a version couldn't be decoded or encoded.
+    Format { reason: String },
+    /// A position is past the end of the text.
+    OutOfRange,
+}
+
+impl fmt::Display for FfiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FfiError::Format { reason } => write!(f, "{}", reason),
+            FfiError::OutOfRange => write!(f, "position out of range"),
+        }
+    }
+}
+
+impl From<FormatError> for FfiError {
+    fn from(err: FormatError) -> Self {
+        FfiError::Format {
+            reason: err.to_string(),
+        }
+    }
+}
+
+/// A change of the text: `deleted` characters at `position` are replaced by
+/// `inserted`.
+///
+/// Deltas are to be applied in order, so positions refer to the text with
+/// the previous deltas applied.
+#[derive(PartialEq, Eq, Clone, Debug, Default, uniffi::Record)]
+pub struct TextDelta {
+    pub position: u64,
+    pub deleted: u64,
+    pub inserted: String,
+}
+
+/// A replicated text edited by one local author.
+#[derive(uniffi::Object)]
+pub struct Text {
+    author: usize,
+    inner: Mutex<Chronofold<usize, char>>,
+}
+
+#[uniffi::export]
+impl Text {
+    /// Creates an empty text for `author`, to be synced with peers.
+    ///
+    /// All replicas of a text have to share the root, so pass the same
+    /// `root_author` on all of them.
+    #[uniffi::constructor]
+    pub fn new(author: u64, root_author: u64) -> Self {
+        Self {
+            author: author as usize,
+            inner: Mutex::new(Chronofold::new(root_author as usize)),
+        }
+    }
+
+    /// Returns the text.
+    pub fn text(&self) -> String {
+        self.lock().to_string()
+    }
+
+    /// Returns the version, to be sent to peers.
+    pub fn version(&self) -> Result<Vec<u8>, FfiError> {
+        Ok(to_cbor_bytes(self.lock().version())?)
+    }
+
+    /// Returns the ops newer than a peer's `version`.
+    pub fn ops_since(&self, version: Vec<u8>) -> Result<Vec<u8>, FfiError> {
+        let version: Version<usize> = from_cbor_slice(&version)?;
+        let cfold = self.lock();
+        let ops: Vec<Op<usize, &char>> = cfold.iter_newer_ops(&version).collect();
+        Ok(to_cbor_bytes(&ops)?)
+    }
+
+    /// Applies ops received from a peer and returns the resulting changes of
+    /// the text.
+    pub fn apply_ops(&self, ops: Vec<u8>) -> Result<Vec<TextDelta>, FfiError> {
+        let ops: Vec<Op<usize, char>> = from_cbor_slice(&ops)?;
+        let mut cfold = self.lock();
+        let before: Vec<LocalIndex> = cfold.iter().map(|(_, idx)| idx).collect();
+        cfold.apply_iter(ops);
+        Ok(deltas(&cfold, &before))
+    }
+
+    /// Inserts `text` at `position` and returns the ops to send to peers.
+    pub fn insert(&self, position: u64, text: String) -> Result<Vec<u8>, FfiError> {
+        let mut cfold = self.lock();
+        let mut reference = match (position as usize).checked_sub(1) {
+            None => cfold.root,
+            Some(previous) => cfold.iter().nth(previous).ok_or(FfiError::OutOfRange)?.1,
+        };
+        let first = cfold.next_log_index();
+        let mut session = cfold.session(self.author);
+        for c in text.chars() {
+            reference = session.insert_after(reference, c);
+        }
+        local_ops(&cfold, first)
+    }
+
+    /// Deletes `length` characters from `position` on and returns the ops to
+    /// send to peers.
+    pub fn delete(&self, position: u64, length: u64) -> Result<Vec<u8>, FfiError> {
+        let mut cfold = self.lock();
+        let indices: Vec<LocalIndex> = cfold
+            .iter()
+            .skip(position as usize)
+            .take(length as usize)
+            .map(|(_, idx)| idx)
+            .collect();
+        if indices.len() as u64 != length {
+            return Err(FfiError::OutOfRange);
+        }
+        let first = cfold.next_log_index();
+        let mut session = cfold.session(self.author);
+        for idx in indices {
+            session.remove(idx);
+        }
+        local_ops(&cfold, first)
+    }
+}
+
+impl Text {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Chronofold<usize, char>> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Returns the ops from log index `first` on.
+fn local_ops(cfold: &Chronofold<usize, char>, first: LocalIndex) -> Result<Vec<u8>, FfiError> {
+    let ops: Vec<Op<usize, &char>> = cfold.iter_ops(first..).collect();
+    Ok(to_cbor_bytes(&ops)?)
+}
+
+/// Returns the deltas turning the elements `before` into the current ones.
+///
+/// Elements keep their relative order, so both sequences can be merged in a
+/// single pass.
+fn deltas(cfold: &Chronofold<usize, char>, before: &[LocalIndex]) -> Vec<TextDelta> {
+    let after: Vec<(char, LocalIndex)> = cfold.iter().map(|(c, idx)| (*c, idx)).collect();
+    let kept: HashSet<LocalIndex> = after.iter().map(|(_, idx)| *idx).collect();
+    let existed: HashSet<LocalIndex> = before.iter().copied().collect();
+
+    let mut deltas = Vec::new();
+    let mut current: Option<TextDelta> = None;
+    let (mut old, mut new) = (before.iter().peekable(), after.iter().peekable());
+    let mut position = 0;
+    loop {
+        match (old.peek(), new.peek()) {
+            (Some(old_idx), _) if !kept.contains(old_idx) => {
+                current.get_or_insert_with(|| delta_at(position)).deleted += 1;
+                old.next();
+            }
+            (_, Some((c, new_idx))) if !existed.contains(new_idx) => {
+                current
+                    .get_or_insert_with(|| delta_at(position))
+                    .inserted
+                    .push(*c);
+                new.next();
+            }
+            (None, None) => break,
+            _ => {
+                // The element is unchanged.
+                if let Some(delta) = current.take() {
+                    position = delta.position + delta.inserted.chars().count() as u64;
+                    deltas.push(delta);
+                }
+                position += 1;
+                old.next();
+                new.next();
+            }
+        }
+    }
+    deltas.extend(current);
+    deltas
+}
+
+fn delta_at(position: u64) -> TextDelta {
+    TextDelta {
+        position,
+        ..TextDelta::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delta(position: u64, deleted: u64, inserted: &str) -> TextDelta {
+        TextDelta {
+            position,
+            deleted,
+            inserted: inserted.to_owned(),
+        }
+    }
+
+    #[test]
+    fn sync_with_deltas() {
+        let alice = Text::new(1, 0);
+        let bob = Text::new(2, 0);
+        let ops = alice.insert(0, "hello world".to_owned()).unwrap();
+        assert_eq!(
+            vec![delta(0, 0, "hello world")],
+            bob.apply_ops(ops).unwrap()
+        );
+
+        let version = bob.version().unwrap();
+        alice.delete(0, 1).unwrap();
+        alice.insert(0, "J".to_owned()).unwrap();
+        alice.delete(5, 6).unwrap();
+        alice.insert(5, "!".to_owned()).unwrap();
+        let ops = alice.ops_since(version).unwrap();
+        assert_eq!(
+            vec![delta(0, 1, "J"), delta(5, 6, "!")],
+            bob.apply_ops(ops).unwrap()
+        );
+        assert_eq!("Jello!", bob.text());
+        assert!(matches!(
+            alice.insert(7, "?".to_owned()),
+            Err(FfiError::OutOfRange)
+        ));
+        assert!(matches!(alice.delete(5, 2), Err(FfiError::OutOfRange)));
+    }
+}
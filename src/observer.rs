@@ -0,0 +1,313 @@
+//! Notifications about a chronofold's progress.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{Author, Change, Chronofold, ElementId, LocalIndex, Timestamp, Version};
+
+type VersionObserver<A> = dyn Fn(&Version<A>) + Send + Sync;
+type Subscriber<A, T> = dyn Fn(&ChangeEvent<'_, A, T>) + Send + Sync;
+
+/// A change of the visible elements, as passed to subscribers.
+///
+/// Positions refer to the visible elements right before the event, so
+/// applying the events in order to a copy of the elements, e.g. an editor's
+/// view, keeps it in sync.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ChangeEvent<'a, A, T> {
+    /// An element was inserted at `position`.
+    Insert {
+        position: usize,
+        id: ElementId<A>,
+        value: &'a T,
+    },
+    /// The element at `position` was deleted by `author`.
+    Delete {
+        position: usize,
+        id: ElementId<A>,
+        author: A,
+    },
+}
+
+impl<A: Clone, T> ChangeEvent<'_, A, T> {
+    /// Returns the position of the inserted or deleted element.
+    pub fn position(&self) -> usize {
+        match self {
+            Self::Insert { position, .. } | Self::Delete { position, .. } => *position,
+        }
+    }
+
+    /// Returns the id of the inserted or deleted element.
+    pub fn id(&self) -> ElementId<A> {
+        match self {
+            Self::Insert { id, .. } | Self::Delete { id, .. } => id.clone(),
+        }
+    }
+
+    /// Returns the author who made the change.
+    pub fn author(&self) -> A {
+        match self {
+            Self::Insert { id, .. } => id.author().clone(),
+            Self::Delete { author, .. } => author.clone(),
+        }
+    }
+}
+
+/// Identifies a subscriber, see `Chronofold::subscribe`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub struct Subscription(u64);
+
+/// The observers registered on a chronofold.
+///
+/// Like policies, observers are neither compared nor serialized. Unlike
+/// policies, they aren't cloned either, as a clone advances independently.
+pub(crate) struct Observers<A, T> {
+    version: Option<Arc<VersionObserver<A>>>,
+    subscribers: Vec<(Subscription, Arc<Subscriber<A, T>>)>,
+    next_subscription: u64,
+    /// Whether the version changed during the current `RemoteBatch`.
+    pending: bool,
+    /// The length of the log when the current `RemoteBatch` began.
+    batch_start: usize,
+}
+
+impl<A, T> Clone for Observers<A, T> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<A, T> Default for Observers<A, T> {
+    fn default() -> Self {
+        Self {
+            version: None,
+            subscribers: Vec::new(),
+            next_subscription: 0,
+            pending: false,
+            batch_start: 0,
+        }
+    }
+}
+
+impl<A, T> fmt::Debug for Observers<A, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Observers")
+            .field("version", &self.version.as_ref().map(|_| ".."))
+            .field("subscribers", &self.subscribers.len())
+            .finish()
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Sets a callback, which is called with the new version whenever the
+    /// version changes.
+    ///
+    /// This happens once for every applied op or local change, and once at
+    /// the end of a `RemoteBatch`. A coalescing `Session` cancelling an
+    /// insert moves its author's version back, which is reported as well.
+    /// The callback replaces any previous one.
+    pub fn set_version_observer(&mut self, observer: impl Fn(&Version<A>) + Send + Sync + 'static) {
+        self.observers.version = Some(Arc::new(observer));
+    }
+
+    /// Removes the version observer, if any.
+    pub fn remove_version_observer(&mut self) {
+        self.observers.version = None;
+    }
+
+    /// Registers a callback, which is called with a `ChangeEvent` whenever
+    /// an element is inserted or deleted, locally or remotely.
+    ///
+    /// Events of a `RemoteBatch` are emitted when it ends: first the
+    /// deletes, from the last position to the first, then the inserts, from
+    /// the first position to the last. Elements both inserted and deleted
+    /// during the batch are left out.
+    pub fn subscribe(
+        &mut self,
+        subscriber: impl Fn(&ChangeEvent<'_, A, T>) + Send + Sync + 'static,
+    ) -> Subscription {
+        let subscription = Subscription(self.observers.next_subscription);
+        self.observers.next_subscription += 1;
+        self.observers
+            .subscribers
+            .push((subscription, Arc::new(subscriber)));
+        subscription
+    }
+
+    /// Removes a subscriber, returning `false` if it was removed before.
+    pub fn unsubscribe(&mut self, subscription: Subscription) -> bool {
+        let len = self.observers.subscribers.len();
+        self.observers
+            .subscribers
+            .retain(|(s, _)| *s != subscription);
+        self.observers.subscribers.len() != len
+    }
+
+    /// Notifies subscribers about the change at log index `index`, which was
+    /// just applied.
+    ///
+    /// `deletes_element` tells whether a delete hid a visible element.
+    pub(crate) fn notify_change(&self, index: LocalIndex, deletes_element: bool) {
+        if self.observers.subscribers.is_empty() || self.remote_batch.is_some() {
+            return;
+        }
+        match &self.log[index.0] {
+            Change::Insert(value) => self.emit(&ChangeEvent::Insert {
+                position: self.visible_before(index),
+                id: self
+                    .timestamp(index)
+                    .expect("log entries have timestamps")
+                    .into(),
+                value,
+            }),
+            Change::Delete if deletes_element => {
+                let element = self.get_reference(&index).expect("deletes have references");
+                self.emit(&ChangeEvent::Delete {
+                    position: self.visible_before(element),
+                    id: self
+                        .timestamp(element)
+                        .expect("log entries have timestamps")
+                        .into(),
+                    author: self
+                        .get_author(&index)
+                        .expect("log entries have authors")
+                        .clone(),
+                })
+            }
+            _ => {}
+        }
+    }
+
+    /// Notifies subscribers that the visible element at `index` is about to
+    /// be removed by `author`, without a delete.
+    pub(crate) fn notify_removal(&self, index: LocalIndex, author: A) {
+        if self.observers.subscribers.is_empty() {
+            return;
+        }
+        self.emit(&ChangeEvent::Delete {
+            position: self.visible_before(index),
+            id: self
+                .timestamp(index)
+                .expect("log entries have timestamps")
+                .into(),
+            author,
+        });
+    }
+
+    /// Remembers where a `RemoteBatch` begins, so its changes can be
+    /// reported when it ends.
+    pub(crate) fn mark_batch_start(&mut self) {
+        self.observers.batch_start = self.log.len();
+    }
+
+    /// Returns the length of the log when the current `RemoteBatch` began.
+    pub(crate) fn batch_start(&self) -> usize {
+        self.observers.batch_start
+    }
+
+    /// Notifies subscribers about the changes of the `RemoteBatch` that just
+    /// ended.
+    pub(crate) fn report_batch(&self) {
+        let start = self.observers.batch_start;
+        if self.observers.subscribers.is_empty() || start == self.log.len() {
+            return;
+        }
+
+        // For each deleted element, whether it was deleted before the batch,
+        // and the earliest delete during the batch.
+        let mut deletes: BTreeMap<LocalIndex, (bool, Option<Timestamp<A>>)> = BTreeMap::new();
+        for (idx, change) in self.log.iter().enumerate() {
+            if !matches!(change, Change::Delete) {
+                continue;
+            }
+            let idx = LocalIndex(idx);
+            let mut target = self.get_reference(&idx).expect("deletes have references");
+            while let Change::Delete = self.log[target.0] {
+                target = self
+                    .get_reference(&target)
+                    .expect("deletes have references");
+            }
+            let entry = deletes.entry(target).or_default();
+            if idx.0 < start {
+                entry.0 = true;
+            } else {
+                let timestamp = self.timestamp(idx).expect("log entries have timestamps");
+                entry.1 = Some(match entry.1.take() {
+                    Some(t) => t.min(timestamp),
+                    None => timestamp,
+                });
+            }
+        }
+
+        let mut deleted = Vec::new();
+        let mut inserted = Vec::new();
+        let (mut visible_before, mut visible_after) = (0, 0);
+        for (change, idx) in self.iter_log_indices_causal_range(..) {
+            let Change::Insert(value) = change else {
+                continue;
+            };
+            let (deleted_before, deleted_by) = deletes.get(&idx).cloned().unwrap_or_default();
+            let was_visible = idx.0 < start && !deleted_before;
+            let is_visible = !deleted_before && deleted_by.is_none();
+            let id = self
+                .timestamp(idx)
+                .expect("log entries have timestamps")
+                .into();
+            match deleted_by {
+                Some(deleted_by) if was_visible => deleted.push(ChangeEvent::Delete {
+                    position: visible_before,
+                    id,
+                    author: deleted_by.author,
+                }),
+                _ if is_visible && idx.0 >= start => inserted.push(ChangeEvent::Insert {
+                    position: visible_after,
+                    id,
+                    value,
+                }),
+                _ => {}
+            }
+            visible_before += usize::from(was_visible);
+            visible_after += usize::from(is_visible);
+        }
+        for event in deleted.iter().rev().chain(&inserted) {
+            self.emit(event);
+        }
+    }
+
+    fn emit(&self, event: &ChangeEvent<'_, A, T>) {
+        for (_, subscriber) in &self.observers.subscribers {
+            subscriber(event);
+        }
+    }
+
+    /// Returns the number of visible elements before `idx` in causal order.
+    pub(crate) fn visible_before(&self, idx: LocalIndex) -> usize {
+        self.count_visible_before(idx).unwrap_or_else(|| {
+            self.iter_log_indices_causal_range(..)
+                .take_while(|(_, i)| *i != idx)
+                .filter(|(_, i)| self.is_visible_element(*i))
+                .count()
+        })
+    }
+}
+
+impl<A, T> Chronofold<A, T> {
+    /// Notifies the version observer, or defers it until the end of the
+    /// current `RemoteBatch`.
+    pub(crate) fn version_changed(&mut self) {
+        if self.remote_batch.is_some() {
+            self.observers.pending = true;
+        } else if let Some(observer) = &self.observers.version {
+            observer(&self.version);
+        }
+    }
+
+    /// Notifies the version observer about progress made during a
+    /// `RemoteBatch`.
+    pub(crate) fn flush_version_observer(&mut self) {
+        if std::mem::take(&mut self.observers.pending) {
+            self.version_changed();
+        }
+    }
+}
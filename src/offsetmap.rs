@@ -1,5 +1,7 @@
 use std::collections::BTreeMap;
 
+use crate::index::OffsetOverflow;
+
 /// A map from `K` to `K` with a default value of `O::default().add(key)`.
 ///
 /// For a chronofold, there are two cases where a structure like this makes
@@ -16,9 +18,22 @@ pub(crate) struct OffsetMap<K: Ord, O> {
     map: BTreeMap<K, Option<O>>,
 }
 
+/// An offset between two keys.
+///
+/// Offsets are bounded, so adding and subtracting are checked. The unchecked
+/// variants are for keys known to be in range, e.g. log indices of the log.
 pub(crate) trait Offset<K>: Default {
-    fn add(&self, value: &K) -> K;
-    fn sub(a: &K, b: &K) -> Self;
+    fn checked_add(&self, value: &K) -> Result<K, OffsetOverflow>;
+    fn checked_sub(a: &K, b: &K) -> Result<Self, OffsetOverflow>;
+
+    fn add(&self, value: &K) -> K {
+        self.checked_add(value)
+            .expect("relative indices point into the log")
+    }
+
+    fn sub(a: &K, b: &K) -> Self {
+        Self::checked_sub(a, b).expect("log indices are in range")
+    }
 }
 
 impl<K: Ord, O> OffsetMap<K, O> {
@@ -0,0 +1,79 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Author, Chronofold, FromLocalValue, LocalIndex, Op, Version};
+
+/// A continuation token for fetching the next page of ops.
+///
+/// Tokens stay valid while ops are applied, so a client can catch up in
+/// several requests while the chronofold keeps changing. Compaction
+/// invalidates them.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PageToken(LocalIndex);
+
+/// A bounded batch of ops.
+///
+/// This struct is created by the `ops_page` method on `Chronofold`. See its
+/// documentation for more.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct OpsPage<A, V> {
+    pub ops: Vec<Op<A, V>>,
+    /// The token to fetch the next page, or `None` if this is the last page.
+    pub next: Option<PageToken>,
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns up to `max_ops` ops newer than `since` in log order, starting
+    /// at `token` (or at the beginning for `None`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_ops` is zero.
+    pub fn ops_page<'a, V>(
+        &'a self,
+        since: &Version<A>,
+        token: Option<PageToken>,
+        max_ops: usize,
+    ) -> OpsPage<A, V>
+    where
+        V: FromLocalValue<'a, A, T>,
+    {
+        assert!(max_ops > 0, "pages must not be empty");
+        let start = token.map_or(LocalIndex(0), |PageToken(idx)| idx);
+        let mut newer_ops = (start.0..)
+            .map(LocalIndex)
+            .zip(self.iter_ops(start..))
+            .filter(|(_, op)| !since.contains(&op.id));
+        let ops = newer_ops.by_ref().take(max_ops).map(|(_, op)| op).collect();
+        OpsPage {
+            ops,
+            next: newer_ops.next().map(|(idx, _)| PageToken(idx)),
+        }
+    }
+
+    /// Returns an iterator over batches of up to `max_ops` ops newer than
+    /// `since`.
+    ///
+    /// This is like `iter_newer_ops`, but doesn't require the receiver to
+    /// handle all ops at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_ops` is zero.
+    pub fn iter_ops_paginated<'a, V>(
+        &'a self,
+        since: &'a Version<A>,
+        max_ops: usize,
+    ) -> impl Iterator<Item = Vec<Op<A, V>>> + 'a
+    where
+        V: FromLocalValue<'a, A, T> + 'a,
+    {
+        let mut token = Some(None);
+        std::iter::from_fn(move || {
+            let page = self.ops_page(since, token.take()?, max_ops);
+            token = page.next.map(Some);
+            Some(page.ops).filter(|ops| !ops.is_empty())
+        })
+    }
+}
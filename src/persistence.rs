@@ -0,0 +1,98 @@
+//! Incremental persistence.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{AppliedSummary, Author, Chronofold, ChronofoldError, Op, Version};
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Serializes all ops newer than `version` as a sequence.
+    ///
+    /// Together with `apply_serialized_ops` this allows for periodic
+    /// persistence, writing only what changed since the last checkpoint
+    /// instead of the whole chronofold.
+    pub fn serialize_ops_since<S>(
+        &self,
+        version: &Version<A>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        A: Serialize,
+        T: Serialize,
+    {
+        serializer.collect_seq(self.iter_newer_ops::<&T>(version))
+    }
+
+    /// Deserializes a sequence of ops and applies them.
+    ///
+    /// Ops are applied while they are decoded, so the sequence is never held
+    /// in memory as a whole. Only ops that can't be applied yet are kept
+    /// and retried at the end.
+    ///
+    /// Ops that were applied before are skipped, so loading overlapping
+    /// checkpoints is fine. See `apply_iter` for details.
+    pub fn apply_serialized_ops<'de, D>(
+        &mut self,
+        deserializer: D,
+    ) -> Result<AppliedSummary<A, T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        A: Deserialize<'de>,
+        T: Deserialize<'de>,
+    {
+        deserializer.deserialize_seq(ApplyOps {
+            cfold: self,
+            _de: PhantomData,
+        })
+    }
+}
+
+/// Applies the elements of a sequence of ops as they are visited.
+struct ApplyOps<'a, 'de, A, T> {
+    cfold: &'a mut Chronofold<A, T>,
+    _de: PhantomData<&'de ()>,
+}
+
+impl<'de, A, T> Visitor<'de> for ApplyOps<'_, 'de, A, T>
+where
+    A: Author + Deserialize<'de>,
+    T: Deserialize<'de>,
+{
+    type Value = AppliedSummary<A, T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of ops")
+    }
+
+    fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+    where
+        S: SeqAccess<'de>,
+    {
+        let mut summary = AppliedSummary::default();
+        let mut pending = Vec::new();
+        while let Some(op) = seq.next_element::<Op<A, T>>()? {
+            match self.cfold.apply(op) {
+                Ok(()) => summary.applied += 1,
+                Err(ChronofoldError::AlreadyApplied(op)) => summary.duplicates.push(op.id),
+                Err(
+                    err @ (ChronofoldError::Rejected(..)
+                    | ChronofoldError::ExistingTimestamp(_)
+                    | ChronofoldError::ImplausibleReference(_)),
+                ) => summary.rejected.push(err),
+                Err(err) => pending.extend(err.into_op()),
+            }
+        }
+        if !pending.is_empty() {
+            let retried = self.cfold.apply_iter(pending);
+            summary.applied += retried.applied;
+            summary.duplicates.extend(retried.duplicates);
+            summary.deferred = retried.deferred;
+            summary.rejected.extend(retried.rejected);
+        }
+        Ok(summary)
+    }
+}
@@ -0,0 +1,264 @@
+//! Pluggable behaviour of a chronofold.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::{Author, Chronofold, ElementId, Op, Timestamp, Version};
+
+/// Determines the order of concurrent insertions referencing the same
+/// element.
+///
+/// Siblings comparing `Greater` are placed first in the weave. The default is
+/// `TimestampOrder`, i.e. the sibling with the greater timestamp wins.
+///
+/// **Note:** The ordering has to be a deterministic total order, and all
+/// replicas of a chronofold have to use the same one. Otherwise their states
+/// will diverge.
+pub trait SiblingOrder<A>: fmt::Debug + Send + Sync {
+    fn cmp(&self, a: &Timestamp<A>, b: &Timestamp<A>) -> Ordering;
+}
+
+/// Orders siblings by their timestamps, the greater timestamp comes first.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct TimestampOrder;
+
+impl<A: Ord> SiblingOrder<A> for TimestampOrder {
+    fn cmp(&self, a: &Timestamp<A>, b: &Timestamp<A>) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Validates ops before they are applied to a chronofold.
+///
+/// This is invoked by `Chronofold::apply` (and everything built on it) after
+/// the op's causal history was checked, but before the chronofold is
+/// mutated. Returning `Err(reason)` rejects the op with a
+/// `ChronofoldError::Rejected`. Local edits made through a `Session` are not
+/// validated.
+///
+/// Validators are implemented for all matching closures.
+pub trait Validator<A, T>: Send + Sync {
+    fn validate(&self, op: &Op<A, &T>) -> Result<(), String>;
+}
+
+impl<A, T, F> Validator<A, T> for F
+where
+    F: Fn(&Op<A, &T>) -> Result<(), String> + Send + Sync,
+{
+    fn validate(&self, op: &Op<A, &T>) -> Result<(), String> {
+        self(op)
+    }
+}
+
+/// A deleted element, as presented to a `RetentionPolicy`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Tombstone<A> {
+    /// The id of the deleted element.
+    pub element: ElementId<A>,
+    /// The timestamp of the (earliest) delete of the element.
+    pub deleted_by: Timestamp<A>,
+}
+
+/// Decides which deleted elements are dropped by `Chronofold::compact`.
+///
+/// Compaction only ever considers elements whose deletion is part of the
+/// stable version passed to it. The policy can retain them even longer. If no
+/// policy is set, `KeepUntilStable` is used.
+///
+/// Like `SiblingOrder`, all replicas should use the same policy, so they
+/// compact the same prefix of their history.
+pub trait RetentionPolicy<A>: Send + Sync {
+    /// Returns `true` if the tombstone may be removed from the log when
+    /// compacting at the `stable` version.
+    fn may_drop(&self, tombstone: &Tombstone<A>, stable: &Version<A>) -> bool;
+}
+
+/// Keeps all tombstones, i.e. disables compaction.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct KeepForever;
+
+impl<A> RetentionPolicy<A> for KeepForever {
+    fn may_drop(&self, _tombstone: &Tombstone<A>, _stable: &Version<A>) -> bool {
+        false
+    }
+}
+
+/// Keeps tombstones until their deletion is part of the stable version.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct KeepUntilStable;
+
+impl<A> RetentionPolicy<A> for KeepUntilStable {
+    fn may_drop(&self, _tombstone: &Tombstone<A>, _stable: &Version<A>) -> bool {
+        true
+    }
+}
+
+/// Keeps tombstones for a fixed duration after their deletion.
+///
+/// Ops don't carry wall-clock times, so the time of an op is looked up in the
+/// application's op metadata using the given function. A deletion's age is
+/// measured up to the newest op of the stable version rather than the local
+/// clock, so replicas compacting at the same stable version drop the same
+/// tombstones. Tombstones are kept if their deletion time or the times of
+/// all ops in the stable version are unknown.
+pub struct KeepFor<A> {
+    duration: Duration,
+    time_of: Box<OpTime<A>>,
+}
+
+type OpTime<A> = dyn Fn(&Timestamp<A>) -> Option<SystemTime> + Send + Sync;
+
+impl<A> KeepFor<A> {
+    pub fn new(
+        duration: Duration,
+        time_of: impl Fn(&Timestamp<A>) -> Option<SystemTime> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            duration,
+            time_of: Box::new(time_of),
+        }
+    }
+}
+
+impl<A> fmt::Debug for KeepFor<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeepFor")
+            .field("duration", &self.duration)
+            .finish()
+    }
+}
+
+impl<A: Author> RetentionPolicy<A> for KeepFor<A> {
+    fn may_drop(&self, tombstone: &Tombstone<A>, stable: &Version<A>) -> bool {
+        let Some(deleted_at) = (self.time_of)(&tombstone.deleted_by) else {
+            return false;
+        };
+        stable
+            .iter()
+            .filter_map(|t| (self.time_of)(&t))
+            .max()
+            .and_then(|now| now.duration_since(deleted_at).ok())
+            .is_some_and(|age| age >= self.duration)
+    }
+}
+
+/// The policies configured for a chronofold.
+///
+/// Policies are not part of the chronofold's state, i.e. they are neither
+/// compared nor serialized.
+pub(crate) struct Policies<A, T> {
+    /// A custom sibling order, `None` means `TimestampOrder`.
+    pub(crate) sibling_order: Option<Arc<dyn SiblingOrder<A>>>,
+    pub(crate) validator: Option<Arc<dyn Validator<A, T>>>,
+    /// The retention policy, `None` means `KeepUntilStable`.
+    pub(crate) retention: Option<Arc<dyn RetentionPolicy<A>>>,
+    /// Whether references are checked for plausibility.
+    pub(crate) strict: bool,
+    /// Whether concurrent insertions are recorded as conflicts.
+    pub(crate) track_conflicts: bool,
+}
+
+// Deriving would require `A: Clone` and `T: Clone`.
+impl<A, T> Clone for Policies<A, T> {
+    fn clone(&self) -> Self {
+        Self {
+            sibling_order: self.sibling_order.clone(),
+            validator: self.validator.clone(),
+            retention: self.retention.clone(),
+            strict: self.strict,
+            track_conflicts: self.track_conflicts,
+        }
+    }
+}
+
+impl<A, T> Default for Policies<A, T> {
+    fn default() -> Self {
+        Self {
+            sibling_order: None,
+            validator: None,
+            retention: None,
+            strict: false,
+            track_conflicts: false,
+        }
+    }
+}
+
+impl<A, T> fmt::Debug for Policies<A, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Policies")
+            .field("sibling_order", &self.sibling_order)
+            .field("validator", &self.validator.as_ref().map(|_| ".."))
+            .field("retention", &self.retention.as_ref().map(|_| ".."))
+            .field("strict", &self.strict)
+            .field("track_conflicts", &self.track_conflicts)
+            .finish()
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Compares two sibling insertions using the configured `SiblingOrder`.
+    pub(crate) fn cmp_siblings(&self, a: &Timestamp<A>, b: &Timestamp<A>) -> Ordering {
+        match &self.policies.sibling_order {
+            Some(order) => order.cmp(a, b),
+            None => a.cmp(b),
+        }
+    }
+
+    /// Asks the configured `RetentionPolicy` whether a tombstone may be
+    /// dropped at the `stable` version.
+    pub(crate) fn may_drop(&self, tombstone: &Tombstone<A>, stable: &Version<A>) -> bool {
+        match &self.policies.retention {
+            Some(policy) => policy.may_drop(tombstone, stable),
+            None => true,
+        }
+    }
+
+    /// Sets the order of concurrent insertions referencing the same element.
+    ///
+    /// This should be set right after constructing the chronofold, as
+    /// changing it later only affects future insertions. See `SiblingOrder`
+    /// for details.
+    pub fn set_sibling_order(&mut self, order: impl SiblingOrder<A> + 'static) {
+        self.policies.sibling_order = Some(Arc::new(order));
+    }
+
+    /// Sets a validator for ops applied to this chronofold.
+    ///
+    /// See `Validator` for details.
+    pub fn set_validator(&mut self, validator: impl Validator<A, T> + 'static) {
+        self.policies.validator = Some(Arc::new(validator));
+    }
+
+    /// Removes the validator, if any.
+    pub fn remove_validator(&mut self) {
+        self.policies.validator = None;
+    }
+
+    /// Sets the policy deciding which tombstones are dropped by `compact`.
+    ///
+    /// See `RetentionPolicy` for details.
+    pub fn set_retention_policy(&mut self, policy: impl RetentionPolicy<A> + 'static) {
+        self.policies.retention = Some(Arc::new(policy));
+    }
+
+    /// Enables or disables strict mode, which is disabled by default.
+    ///
+    /// In strict mode, remote ops must reference a change that precedes them
+    /// in their author's history. As an author's ops get author indices
+    /// greater than those of all changes it has seen, a reference with an
+    /// author index not smaller than the op's can only be fabricated, e.g.
+    /// pointing to a future or concurrent op. Such ops are rejected with
+    /// `ChronofoldError::ImplausibleReference`.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.policies.strict = strict;
+    }
+
+    /// Enables or disables conflict tracking, which is disabled by default.
+    ///
+    /// See `ConflictRegion` for details.
+    pub fn set_conflict_tracking(&mut self, enabled: bool) {
+        self.policies.track_conflicts = enabled;
+    }
+}
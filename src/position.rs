@@ -0,0 +1,171 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::ops::{Bound, Index, Range, RangeBounds};
+
+use crate::{Author, Change, Chronofold, LocalIndex, Version};
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Maps a position recorded at version `since` to its current position.
+    ///
+    /// A position is the 0-based index of an element in the sequence of
+    /// visible elements. Positions are what editors work with (selections,
+    /// folds, markers), but unlike log indices they shift when ops are
+    /// applied. Use this function to update them after applying remote ops.
+    ///
+    /// If the element at `position` was deleted since, the position of the
+    /// next remaining element is returned. Positions past the end map to the
+    /// current end.
+    ///
+    /// Anchors kept across edits don't need transforming if they are stored
+    /// as `ElementId`s, see `id_at` and `position_of`.
+    pub fn transform_index(&self, position: usize, since: &Version<A>) -> usize {
+        let existed = |idx| self.timestamp(idx).is_some_and(|t| since.contains(&t));
+        let deleted_since = self.deleted_elements(existed);
+        let deleted_now = self.deleted_elements(|_| true);
+
+        let mut old_position = 0;
+        let mut new_position = 0;
+        for (change, idx) in self.iter_log_indices_causal_range(..) {
+            if !matches!(change, Change::Insert(_)) {
+                continue;
+            }
+            if existed(idx) && !deleted_since.contains(&idx) {
+                if old_position == position {
+                    return new_position;
+                }
+                old_position += 1;
+            }
+            if !deleted_now.contains(&idx) {
+                new_position += 1;
+            }
+        }
+        new_position
+    }
+
+    /// Returns the log indices of elements deleted by deletes matching
+    /// `filter`.
+    fn deleted_elements(&self, filter: impl Fn(LocalIndex) -> bool) -> HashSet<LocalIndex> {
+        self.log
+            .iter()
+            .enumerate()
+            .map(|(idx, change)| (LocalIndex(idx), change))
+            .filter(|(idx, change)| matches!(change, Change::Delete) && filter(*idx))
+            .filter_map(|(idx, _)| {
+                // Deletes might reference other deletes of the same element.
+                let mut target = self.get_reference(&idx)?;
+                while let Some(Change::Delete) = self.log.get(target.0) {
+                    target = self.get_reference(&target)?;
+                }
+                Some(target)
+            })
+            .collect()
+    }
+}
+
+impl<A, T> Chronofold<A, T> {
+    /// Returns an iterator over the visible elements at the positions in
+    /// `range`.
+    ///
+    /// Finding the first element doesn't walk every single log entry before
+    /// it.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> impl Iterator<Item = &T> {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let len = match range.end_bound() {
+            Bound::Included(end) => (end + 1).saturating_sub(start),
+            Bound::Excluded(end) => end.saturating_sub(start),
+            Bound::Unbounded => usize::MAX,
+        };
+        let mut iter = self.iter();
+        if start > 0 {
+            iter.nth(start - 1);
+        }
+        iter.take(len).map(|(value, _)| value)
+    }
+
+    /// Returns the index of the first element for which `pred` returns
+    /// `false`, assuming the visible elements are partitioned by it, like
+    /// `slice::partition_point`.
+    pub fn partition_point<P>(&self, mut pred: P) -> usize
+    where
+        P: FnMut(&T) -> bool,
+    {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if pred(self.element_at(mid)) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    /// Binary searches the visible elements, assuming they are sorted, like
+    /// `slice::binary_search_by`.
+    ///
+    /// Returns the position of a matching element, or the position where
+    /// one could be inserted.
+    pub fn binary_search_by<F>(&self, mut f: F) -> Result<usize, usize>
+    where
+        F: FnMut(&T) -> Ordering,
+    {
+        let position = self.partition_point(|value| f(value) == Ordering::Less);
+        match self.slice(position..).next().map(f) {
+            Some(Ordering::Equal) => Ok(position),
+            _ => Err(position),
+        }
+    }
+
+    /// Returns the log index of the visible element at `position`.
+    ///
+    /// Returns `None` if `position` is out of bounds.
+    pub fn position_to_index(&self, position: usize) -> Option<LocalIndex> {
+        self.iter().nth(position).map(|(_, idx)| idx)
+    }
+
+    /// Returns the position of the element at `index` among the visible
+    /// elements.
+    ///
+    /// Returns `None` if `index` is out of bounds or not a visible element,
+    /// e.g. because it was deleted.
+    pub fn index_to_position(&self, index: LocalIndex) -> Option<usize> {
+        if !matches!(self.log.get(index.0), Some(Change::Insert(_))) || self.is_invisible(index) {
+            return None;
+        }
+        let position = self
+            .count_visible_before(index)
+            .unwrap_or_else(|| self.iter().take_while(|(_, idx)| *idx != index).count());
+        Some(position)
+    }
+
+    fn element_at(&self, position: usize) -> &T {
+        let (value, _) = self
+            .iter()
+            .nth(position)
+            .expect("positions below len are visible");
+        value
+    }
+}
+
+impl<A> Chronofold<A, char> {
+    /// Returns the text at the positions in `range`.
+    pub fn slice_to_string(&self, range: impl RangeBounds<usize>) -> String {
+        self.slice(range).collect()
+    }
+}
+
+/// Indexes the visible elements by position, like a `Vec`.
+///
+/// This uses the cache of `as_vec_cached`.
+impl<A, T: Clone> Index<Range<usize>> for Chronofold<A, T> {
+    type Output = [T];
+
+    fn index(&self, range: Range<usize>) -> &Self::Output {
+        &self.as_vec_cached()[range]
+    }
+}
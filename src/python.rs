@@ -0,0 +1,163 @@
+//! Python bindings.
+//!
+//! Exposes chronofolds of text as the Python module `chronofold`. Authors
+//! are integers, ops and versions are passed around as CBOR-encoded bytes.
+//!
+//! Build the extension module with
+//! `cargo rustc --release --lib --crate-type cdylib --features python`.
+
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::{from_cbor_slice, to_cbor_bytes, Chronofold, FormatError, LocalIndex, Op, Version};
+
+type Text = Chronofold<usize, char>;
+
+impl From<FormatError> for PyErr {
+    fn from(err: FormatError) -> Self {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+/// A replicated text.
+#[pyclass(name = "Chronofold")]
+pub struct PyChronofold {
+    inner: Text,
+}
+
+#[pymethods]
+impl PyChronofold {
+    #[new]
+    #[pyo3(signature = (author = 0))]
+    fn new(author: usize) -> Self {
+        Self {
+            inner: Chronofold::new(author),
+        }
+    }
+
+    fn __str__(&self) -> String {
+        self.inner.to_string()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Starts an editing session for `author`.
+    fn session(slf: Py<Self>, author: usize) -> PySession {
+        PySession {
+            chronofold: slf,
+            author,
+        }
+    }
+
+    /// Returns the version as bytes.
+    fn version<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        Ok(PyBytes::new(py, &to_cbor_bytes(self.inner.version())?))
+    }
+
+    /// Returns the ops newer than `version` (all ops if `None`), each as
+    /// bytes.
+    #[pyo3(signature = (version = None))]
+    fn ops_since<'py>(
+        &self,
+        py: Python<'py>,
+        version: Option<&[u8]>,
+    ) -> PyResult<Vec<Bound<'py, PyBytes>>> {
+        let version: Version<usize> = match version {
+            Some(bytes) => from_cbor_slice(bytes)?,
+            None => Version::new(),
+        };
+        self.inner
+            .iter_newer_ops::<&char>(&version)
+            .map(|op| Ok(PyBytes::new(py, &to_cbor_bytes(&op)?)))
+            .collect()
+    }
+
+    /// Applies an op received as bytes.
+    fn apply(&mut self, op: &[u8]) -> PyResult<()> {
+        let op: Op<usize, char> = from_cbor_slice(op)?;
+        self.inner
+            .apply(op)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Applies ops received as bytes in any order, and returns the number of
+    /// ops applied. See `apply_iter`.
+    fn apply_ops(&mut self, ops: Vec<Vec<u8>>) -> PyResult<usize> {
+        let ops = ops
+            .iter()
+            .map(|op| from_cbor_slice::<Op<usize, char>>(op))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self.inner.apply_iter(ops).applied)
+    }
+}
+
+/// An editing session for one author.
+///
+/// Edits are addressed by position in the visible text.
+#[pyclass(name = "Session")]
+pub struct PySession {
+    chronofold: Py<PyChronofold>,
+    author: usize,
+}
+
+#[pymethods]
+impl PySession {
+    /// Inserts `text` at `position`.
+    fn insert(&self, py: Python<'_>, position: usize, text: &str) -> PyResult<()> {
+        let mut cfold = self.chronofold.borrow_mut(py);
+        let mut reference = element_before(&cfold.inner, position)?;
+        let mut session = cfold.inner.session(self.author);
+        for c in text.chars() {
+            reference = session.insert_after(reference, c);
+        }
+        Ok(())
+    }
+
+    /// Appends `text`.
+    fn push(&self, py: Python<'_>, text: &str) {
+        let mut cfold = self.chronofold.borrow_mut(py);
+        cfold.inner.session(self.author).extend(text.chars());
+    }
+
+    /// Deletes `length` elements from `position` on.
+    fn delete(&self, py: Python<'_>, position: usize, length: usize) -> PyResult<()> {
+        let mut cfold = self.chronofold.borrow_mut(py);
+        let indices: Vec<LocalIndex> = cfold
+            .inner
+            .iter()
+            .skip(position)
+            .take(length)
+            .map(|(_, idx)| idx)
+            .collect();
+        if indices.len() < length {
+            return Err(PyIndexError::new_err("deletion out of range"));
+        }
+        let mut session = cfold.inner.session(self.author);
+        for idx in indices {
+            session.remove(idx);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the log index of the element before `position`, or the root.
+fn element_before(cfold: &Text, position: usize) -> PyResult<LocalIndex> {
+    match position.checked_sub(1) {
+        None => Ok(cfold.root),
+        Some(previous) => cfold
+            .iter()
+            .nth(previous)
+            .map(|(_, idx)| idx)
+            .ok_or_else(|| PyIndexError::new_err("position out of range")),
+    }
+}
+
+#[pymodule]
+fn chronofold(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyChronofold>()?;
+    m.add_class::<PySession>()?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! Relaying ops without knowing their values.
+
+use std::collections::BTreeSet;
+
+use crate::{Author, Op, OpPayload, Timestamp, Version};
+
+/// An op whose value is kept as encoded bytes.
+///
+/// Relays only need an op's id and reference, so clients exchange opaque
+/// ops with them and encode values however they like.
+pub type OpaqueOp<A> = Op<A, Vec<u8>>;
+
+impl<A, T> Op<A, T> {
+    /// Converts the op into an `OpaqueOp` by encoding its value.
+    pub fn into_opaque<E>(
+        self,
+        encode: impl FnOnce(T) -> Result<Vec<u8>, E>,
+    ) -> Result<OpaqueOp<A>, E> {
+        use OpPayload::*;
+        let payload = match self.payload {
+            Root => Root,
+            Insert(reference, value) => Insert(reference, encode(value)?),
+            Delete(reference) => Delete(reference),
+        };
+        Ok(Op::new(self.id, payload))
+    }
+}
+
+impl<A> OpaqueOp<A> {
+    /// Converts an opaque op back by decoding its value.
+    pub fn decode<T, E>(self, decode: impl FnOnce(&[u8]) -> Result<T, E>) -> Result<Op<A, T>, E> {
+        use OpPayload::*;
+        let payload = match self.payload {
+            Root => Root,
+            Insert(reference, bytes) => Insert(reference, decode(&bytes)?),
+            Delete(reference) => Delete(reference),
+        };
+        Ok(Op::new(self.id, payload))
+    }
+}
+
+/// Stores and forwards opaque ops, e.g. on a server between clients.
+///
+/// Ops are deduplicated by id and kept in causal order: An op is only
+/// stored once the op it references is, and once enough ops are stored for
+/// its author index. Until then it's pending. Ops forwarded by `ops_since`
+/// can therefore be applied in order.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "A: serde::Serialize, Version<A>: serde::Serialize",
+        deserialize = "A: Author + serde::Deserialize<'de>, Version<A>: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Relay<A> {
+    ops: Vec<OpaqueOp<A>>,
+    pending: Vec<OpaqueOp<A>>,
+    ids: BTreeSet<Timestamp<A>>,
+    version: Version<A>,
+}
+
+impl<A: Author> Default for Relay<A> {
+    fn default() -> Self {
+        Self {
+            ops: Vec::new(),
+            pending: Vec::new(),
+            ids: BTreeSet::new(),
+            version: Version::new(),
+        }
+    }
+}
+
+impl<A: Author> Relay<A> {
+    /// Creates an empty relay.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Receives an op and returns the number of ops stored as a result.
+    ///
+    /// This is 0 for duplicates and ops whose reference is missing, and can
+    /// be more than 1 if the op was referenced by pending ops.
+    pub fn receive(&mut self, op: OpaqueOp<A>) -> usize {
+        if self.contains(&op.id) {
+            return 0;
+        }
+        if !self.is_ready(&op) {
+            self.pending.push(op);
+            return 0;
+        }
+        self.store(op);
+
+        let mut stored = 1;
+        loop {
+            let ready = self.pending.iter().position(|op| self.is_ready(op));
+            match ready {
+                Some(idx) => {
+                    let op = self.pending.swap_remove(idx);
+                    self.store(op);
+                    stored += 1;
+                }
+                None => break stored,
+            }
+        }
+    }
+
+    /// Receives ops in any order and returns the number of ops stored.
+    pub fn receive_all(&mut self, ops: impl IntoIterator<Item = OpaqueOp<A>>) -> usize {
+        ops.into_iter().map(|op| self.receive(op)).sum()
+    }
+
+    /// Returns `true` if the op with the given id was received, stored or
+    /// pending.
+    pub fn contains(&self, id: &Timestamp<A>) -> bool {
+        self.ids.contains(id) || self.pending.iter().any(|op| op.id == *id)
+    }
+
+    /// Returns the version of the stored ops.
+    pub fn version(&self) -> &Version<A> {
+        &self.version
+    }
+
+    /// Returns the stored ops in the order they were stored.
+    pub fn ops(&self) -> &[OpaqueOp<A>] {
+        &self.ops
+    }
+
+    /// Returns the ops waiting for the ops they reference.
+    pub fn pending(&self) -> &[OpaqueOp<A>] {
+        &self.pending
+    }
+
+    /// Returns an iterator over the stored ops newer than `version`, to
+    /// forward to a client.
+    pub fn ops_since<'a>(
+        &'a self,
+        version: &'a Version<A>,
+    ) -> impl Iterator<Item = &'a OpaqueOp<A>> + 'a {
+        self.ops.iter().filter(move |op| !version.contains(&op.id))
+    }
+
+    /// Returns `true` if `op` can be applied after the stored ops, the same
+    /// way `Chronofold::apply` checks it.
+    fn is_ready(&self, op: &OpaqueOp<A>) -> bool {
+        op.id.idx.0 <= self.ops.len()
+            && op
+                .payload
+                .reference()
+                .is_none_or(|reference| self.ids.contains(reference))
+    }
+
+    fn store(&mut self, op: OpaqueOp<A>) {
+        self.version.inc(&op.id);
+        self.ids.insert(op.id.clone());
+        self.ops.push(op);
+    }
+}
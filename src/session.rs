@@ -1,6 +1,21 @@
-use std::ops::{Bound, RangeBounds};
+use std::ops::{Bound, Range, RangeBounds};
+use std::time::{Duration, Instant};
 
-use crate::{Author, Change, Chronofold, FromLocalValue, LocalIndex, Op, Timestamp, AuthorIndex};
+use crate::{
+    coalesce_ops, Author, AuthorIndex, Change, Chronofold, CoalescedOp, FromLocalValue, Iter,
+    LocalIndex, Op, Timestamp,
+};
+
+/// When a `Session` emits its staged ops as a batch.
+///
+/// The default only flushes explicitly.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct FlushPolicy {
+    /// Flush once this many ops are staged.
+    pub max_ops: Option<usize>,
+    /// Flush once the oldest staged op is this old.
+    pub max_latency: Option<Duration>,
+}
 
 /// An editing session tied to one author.
 ///
@@ -16,6 +31,14 @@ pub struct Session<'a, A, T> {
     chronofold: &'a mut Chronofold<A, T>,
     author: A,
     first_index: LocalIndex,
+    coalescing: bool,
+    flush_policy: FlushPolicy,
+    /// The first log index that wasn't flushed yet.
+    flushed: LocalIndex,
+    /// When the oldest unflushed change was made.
+    staged_since: Option<Instant>,
+    /// The log index of the last element inserted in this session.
+    last_insert: Option<LocalIndex>,
 }
 
 impl<'a, A: Author, T> Session<'a, A, T> {
@@ -26,6 +49,163 @@ impl<'a, A: Author, T> Session<'a, A, T> {
             chronofold,
             author,
             first_index,
+            coalescing: false,
+            flush_policy: FlushPolicy::default(),
+            flushed: first_index,
+            staged_since: None,
+            last_insert: None,
+        }
+    }
+
+    /// Returns the session's author.
+    pub fn author(&self) -> A {
+        self.author.clone()
+    }
+
+    /// Returns the number of elements, see `Chronofold::len`.
+    pub fn len(&self) -> usize {
+        self.chronofold.len()
+    }
+
+    /// Returns `true` if there are no elements, see `Chronofold::is_empty`.
+    pub fn is_empty(&self) -> bool {
+        self.chronofold.is_empty()
+    }
+
+    /// Returns a reference to a change, see `Chronofold::get`.
+    pub fn get(&self, index: LocalIndex) -> Option<&Change<T>> {
+        self.chronofold.get(index)
+    }
+
+    /// Returns an iterator over elements and their log indices, see
+    /// `Chronofold::iter`.
+    pub fn iter(&self) -> Iter<'_, A, T> {
+        self.chronofold.iter()
+    }
+
+    /// Returns an iterator over elements, see `Chronofold::iter_elements`.
+    pub fn iter_elements(&self) -> impl Iterator<Item = &T> {
+        self.chronofold.iter_elements()
+    }
+
+    /// Returns the log index of the element at `position`, see
+    /// `Chronofold::position_to_index`.
+    pub fn position_to_index(&self, position: usize) -> Option<LocalIndex> {
+        self.chronofold.position_to_index(position)
+    }
+
+    /// Returns the position of the element at `index`, see
+    /// `Chronofold::index_to_position`.
+    pub fn index_to_position(&self, index: LocalIndex) -> Option<usize> {
+        self.chronofold.index_to_position(index)
+    }
+
+    /// Enables or disables coalescing of this session's edits.
+    ///
+    /// With coalescing, removing an element that was just inserted in this
+    /// session (and nothing else was changed since) takes back the insertion
+    /// instead of recording a deletion. Typing mistakes that are immediately
+    /// corrected then never show up in the ops.
+    ///
+    /// Only enable this if the session's ops are sent after the session,
+    /// e.g. using `coalesced_ops`. Note that a cancelled element's log
+    /// index is reused by the next change.
+    pub fn set_coalescing(&mut self, coalescing: bool) {
+        self.coalescing = coalescing;
+    }
+
+    /// Sets when staged ops are emitted by `poll_flush`.
+    pub fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.flush_policy = policy;
+    }
+
+    /// Returns the number of ops made in this session since the last flush.
+    pub fn staged_len(&self) -> usize {
+        (self.flushed.0..self.chronofold.log.len())
+            .filter(|idx| self.chronofold.get_author(&LocalIndex(*idx)) == Some(&self.author))
+            .count()
+    }
+
+    /// Returns the number of ops made in this session.
+    ///
+    /// Insertions taken back by coalescing and ops of other authors applied
+    /// through `as_mut` don't count.
+    pub fn op_count(&self) -> usize {
+        self.own_indices().count()
+    }
+
+    /// Returns the range of author indices spanned by the ops made in this
+    /// session.
+    ///
+    /// The range starts at the session's first op and ends after its last
+    /// one. It's empty, starting at the next author index, if no ops were
+    /// made. Ops of other authors applied through `as_mut` during the session
+    /// may be interleaved, so the range can be longer than `op_count`. Offline
+    /// clients can persist it together with the session's author to track
+    /// which ops were sent.
+    pub fn authored_range(&self) -> Range<AuthorIndex> {
+        let mut indices = self.own_indices();
+        match indices.next() {
+            Some(first) => {
+                let last = indices.last().unwrap_or(first);
+                self.chronofold.local_author_index(first)
+                    ..self.chronofold.local_author_index(LocalIndex(last.0 + 1))
+            }
+            None => {
+                let next = self
+                    .chronofold
+                    .local_author_index(self.chronofold.next_log_index());
+                next..next
+            }
+        }
+    }
+
+    /// Returns the log indices of the ops made in this session.
+    fn own_indices(&self) -> impl Iterator<Item = LocalIndex> + '_ {
+        (self.first_index.0..self.chronofold.log.len())
+            .map(LocalIndex)
+            .filter(move |idx| self.chronofold.get_author(idx) == Some(&self.author))
+    }
+
+    /// Returns the ops made in this session since the last flush, as one
+    /// batch.
+    ///
+    /// Flushed ops are considered sent, so coalescing won't take them back
+    /// anymore.
+    pub fn flush(&mut self) -> Vec<Op<A, T>>
+    where
+        T: Clone,
+    {
+        let ops = self
+            .chronofold
+            .iter_ops(self.flushed..)
+            .filter(|op| op.id.author == self.author)
+            .map(Op::cloned)
+            .collect();
+        self.flushed = self.chronofold.next_log_index();
+        self.staged_since = None;
+        ops
+    }
+
+    /// Flushes the staged ops if the `FlushPolicy` says so.
+    pub fn poll_flush(&mut self, now: Instant) -> Option<Vec<Op<A, T>>>
+    where
+        T: Clone,
+    {
+        let FlushPolicy {
+            max_ops,
+            max_latency,
+        } = self.flush_policy;
+        let staged = self.staged_since.is_some();
+        let full = max_ops.is_some_and(|max_ops| staged && self.staged_len() >= max_ops);
+        let late = match (self.staged_since, max_latency) {
+            (Some(since), Some(max_latency)) => now.saturating_duration_since(since) >= max_latency,
+            _ => false,
+        };
+        if full || late {
+            Some(self.flush())
+        } else {
+            None
         }
     }
 
@@ -44,10 +224,9 @@ impl<'a, A: Author, T> Session<'a, A, T> {
     /// Appends an element to the back of the chronofold and returns the new
     /// element's log index.
     pub fn push_back(&mut self, value: T) -> LocalIndex {
-        let index = self.chronofold.iter().last()
-            .map_or_else(
-                || self.as_ref().root, // no non-deleted entries left
-                |(_, last_index)| last_index,
+        let index = self.chronofold.iter().last().map_or_else(
+            || self.as_ref().root, // no non-deleted entries left
+            |(_, last_index)| last_index,
         );
         self.insert_after(index, value)
     }
@@ -71,7 +250,67 @@ impl<'a, A: Author, T> Session<'a, A, T> {
     /// Note that this just marks the element as deleted, not actually modify
     /// the log apart from appending a `Change::Delete`.
     pub fn remove(&mut self, index: LocalIndex) {
-        self.apply_change(index, Change::Delete);
+        if self.cancel(index).is_none() {
+            self.apply_change(index, Change::Delete);
+        }
+    }
+
+    /// Inserts an element after the element inserted at `timestamp` and
+    /// returns the new element's log index.
+    ///
+    /// This is `insert_after_id` for callers holding a raw timestamp.
+    /// Returns `None` if no element with this timestamp is known.
+    pub fn insert_after_timestamp(
+        &mut self,
+        timestamp: &Timestamp<A>,
+        value: T,
+    ) -> Option<LocalIndex> {
+        let index = self.as_ref().resolve(&timestamp.clone().into())?;
+        Some(self.insert_after(index, value))
+    }
+
+    /// Removes the element inserted at `timestamp` from the chronofold.
+    ///
+    /// This is `remove_by_id` for callers holding a raw timestamp. Returns
+    /// `false` if no element with this timestamp is known.
+    pub fn remove_by_timestamp(&mut self, timestamp: &Timestamp<A>) -> bool {
+        self.remove_by_id(&timestamp.clone().into())
+    }
+
+    /// Replaces the element with log index `index` by `value` and returns the
+    /// new element's log index.
+    ///
+    /// This removes the element and inserts `value` in its place, so other
+    /// authors see the replacement as a deletion followed by an insertion.
+    pub fn replace(&mut self, index: LocalIndex, value: T) -> LocalIndex {
+        match self.cancel(index) {
+            Some(reference) => self.insert_after(reference, value),
+            None => {
+                self.apply_change(index, Change::Delete);
+                self.insert_after(index, value)
+            }
+        }
+    }
+
+    /// Swaps the elements with log indices `a` and `b` and returns the new
+    /// log indices of the elements now at `a`'s and `b`'s positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` don't refer to an element.
+    pub fn swap(&mut self, a: LocalIndex, b: LocalIndex) -> (LocalIndex, LocalIndex)
+    where
+        T: Clone,
+    {
+        if a == b {
+            return (a, b);
+        }
+        let value = |idx: LocalIndex| match self.chronofold.get(idx) {
+            Some(Change::Insert(v)) => v.clone(),
+            _ => panic!("no element at log index {}", idx),
+        };
+        let (value_a, value_b) = (value(a), value(b));
+        (self.replace(a, value_b), self.replace(b, value_a))
     }
 
     /// Extends the chronofold with the contents of `iter`, returns the log
@@ -84,14 +323,12 @@ impl<'a, A: Author, T> Session<'a, A, T> {
     /// Replaces the specified range in the chronofold with the given
     /// `replace_with` iterator and returns the log index of the last inserted
     /// element, if any.
-    pub fn splice(&mut self, range: impl RangeBounds<LocalIndex>, replace_with: impl IntoIterator<Item = T>) -> Option<LocalIndex>
-    {
-        let last_idx = match range.start_bound() {
-            Bound::Unbounded => None,
-            Bound::Included(idx) => self.chronofold.index_before(*idx),
-            Bound::Excluded(idx) => Some(*idx),
-        }
-        .unwrap_or_else(|| self.as_ref().root);
+    pub fn splice(
+        &mut self,
+        range: impl RangeBounds<LocalIndex>,
+        replace_with: impl IntoIterator<Item = T>,
+    ) -> Option<LocalIndex> {
+        let last_idx = self.splice_start(&range);
         let to_remove = self
             .chronofold
             .iter_range(range)
@@ -103,20 +340,115 @@ impl<'a, A: Author, T> Session<'a, A, T> {
         self.apply_changes(last_idx, replace_with.into_iter().map(Change::Insert))
     }
 
-    pub fn create_root(&mut self) -> LocalIndex {
-        let new_index = AuthorIndex(self.chronofold.log.len());
+    /// Replaces the specified range like `splice`, but only deletes and
+    /// inserts elements where the content actually differs.
+    ///
+    /// Elements that are equal in the existing range and in `replace_with`
+    /// are kept, so concurrent edits next to them are preserved. Returns the
+    /// log index of the last inserted element, if any.
+    ///
+    /// Note that finding the minimal difference takes quadratic time in the
+    /// length of the range (after skipping a common prefix and suffix).
+    pub fn splice_diff(
+        &mut self,
+        range: impl RangeBounds<LocalIndex>,
+        replace_with: impl IntoIterator<Item = T>,
+    ) -> Option<LocalIndex>
+    where
+        T: PartialEq,
+    {
+        let mut last_idx = self.splice_start(&range);
+        let old: Vec<(&T, LocalIndex)> = self.chronofold.iter_range(range).collect();
+        let new: Vec<T> = replace_with.into_iter().collect();
+        let script = diff(&old.iter().map(|(v, _)| *v).collect::<Vec<_>>(), &new);
+        let old: Vec<LocalIndex> = old.into_iter().map(|(_, idx)| idx).collect();
+
+        let mut new = new.into_iter();
+        let mut last_inserted = None;
+        for edit in script {
+            match edit {
+                Edit::Keep(i) => {
+                    new.next();
+                    last_idx = old[i];
+                }
+                Edit::Remove(i) => self.remove(old[i]),
+                Edit::Insert => {
+                    let value = new.next().expect("script matches `replace_with`");
+                    last_idx = self.insert_after(last_idx, value);
+                    last_inserted = Some(last_idx);
+                }
+            }
+        }
+        last_inserted
+    }
+
+    fn splice_start(&self, range: &impl RangeBounds<LocalIndex>) -> LocalIndex {
+        match range.start_bound() {
+            Bound::Unbounded => None,
+            // Out of bounds, the range starts after the last index.
+            Bound::Included(idx) if idx.0 >= self.chronofold.log.len() => self
+                .chronofold
+                .last_index()
+                .and_then(|last| self.chronofold.iter_log_indices_causal_range(last..).last())
+                .map(|(_, idx)| idx),
+            Bound::Included(idx) => self.chronofold.index_before(*idx),
+            Bound::Excluded(idx) => Some(*idx),
+        }
+        .unwrap_or_else(|| self.as_ref().root)
+    }
+
+    /// Takes back the insertion of the element at `index` if coalescing
+    /// allows it, returning the element's reference.
+    fn cancel(&mut self, index: LocalIndex) -> Option<LocalIndex> {
+        if !self.coalescing || index < self.flushed {
+            return None;
+        }
+        self.last_insert = None;
         self.chronofold
-            .apply_change(Timestamp::new(new_index, self.author), None, Change::Root)
+            .cancel_last_insert(self.author.clone(), index)
+    }
+
+    pub fn create_root(&mut self) -> LocalIndex {
+        self.stage();
+        let new_index = self
+            .chronofold
+            .local_author_index(self.chronofold.next_log_index());
+        self.chronofold.apply_change(
+            Timestamp::new(new_index, self.author.clone()),
+            None,
+            Change::Root,
+        )
     }
 
     fn apply_change(&mut self, reference: LocalIndex, change: Change<T>) -> LocalIndex {
         self.apply_changes(reference, Some(change)).unwrap()
     }
 
-    fn apply_changes(&mut self, reference: LocalIndex, changes: impl IntoIterator<Item = Change<T>>) -> Option<LocalIndex>
-    {
-        self.chronofold
-            .apply_local_changes(self.author, reference, changes)
+    fn apply_changes(
+        &mut self,
+        reference: LocalIndex,
+        changes: impl IntoIterator<Item = Change<T>>,
+    ) -> Option<LocalIndex> {
+        self.stage();
+        // Typing continues after the last inserted element. If that's still
+        // the last log entry, the changes can be appended right away.
+        let appending =
+            self.last_insert == Some(reference) && self.chronofold.last_index() == Some(reference);
+        let last_index = if appending {
+            self.chronofold
+                .append_local_changes(self.author.clone(), changes)
+        } else {
+            self.chronofold
+                .apply_local_changes(self.author.clone(), reference, changes)
+        }?;
+        self.last_insert =
+            Some(last_index).filter(|idx| matches!(self.chronofold.log[idx.0], Change::Insert(_)));
+        Some(last_index)
+    }
+
+    /// Records when the oldest unflushed change was made.
+    fn stage(&mut self) {
+        self.staged_since.get_or_insert_with(Instant::now);
     }
 
     /// Returns an iterator over ops in log order, that where created in this
@@ -129,6 +461,22 @@ impl<'a, A: Author, T> Session<'a, A, T> {
             .iter_ops(self.first_index..)
             .filter(move |op| op.id.author == self.author)
     }
+
+    /// Returns the ops created in this session, with runs of consecutively
+    /// typed elements merged into `InsertRun`s.
+    ///
+    /// See `coalesce_ops` for details.
+    pub fn coalesced_ops(&self) -> Vec<CoalescedOp<A, T>>
+    where
+        T: Clone,
+    {
+        coalesce_ops(
+            self.chronofold
+                .iter_ops(self.first_index..)
+                .filter(|op| op.id.author == self.author)
+                .map(Op::cloned),
+        )
+    }
 }
 
 impl<A: Author, T> AsRef<Chronofold<A, T>> for Session<'_, A, T> {
@@ -142,3 +490,92 @@ impl<A: Author, T> AsMut<Chronofold<A, T>> for Session<'_, A, T> {
         self.chronofold
     }
 }
+
+#[derive(PartialEq, Eq, Debug)]
+enum Edit {
+    /// Keep the old element at the given position.
+    Keep(usize),
+    /// Remove the old element at the given position.
+    Remove(usize),
+    /// Insert the next new element.
+    Insert,
+}
+
+/// Computes a minimal edit script transforming `old` into `new`.
+///
+/// This is the classic longest common subsequence approach, after trimming a
+/// common prefix and suffix.
+fn diff<T: PartialEq>(old: &[&T], new: &[T]) -> Vec<Edit> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| **a == *b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| **a == *b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    // lcs[i][j] is the length of the LCS of old_mid[i..] and new_mid[j..].
+    let (n, m) = (old_mid.len(), new_mid.len());
+    let mut lcs = vec![vec![0; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if *old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut script: Vec<Edit> = (0..prefix).map(Edit::Keep).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && *old_mid[i] == new_mid[j] {
+            script.push(Edit::Keep(prefix + i));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            script.push(Edit::Insert);
+            j += 1;
+        } else {
+            script.push(Edit::Remove(prefix + i));
+            i += 1;
+        }
+    }
+    script.extend((old.len() - suffix..old.len()).map(Edit::Keep));
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff() {
+        let old = "kitten".chars().collect::<Vec<_>>();
+        let new = "sitting".chars().collect::<Vec<_>>();
+        use Edit::*;
+        assert_eq!(
+            vec![
+                Insert,
+                Remove(0),
+                Keep(1),
+                Keep(2),
+                Keep(3),
+                Insert,
+                Remove(4),
+                Keep(5),
+                Insert
+            ],
+            super::diff(&old.iter().collect::<Vec<_>>(), &new)
+        );
+    }
+}
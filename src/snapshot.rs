@@ -0,0 +1,237 @@
+//! Binary snapshot files.
+//!
+//! A snapshot consists of a fixed header followed by the chronofold encoded
+//! as CBOR, which may be compressed:
+//!
+//! | bytes | content                                   |
+//! |-------|-------------------------------------------|
+//! | 4     | magic bytes `CFLD`                        |
+//! | 1     | format version                            |
+//! | 1     | compression (0 = none, 1 = LZ4)           |
+//! | 8     | FNV-1a checksum of the payload, LE        |
+//! | 8     | payload length in bytes, LE               |
+
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::hash::StableHasher;
+use crate::{from_cbor_slice, to_cbor_bytes, Author, Chronofold, ErrorCategory, FormatError};
+
+const MAGIC: &[u8; 4] = b"CFLD";
+const FORMAT_VERSION: u8 = 1;
+
+/// The compression applied to a snapshot's payload.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Compression {
+    None,
+    /// Requires the `snapshot-lz4` feature.
+    Lz4,
+}
+
+impl Compression {
+    fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Lz4),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors that can occur when saving or loading a snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The data doesn't start with the snapshot magic bytes.
+    InvalidMagic,
+    /// The snapshot was written by a newer format version.
+    UnsupportedVersion(u8),
+    /// The compression is unknown or its feature isn't enabled.
+    UnsupportedCompression(u8),
+    /// The payload doesn't match the checksum in the header.
+    ChecksumMismatch,
+    /// The data is encrypted with a key that isn't in the keyring.
+    UnknownKey(u32),
+    /// The ciphertext or its header don't authenticate.
+    DecryptionFailed,
+    Format(FormatError),
+}
+
+impl SnapshotError {
+    /// Returns the category of this error.
+    pub fn category(&self) -> ErrorCategory {
+        use SnapshotError::*;
+        match self {
+            Io(_) => ErrorCategory::Capacity,
+            UnsupportedVersion(_) | UnsupportedCompression(_) => ErrorCategory::Protocol,
+            InvalidMagic | ChecksumMismatch | DecryptionFailed => ErrorCategory::Integrity,
+            UnknownKey(_) => ErrorCategory::Policy,
+            Format(err) => err.category(),
+        }
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SnapshotError::*;
+        match self {
+            Io(err) => write!(f, "i/o error: {}", err),
+            InvalidMagic => write!(f, "not a chronofold snapshot"),
+            UnsupportedVersion(version) => write!(f, "unsupported snapshot version {}", version),
+            UnsupportedCompression(compression) => {
+                write!(f, "unsupported snapshot compression {}", compression)
+            }
+            ChecksumMismatch => write!(f, "snapshot checksum mismatch"),
+            UnknownKey(key_id) => write!(f, "unknown encryption key {}", key_id),
+            DecryptionFailed => write!(f, "decryption failed"),
+            Format(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(err) => Some(err),
+            SnapshotError::Format(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+impl From<FormatError> for SnapshotError {
+    fn from(err: FormatError) -> Self {
+        SnapshotError::Format(err)
+    }
+}
+
+impl<A, T> Chronofold<A, T>
+where
+    A: Author + Serialize,
+    T: Serialize,
+{
+    /// Writes an uncompressed snapshot to the file at `path`.
+    ///
+    /// The snapshot is written to a temporary file next to `path` first, so
+    /// an existing snapshot isn't corrupted if writing fails.
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        self.save_to_with(path, Compression::None)
+    }
+
+    /// Like `save_to`, but with the given compression.
+    pub fn save_to_with(
+        &self,
+        path: impl AsRef<Path>,
+        compression: Compression,
+    ) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        self.write_snapshot(&mut file, compression)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Writes a snapshot to `writer`.
+    pub fn write_snapshot(
+        &self,
+        mut writer: impl Write,
+        compression: Compression,
+    ) -> Result<(), SnapshotError> {
+        let payload = compress(to_cbor_bytes(self)?, compression)?;
+        let mut hasher = StableHasher::default();
+        hasher.write(&payload);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION, compression.to_byte()])?;
+        writer.write_all(&hasher.finish().to_le_bytes())?;
+        writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+        writer.write_all(&payload)?;
+        Ok(())
+    }
+}
+
+impl<A, T> Chronofold<A, T>
+where
+    A: Author + DeserializeOwned,
+    T: DeserializeOwned,
+{
+    /// Loads a snapshot from the file at `path`.
+    ///
+    /// Policies aren't part of snapshots and have to be set again.
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        Self::read_snapshot(io::BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Reads a snapshot from `reader`.
+    pub fn read_snapshot(mut reader: impl Read) -> Result<Self, SnapshotError> {
+        let mut header = [0; 22];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(SnapshotError::InvalidMagic);
+        }
+        if header[4] > FORMAT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(header[4]));
+        }
+        let compression = Compression::from_byte(header[5])
+            .ok_or(SnapshotError::UnsupportedCompression(header[5]))?;
+        let checksum = u64::from_le_bytes(header[6..14].try_into().expect("8 bytes"));
+        let len = u64::from_le_bytes(header[14..22].try_into().expect("8 bytes"));
+
+        let mut payload = Vec::new();
+        reader.take(len).read_to_end(&mut payload)?;
+        if (payload.len() as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let mut hasher = StableHasher::default();
+        hasher.write(&payload);
+        if hasher.finish() != checksum {
+            return Err(SnapshotError::ChecksumMismatch);
+        }
+        Ok(from_cbor_slice(&decompress(payload, compression)?)?)
+    }
+}
+
+fn compress(bytes: Vec<u8>, compression: Compression) -> Result<Vec<u8>, SnapshotError> {
+    match compression {
+        Compression::None => Ok(bytes),
+        #[cfg(feature = "snapshot-lz4")]
+        Compression::Lz4 => Ok(lz4_flex::compress_prepend_size(&bytes)),
+        #[cfg(not(feature = "snapshot-lz4"))]
+        Compression::Lz4 => Err(SnapshotError::UnsupportedCompression(compression.to_byte())),
+    }
+}
+
+fn decompress(bytes: Vec<u8>, compression: Compression) -> Result<Vec<u8>, SnapshotError> {
+    match compression {
+        Compression::None => Ok(bytes),
+        #[cfg(feature = "snapshot-lz4")]
+        Compression::Lz4 => lz4_flex::decompress_size_prepended(&bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err).into()),
+        #[cfg(not(feature = "snapshot-lz4"))]
+        Compression::Lz4 => Err(SnapshotError::UnsupportedCompression(compression.to_byte())),
+    }
+}
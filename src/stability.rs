@@ -0,0 +1,129 @@
+//! Agreeing on a stable version between replicas.
+//!
+//! Before compacting, replicas have to agree on a version everyone has seen,
+//! so they all compact the same prefix of their history. One replica proposes
+//! a version, all peers acknowledge the part of it they have applied, and
+//! once everyone answered, the greatest commonly acknowledged version is
+//! committed.
+
+use std::collections::BTreeMap;
+
+use crate::{Author, Version};
+
+/// A message of the stability protocol.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Version<A>: serde::Serialize",
+        deserialize = "Version<A>: serde::Deserialize<'de>"
+    ))
+)]
+pub enum StabilityMessage<A> {
+    /// Proposes a version to become stable, usually the sender's current
+    /// version.
+    Propose(Version<A>),
+    /// Acknowledges the part of a proposal the sender has applied.
+    Ack(Version<A>),
+    /// Announces a version every peer has acknowledged. Receivers can
+    /// compact up to it.
+    Commit(Version<A>),
+}
+
+impl<A: Author> StabilityMessage<A> {
+    /// Returns the answer to this message for a replica at `version`.
+    ///
+    /// Only `Propose` messages are answered, with an `Ack`.
+    pub fn reply(&self, version: &Version<A>) -> Option<StabilityMessage<A>> {
+        match self {
+            StabilityMessage::Propose(proposal) => {
+                Some(StabilityMessage::Ack(proposal.meet(version)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Tracks the acknowledgements of a proposed stable version.
+///
+/// The tracker belongs to the proposing replica. Its peers are identified by
+/// `P`, which is usually, but not necessarily, the author type.
+#[derive(Clone, Debug)]
+pub struct StabilityTracker<P, A> {
+    proposal: Option<Version<A>>,
+    acks: BTreeMap<P, Option<Version<A>>>,
+}
+
+impl<P: Ord, A: Author> StabilityTracker<P, A> {
+    /// Creates a tracker for the given peers.
+    pub fn new(peers: impl IntoIterator<Item = P>) -> Self {
+        Self {
+            proposal: None,
+            acks: peers.into_iter().map(|peer| (peer, None)).collect(),
+        }
+    }
+
+    /// Adds a peer, which has to acknowledge the current proposal, too.
+    pub fn add_peer(&mut self, peer: P) {
+        self.acks.entry(peer).or_insert(None);
+    }
+
+    /// Removes a peer, e.g. because it left for good.
+    pub fn remove_peer(&mut self, peer: &P) {
+        self.acks.remove(peer);
+    }
+
+    /// Returns the current proposal, if any.
+    pub fn proposal(&self) -> Option<&Version<A>> {
+        self.proposal.as_ref()
+    }
+
+    /// Starts a new proposal, discarding acknowledgements of earlier ones.
+    ///
+    /// Returns the message to send to all peers.
+    pub fn propose(&mut self, version: Version<A>) -> StabilityMessage<A> {
+        self.proposal = Some(version.clone());
+        for ack in self.acks.values_mut() {
+            *ack = None;
+        }
+        StabilityMessage::Propose(version)
+    }
+
+    /// Handles a message from a peer.
+    ///
+    /// Records acknowledgements of the current proposal. Messages from
+    /// unknown peers and other messages are ignored.
+    pub fn receive(&mut self, from: &P, message: StabilityMessage<A>) {
+        if let (StabilityMessage::Ack(version), Some(proposal)) = (message, &self.proposal) {
+            if let Some(ack) = self.acks.get_mut(from) {
+                *ack = Some(proposal.meet(&version));
+            }
+        }
+    }
+
+    /// Returns `true` if the peer acknowledged the current proposal.
+    pub fn has_acked(&self, peer: &P) -> bool {
+        matches!(self.acks.get(peer), Some(Some(_)))
+    }
+
+    /// Returns the greatest version acknowledged by all peers, or `None` if
+    /// some peers didn't answer yet.
+    pub fn stable_version(&self) -> Option<Version<A>> {
+        self.acks
+            .values()
+            .try_fold(self.proposal.clone()?, |stable, ack| {
+                Some(stable.meet(ack.as_ref()?))
+            })
+    }
+
+    /// Ends the current proposal if all peers acknowledged it.
+    ///
+    /// Returns the message to send to all peers. The stable version should
+    /// be compacted locally, too.
+    pub fn commit(&mut self) -> Option<StabilityMessage<A>> {
+        let stable = self.stable_version()?;
+        self.proposal = None;
+        Some(StabilityMessage::Commit(stable))
+    }
+}
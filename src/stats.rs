@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use crate::{Author, Change, Chronofold, LocalIndex};
+
+/// Statistics about the shape of a chronofold.
+///
+/// This struct is created by the `stats` method on `Chronofold`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Stats<A> {
+    /// The number of log entries, i.e. ops including the root and deletes.
+    pub log_len: usize,
+    /// The number of inserted elements, including deleted ones.
+    pub elements: usize,
+    /// The number of visible elements.
+    pub visible: usize,
+    /// The number of deleted elements still in the log.
+    pub tombstones: usize,
+    /// The number of log entries removed by compaction.
+    pub compacted: usize,
+    /// The number of log entries by each author.
+    pub ops_per_author: BTreeMap<A, usize>,
+    /// The number of runs of inserts, where each insert directly follows the
+    /// previous one in the log and is placed after it by the same author.
+    ///
+    /// Fewer runs mean the inserts came in longer typing sequences.
+    pub runs: usize,
+}
+
+impl<A> Stats<A> {
+    /// Returns the share of deleted elements among all elements, or `0.0` if
+    /// there are none.
+    pub fn tombstone_ratio(&self) -> f64 {
+        average(self.tombstones, self.elements)
+    }
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns statistics about the chronofold.
+    ///
+    /// This iterates over the whole log.
+    pub fn stats(&self) -> Stats<A> {
+        let mut ops_per_author = BTreeMap::new();
+        let mut elements = 0;
+        let mut runs = 0;
+        for (idx, change) in self.log.iter().enumerate() {
+            let author = self
+                .get_author(&LocalIndex(idx))
+                .expect("log entries have authors");
+            *ops_per_author.entry(author.clone()).or_insert(0) += 1;
+            if let Change::Insert(_) = change {
+                elements += 1;
+                let continues_run = idx > 0
+                    && matches!(self.log[idx - 1], Change::Insert(_))
+                    && self.get_author(&LocalIndex(idx - 1)) == Some(author)
+                    && self.get_reference(&LocalIndex(idx)) == Some(LocalIndex(idx - 1));
+                if !continues_run {
+                    runs += 1;
+                }
+            }
+        }
+        let tombstones = self
+            .iter_with_tombstones()
+            .filter(|(_, _, deleted)| *deleted)
+            .count();
+        Stats {
+            log_len: self.log.len(),
+            elements,
+            visible: elements - tombstones,
+            tombstones,
+            compacted: self.compacted,
+            ops_per_author,
+            runs,
+        }
+    }
+}
+
+/// What an author contributed to a chronofold.
+///
+/// This struct is returned by the `contributions` method on `Chronofold`.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct Contribution {
+    /// The number of elements inserted by the author, including deleted ones.
+    pub inserted: usize,
+    /// The number of deletes by the author.
+    pub deleted: usize,
+    /// The number of visible elements inserted by the author.
+    pub visible: usize,
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns the contribution of each author, excluding the root.
+    ///
+    /// The first call iterates over the whole log. Afterwards, the
+    /// contributions are kept up to date with every change, until the log is
+    /// compacted. Compaction removes deleted elements along with their
+    /// deletes, so they aren't counted anymore.
+    pub fn contributions(&self) -> &BTreeMap<A, Contribution> {
+        self.contributions.get_or_init(|| {
+            let mut contributions: BTreeMap<A, Contribution> = BTreeMap::new();
+            for (idx, change) in self.log.iter().enumerate() {
+                let author = self
+                    .get_author(&LocalIndex(idx))
+                    .expect("log entries have authors");
+                match change {
+                    Change::Insert(_) => {
+                        contributions.entry(author.clone()).or_default().inserted += 1
+                    }
+                    Change::Delete => contributions.entry(author.clone()).or_default().deleted += 1,
+                    Change::Root => {}
+                }
+            }
+            for (_, idx) in self.iter() {
+                let author = self.get_author(&idx).expect("log entries have authors");
+                contributions.entry(author.clone()).or_default().visible += 1;
+            }
+            contributions
+        })
+    }
+
+    /// Updates the contributions, if collected, for a change by `author`.
+    ///
+    /// `deleted` is the element a delete hides, if any.
+    pub(crate) fn count_contribution(
+        &mut self,
+        author: &A,
+        change: &Change<T>,
+        deleted: Option<LocalIndex>,
+    ) {
+        if self.contributions.get().is_none() {
+            return;
+        }
+        let deleted_author = deleted.and_then(|idx| self.get_author(&idx)).cloned();
+        let contributions = self
+            .contributions
+            .get_mut()
+            .expect("contributions are collected");
+        match change {
+            Change::Insert(_) => {
+                let contribution = contributions.entry(author.clone()).or_default();
+                contribution.inserted += 1;
+                contribution.visible += 1;
+            }
+            Change::Delete => {
+                contributions.entry(author.clone()).or_default().deleted += 1;
+                if let Some(deleted_author) = deleted_author {
+                    contributions.entry(deleted_author).or_default().visible -= 1;
+                }
+            }
+            Change::Root => {}
+        }
+    }
+}
+
+/// Metrics about the shape of a chronofold's causal tree.
+///
+/// Only inserts are considered, deletes are not part of the tree. This
+/// struct is created by the `tree_shape` method on `Chronofold`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct TreeShape {
+    /// The maximum distance of an element from the root.
+    pub max_depth: usize,
+    /// The average distance of elements from the root.
+    pub average_depth: f64,
+    /// The maximum number of children of an element (or the root).
+    ///
+    /// Inserting next to an element with many children requires scanning
+    /// its subtrees, so high values indicate slow merges.
+    pub max_children: usize,
+    /// The average number of children of elements that have any.
+    pub average_children: f64,
+    /// The number of elements (or the root) with more than one child.
+    pub branching_points: usize,
+}
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns metrics about the shape of the causal tree.
+    ///
+    /// This iterates over the whole log.
+    pub fn tree_shape(&self) -> TreeShape {
+        let mut depths = vec![0; self.log.len()];
+        let mut children = vec![0; self.log.len()];
+        let mut elements = 0;
+        let mut total_depth = 0;
+        for (idx, change) in self.log.iter().enumerate() {
+            if let Change::Insert(_) = change {
+                if let Some(reference) = self.get_reference(&LocalIndex(idx)) {
+                    depths[idx] = depths[reference.0] + 1;
+                    children[reference.0] += 1;
+                }
+                elements += 1;
+                total_depth += depths[idx];
+            }
+        }
+        let parents = children.iter().filter(|c| **c > 0).count();
+        TreeShape {
+            max_depth: depths.iter().copied().max().unwrap_or(0),
+            average_depth: average(total_depth, elements),
+            max_children: children.iter().copied().max().unwrap_or(0),
+            average_children: average(children.iter().sum(), parents),
+            branching_points: children.iter().filter(|c| **c > 1).count(),
+        }
+    }
+}
+
+fn average(sum: usize, count: usize) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        sum as f64 / count as f64
+    }
+}
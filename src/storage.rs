@@ -0,0 +1,185 @@
+//! Asynchronous persistence.
+
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::ops::Range;
+
+use crate::{Author, Chronofold, ChronofoldError, ErrorCategory, Op, Version};
+
+/// The number of ops loaded at once when opening an
+/// `AsyncPersistentChronofold`.
+const LOAD_BATCH_SIZE: usize = 1024;
+
+/// An append-only store of ops with an asynchronous interface.
+///
+/// Ops are addressed by the position they were appended at.
+pub trait AsyncStorage<A, T> {
+    type Error;
+
+    /// Appends ops to the end of the storage.
+    fn append(
+        &mut self,
+        ops: Vec<Op<A, T>>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Loads the ops at the positions in `range`.
+    ///
+    /// Returns fewer ops if the storage ends before `range` does.
+    fn load_range(
+        &self,
+        range: Range<usize>,
+    ) -> impl Future<Output = Result<Vec<Op<A, T>>, Self::Error>> + Send;
+}
+
+/// Stores ops in memory.
+impl<A, T> AsyncStorage<A, T> for Vec<Op<A, T>>
+where
+    A: Clone + Send + Sync,
+    T: Clone + Send + Sync,
+{
+    type Error = Infallible;
+
+    async fn append(&mut self, ops: Vec<Op<A, T>>) -> Result<(), Self::Error> {
+        self.extend(ops);
+        Ok(())
+    }
+
+    async fn load_range(&self, range: Range<usize>) -> Result<Vec<Op<A, T>>, Self::Error> {
+        let end = range.end.min(self.len());
+        Ok(self.get(range.start..end).unwrap_or_default().to_vec())
+    }
+}
+
+/// Represents errors of the `AsyncStorage` of an `AsyncPersistentChronofold`.
+#[derive(Debug)]
+pub struct StorageError<E> {
+    source: E,
+}
+
+impl<E> StorageError<E> {
+    /// Returns the category of this error, which is always `Capacity`.
+    pub fn category(&self) -> ErrorCategory {
+        ErrorCategory::Capacity
+    }
+
+    /// Consumes the error, returning the storage's error.
+    pub fn into_inner(self) -> E {
+        self.source
+    }
+}
+
+impl<E> From<E> for StorageError<E> {
+    fn from(source: E) -> Self {
+        Self { source }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for StorageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "storage error: {}", self.source)
+    }
+}
+
+impl<E: Error + 'static> Error for StorageError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// A chronofold backed by an `AsyncStorage`.
+///
+/// Changes are made to the chronofold directly and written to the storage by
+/// `flush`.
+pub struct AsyncPersistentChronofold<S, A, T> {
+    chronofold: Chronofold<A, T>,
+    storage: S,
+    persisted: Version<A>,
+}
+
+impl<S, A, T> AsyncPersistentChronofold<S, A, T>
+where
+    S: AsyncStorage<A, T>,
+    A: Author,
+    T: Clone,
+{
+    /// Loads all ops from `storage` into `chronofold`.
+    ///
+    /// Stored ops that can't be applied, e.g. because their causal history is
+    /// missing from the storage, are skipped.
+    pub async fn open(
+        mut chronofold: Chronofold<A, T>,
+        storage: S,
+    ) -> Result<Self, StorageError<S::Error>> {
+        let mut persisted = Version::new();
+        let mut pending = Vec::new();
+        let mut start = 0;
+        loop {
+            let ops = storage.load_range(start..start + LOAD_BATCH_SIZE).await?;
+            if ops.is_empty() {
+                break;
+            }
+            start += ops.len();
+            for op in &ops {
+                persisted.inc(&op.id);
+            }
+            pending.extend(ops);
+            let summary = chronofold.apply_iter(pending.drain(..));
+            pending.extend(
+                summary
+                    .deferred
+                    .into_iter()
+                    .filter_map(ChronofoldError::into_op),
+            );
+        }
+        Ok(Self {
+            chronofold,
+            storage,
+            persisted,
+        })
+    }
+
+    /// Returns the chronofold.
+    pub fn chronofold(&self) -> &Chronofold<A, T> {
+        &self.chronofold
+    }
+
+    /// Returns the chronofold for making changes, which are persisted by the
+    /// next `flush`.
+    pub fn chronofold_mut(&mut self) -> &mut Chronofold<A, T> {
+        &mut self.chronofold
+    }
+
+    /// Returns the storage.
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns the version written to the storage.
+    pub fn persisted_version(&self) -> &Version<A> {
+        &self.persisted
+    }
+
+    /// Appends all ops newer than the persisted version to the storage and
+    /// returns their number.
+    pub async fn flush(&mut self) -> Result<usize, StorageError<S::Error>> {
+        let ops: Vec<Op<A, T>> = self
+            .chronofold
+            .iter_newer_ops::<&T>(&self.persisted)
+            .map(Op::cloned)
+            .collect();
+        let count = ops.len();
+        if count > 0 {
+            self.storage.append(ops).await?;
+            self.persisted = self.chronofold.version().clone();
+        }
+        Ok(count)
+    }
+
+    /// Consumes the `AsyncPersistentChronofold`, returning the chronofold
+    /// and the storage.
+    pub fn into_inner(self) -> (Chronofold<A, T>, S) {
+        (self.chronofold, self.storage)
+    }
+}
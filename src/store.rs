@@ -0,0 +1,239 @@
+//! Many chronofolds keyed by document id.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppliedSummary, Author, Chronofold, ChronofoldError, FromLocalValue, IntoLocalValue, Op,
+    OpPayload, Version,
+};
+
+/// An op addressed to a document.
+///
+/// This allows ops for several documents to be sent over one connection. A
+/// batch of them is simply a `Vec<DocOp<D, A, T>>`, see `newer_doc_ops` and
+/// `apply_doc_ops` on `DocumentStore`.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DocOp<D, A, T> {
+    pub doc_id: D,
+    pub op: Op<A, T>,
+}
+
+impl<D, A, T> DocOp<D, A, T> {
+    pub fn new(doc_id: D, op: Op<A, T>) -> Self {
+        Self { doc_id, op }
+    }
+}
+
+impl<D, A, T: Clone> DocOp<D, A, &T> {
+    /// Maps a DocOp<D, A, &T> to a DocOp<D, A, T> by cloning the payload.
+    pub fn cloned(self) -> DocOp<D, A, T> {
+        DocOp {
+            doc_id: self.doc_id,
+            op: self.op.cloned(),
+        }
+    }
+}
+
+/// A collection of chronofolds, each identified by a document id.
+///
+/// Incoming ops are routed to their document. Documents not known yet are
+/// created when their root op arrives, using the default `Policies`.
+#[derive(Clone, Debug)]
+pub struct DocumentStore<D, A, T> {
+    documents: BTreeMap<D, Chronofold<A, T>>,
+}
+
+impl<D, A, T> Default for DocumentStore<D, A, T> {
+    fn default() -> Self {
+        Self {
+            documents: BTreeMap::new(),
+        }
+    }
+}
+
+impl<D: Ord + Clone, A: Author, T> DocumentStore<D, A, T> {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a document, returning the one previously stored under `doc_id`.
+    pub fn insert(&mut self, doc_id: D, chronofold: Chronofold<A, T>) -> Option<Chronofold<A, T>> {
+        self.documents.insert(doc_id, chronofold)
+    }
+
+    /// Removes a document.
+    pub fn remove(&mut self, doc_id: &D) -> Option<Chronofold<A, T>> {
+        self.documents.remove(doc_id)
+    }
+
+    /// Returns the document with the given id.
+    pub fn get(&self, doc_id: &D) -> Option<&Chronofold<A, T>> {
+        self.documents.get(doc_id)
+    }
+
+    /// Returns the document with the given id for editing.
+    pub fn get_mut(&mut self, doc_id: &D) -> Option<&mut Chronofold<A, T>> {
+        self.documents.get_mut(doc_id)
+    }
+
+    /// Returns an iterator over all documents ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = (&D, &Chronofold<A, T>)> {
+        self.documents.iter()
+    }
+
+    /// Returns the number of documents.
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Returns `true` if the store contains no documents.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Returns the version of a document.
+    pub fn version(&self, doc_id: &D) -> Option<&Version<A>> {
+        self.get(doc_id).map(Chronofold::version)
+    }
+
+    /// Returns the versions of all documents.
+    ///
+    /// Sent to a peer, this is the counterpart to `newer_ops`.
+    pub fn versions(&self) -> BTreeMap<D, Version<A>> {
+        self.documents
+            .iter()
+            .map(|(doc_id, cfold)| (doc_id.clone(), cfold.version().clone()))
+            .collect()
+    }
+
+    /// Returns the ops newer than `versions`, grouped by document.
+    ///
+    /// Documents missing from `versions` are included with their whole
+    /// history, documents without newer ops are left out.
+    pub fn newer_ops<'a, V>(
+        &'a self,
+        versions: &BTreeMap<D, Version<A>>,
+    ) -> BTreeMap<D, Vec<Op<A, V>>>
+    where
+        V: FromLocalValue<'a, A, T>,
+    {
+        self.documents
+            .iter()
+            .filter_map(|(doc_id, cfold)| {
+                let version = versions.get(doc_id);
+                let ops: Vec<_> = cfold
+                    .iter_ops(..)
+                    .filter(|op| !version.is_some_and(|version| version.contains(&op.id)))
+                    .collect();
+                Some((doc_id.clone(), ops)).filter(|(_, ops)| !ops.is_empty())
+            })
+            .collect()
+    }
+
+    /// Applies an op to the document `doc_id`.
+    ///
+    /// A root op for an unknown document creates it. Other ops for unknown
+    /// documents are missing their causal history and fail with
+    /// `UnknownReference` (or `FutureTimestamp` if they have no reference).
+    pub fn apply<V>(&mut self, doc_id: &D, op: Op<A, V>) -> Result<(), ChronofoldError<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        match self.documents.get_mut(doc_id) {
+            Some(cfold) => cfold.apply(op),
+            None if matches!(op.payload, OpPayload::Root) && op.id.idx.0 == 0 => {
+                self.documents
+                    .insert(doc_id.clone(), Chronofold::new(op.id.author));
+                Ok(())
+            }
+            None => Err(match op.payload.reference() {
+                Some(_) => ChronofoldError::UnknownReference(op, None),
+                None => ChronofoldError::FutureTimestamp(op),
+            }),
+        }
+    }
+
+    /// Applies ops grouped by document and returns a summary per document.
+    ///
+    /// See `apply_iter` for details.
+    pub fn apply_bulk<V>(
+        &mut self,
+        ops: BTreeMap<D, Vec<Op<A, V>>>,
+    ) -> BTreeMap<D, AppliedSummary<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        ops.into_iter()
+            .map(|(doc_id, mut ops)| {
+                if !self.documents.contains_key(&doc_id) {
+                    let root = ops
+                        .iter()
+                        .position(|op| matches!(op.payload, OpPayload::Root) && op.id.idx.0 == 0);
+                    if let Some(root) = root {
+                        let root = ops.remove(root);
+                        self.documents
+                            .insert(doc_id.clone(), Chronofold::new(root.id.author));
+                    }
+                }
+                let summary = match self.documents.get_mut(&doc_id) {
+                    Some(cfold) => cfold.apply_iter(ops),
+                    None => AppliedSummary {
+                        deferred: ops
+                            .into_iter()
+                            .filter_map(|op| self.apply(&doc_id, op).err())
+                            .collect(),
+                        ..AppliedSummary::default()
+                    },
+                };
+                (doc_id, summary)
+            })
+            .collect()
+    }
+
+    /// Returns the ops newer than `versions` as a batch of `DocOp`s.
+    ///
+    /// This is the flat form of `newer_ops`.
+    pub fn newer_doc_ops<'a, V>(&'a self, versions: &BTreeMap<D, Version<A>>) -> Vec<DocOp<D, A, V>>
+    where
+        V: FromLocalValue<'a, A, T>,
+    {
+        self.newer_ops(versions)
+            .into_iter()
+            .flat_map(|(doc_id, ops)| {
+                ops.into_iter()
+                    .map(move |op| DocOp::new(doc_id.clone(), op))
+            })
+            .collect()
+    }
+
+    /// Applies a single `DocOp`. See `apply` for details.
+    pub fn apply_doc_op<V>(&mut self, doc_op: DocOp<D, A, V>) -> Result<(), ChronofoldError<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        self.apply(&doc_op.doc_id, doc_op.op)
+    }
+
+    /// Applies a batch of `DocOp`s and returns a summary per document.
+    ///
+    /// Ops are grouped by document first, so documents can be interleaved
+    /// in any order. See `apply_bulk` for details.
+    pub fn apply_doc_ops<V>(
+        &mut self,
+        doc_ops: impl IntoIterator<Item = DocOp<D, A, V>>,
+    ) -> BTreeMap<D, AppliedSummary<A, V>>
+    where
+        V: IntoLocalValue<A, T>,
+    {
+        let mut ops: BTreeMap<D, Vec<Op<A, V>>> = BTreeMap::new();
+        for DocOp { doc_id, op } in doc_ops {
+            ops.entry(doc_id).or_default().push(op);
+        }
+        self.apply_bulk(ops)
+    }
+}
@@ -0,0 +1,218 @@
+//! Bookkeeping for synchronizing with peers.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::{Author, Chronofold, FromLocalValue, Op, Version};
+
+/// Tracks which ops each connected peer has acknowledged.
+///
+/// Ops are sent to a peer with `unacked_ops_for` followed by `mark_sent`.
+/// When a peer acknowledges ops (by sending its version), call `ack`. If an
+/// acknowledgement doesn't arrive in time, `peers_to_retransmit` lists the
+/// peer again, so lost messages don't stall convergence.
+#[derive(Clone, Debug)]
+pub struct SyncState<P, A> {
+    peers: BTreeMap<P, PeerState<A>>,
+}
+
+#[derive(Clone, Debug)]
+struct PeerState<A> {
+    acked: Version<A>,
+    sent: Option<(Version<A>, Instant)>,
+}
+
+impl<P: Ord, A: Author> SyncState<P, A> {
+    /// Creates a sync state without peers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a peer that is known to have seen `acked`.
+    ///
+    /// Use an empty version for peers starting from scratch. If the peer was
+    /// added before, it keeps its state.
+    pub fn add_peer(&mut self, peer: P, acked: Version<A>) {
+        self.peers
+            .entry(peer)
+            .or_insert(PeerState { acked, sent: None });
+    }
+
+    /// Removes a peer.
+    pub fn remove_peer(&mut self, peer: &P) {
+        self.peers.remove(peer);
+    }
+
+    /// Returns an iterator over all peers.
+    pub fn peers(&self) -> impl Iterator<Item = &P> {
+        self.peers.keys()
+    }
+
+    /// Returns the version acknowledged by `peer`.
+    pub fn acked(&self, peer: &P) -> Option<&Version<A>> {
+        self.peers.get(peer).map(|state| &state.acked)
+    }
+
+    /// Records that `peer` has seen `version`.
+    ///
+    /// Acknowledgements never go back, so outdated ones are harmless.
+    pub fn ack(&mut self, peer: &P, version: &Version<A>) {
+        if let Some(state) = self.peers.get_mut(peer) {
+            for timestamp in version.iter() {
+                state.acked.inc(&timestamp);
+            }
+            if matches!(&state.sent, Some((sent, _)) if *sent <= state.acked) {
+                state.sent = None;
+            }
+        }
+    }
+
+    /// Returns an iterator over the ops `peer` hasn't acknowledged yet, or
+    /// `None` if the peer is unknown.
+    pub fn unacked_ops_for<'a, T, V>(
+        &'a self,
+        cfold: &'a Chronofold<A, T>,
+        peer: &P,
+    ) -> Option<impl Iterator<Item = Op<A, V>> + 'a>
+    where
+        V: FromLocalValue<'a, A, T> + 'a,
+    {
+        let state = self.peers.get(peer)?;
+        Some(cfold.iter_newer_ops(&state.acked))
+    }
+
+    /// Records that all ops up to `version` were sent to `peer` at `now`.
+    pub fn mark_sent(&mut self, peer: &P, version: Version<A>, now: Instant) {
+        if let Some(state) = self.peers.get_mut(peer) {
+            let acked = matches!(
+                version.partial_cmp(&state.acked),
+                Some(Ordering::Less | Ordering::Equal)
+            );
+            if !acked {
+                state.sent = Some((version, now));
+            }
+        }
+    }
+
+    /// Returns the peers that didn't acknowledge ops sent to them for
+    /// `timeout`.
+    ///
+    /// Their unacknowledged ops should be sent again.
+    pub fn peers_to_retransmit(&self, now: Instant, timeout: Duration) -> Vec<&P> {
+        self.peers
+            .iter()
+            .filter(|(_, state)| {
+                matches!(state.sent, Some((_, sent_at)) if now.duration_since(sent_at) >= timeout)
+            })
+            .map(|(peer, _)| peer)
+            .collect()
+    }
+}
+
+impl<P: Ord, A: Author> Default for SyncState<P, A> {
+    fn default() -> Self {
+        Self {
+            peers: BTreeMap::new(),
+        }
+    }
+}
+
+impl<A: Author, T: Clone> Chronofold<A, T> {
+    /// Returns what to exchange with a peer after reconnecting: the ops the
+    /// peer is missing given its version, and the version to send it, so it
+    /// can reply with the ops missing here.
+    pub fn resync(&self, remote_version: &Version<A>) -> (Vec<Op<A, T>>, Version<A>) {
+        let ops = self
+            .iter_newer_ops(remote_version)
+            .map(Op::cloned)
+            .collect();
+        (ops, self.version.clone())
+    }
+}
+
+/// Buffers locally generated ops until they are acknowledged.
+///
+/// This is the "pending changes" of an offline-first client: Push ops as
+/// they are made, send `pending` whenever connected, and call `ack` with
+/// the version the server or peers confirm. With the `serde` feature, the
+/// queue can be persisted to survive restarts.
+#[derive(PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Op<A, T>: serde::Serialize, Version<A>: serde::Serialize",
+        deserialize = "Op<A, T>: serde::Deserialize<'de>, Version<A>: serde::Deserialize<'de>"
+    ))
+)]
+pub struct OutboundQueue<A, T> {
+    pending: Vec<Op<A, T>>,
+    acked: Version<A>,
+}
+
+impl<A: Author, T> OutboundQueue<A, T> {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an op, unless it was acknowledged before.
+    pub fn push(&mut self, op: Op<A, T>) {
+        if !self.acked.contains(&op.id) {
+            self.pending.push(op);
+        }
+    }
+
+    /// Returns the ops that weren't acknowledged yet, in the order they were
+    /// pushed.
+    pub fn pending(&self) -> &[Op<A, T>] {
+        &self.pending
+    }
+
+    /// Returns the number of unacknowledged ops.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if all ops were acknowledged.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns the acknowledged version.
+    pub fn acked(&self) -> &Version<A> {
+        &self.acked
+    }
+
+    /// Records that `version` was acknowledged and returns the number of
+    /// ops removed from the queue.
+    ///
+    /// Acknowledgements never go back, so outdated ones are harmless.
+    pub fn ack(&mut self, version: &Version<A>) -> usize {
+        for timestamp in version.iter() {
+            self.acked.inc(&timestamp);
+        }
+        let len = self.pending.len();
+        let acked = &self.acked;
+        self.pending.retain(|op| !acked.contains(&op.id));
+        len - self.pending.len()
+    }
+}
+
+impl<A: Author, T> Extend<Op<A, T>> for OutboundQueue<A, T> {
+    fn extend<I: IntoIterator<Item = Op<A, T>>>(&mut self, ops: I) {
+        for op in ops {
+            self.push(op);
+        }
+    }
+}
+
+impl<A: Author, T> Default for OutboundQueue<A, T> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            acked: Version::new(),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Generators for realistic editing workloads.
+//!
+//! These are meant for tests and benchmarks, both of this crate and of
+//! downstream crates, so everyone exercises the same scenarios. All
+//! generators return the ops they created, ready to be applied to other
+//! replicas.
+//!
+//! This module is only available with the `test-util` feature.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::{Author, Chronofold, LocalIndex, Op};
+
+/// Types `text` character by character after `index`, the way a user would
+/// do it.
+///
+/// Returns the ops created, one per character.
+pub fn typing_burst<A: Author>(
+    cfold: &mut Chronofold<A, char>,
+    author: A,
+    index: LocalIndex,
+    text: &str,
+) -> Vec<Op<A, char>> {
+    let mut session = cfold.session(author);
+    let mut last = index;
+    for c in text.chars() {
+        last = session.insert_after(last, c);
+    }
+    session.iter_ops().map(Op::cloned).collect()
+}
+
+/// Pastes `text` after `index` in a single splice.
+pub fn paste<A: Author>(
+    cfold: &mut Chronofold<A, char>,
+    author: A,
+    index: LocalIndex,
+    text: &str,
+) -> Vec<Op<A, char>> {
+    let mut session = cfold.session(author);
+    session.splice(index..index, text.chars());
+    session.iter_ops().map(Op::cloned).collect()
+}
+
+/// Lets each author concurrently type their text after the same `index`.
+///
+/// Every author works on their own replica of `base`. Returns the ops of each
+/// author, in the order of `edits`.
+pub fn concurrent_edits_at<A: Author>(
+    base: &Chronofold<A, char>,
+    index: LocalIndex,
+    edits: &[(A, &str)],
+) -> Vec<Vec<Op<A, char>>> {
+    edits
+        .iter()
+        .map(|(author, text)| typing_burst(&mut base.clone(), author.clone(), index, text))
+        .collect()
+}
+
+/// Makes 1 to 5 insertions of random words and 0 to 2 deletions of 1 to 3
+/// characters at random positions.
+pub fn random_edits<A: Author, R: Rng>(
+    rng: &mut R,
+    cfold: &mut Chronofold<A, char>,
+    author: A,
+) -> Vec<Op<A, char>> {
+    let mut session = cfold.session(author);
+
+    for _ in 0..rng.gen_range(1, 6) {
+        let current: Vec<_> = session.as_ref().iter().map(|(_, i)| i).collect();
+        let word = random_word(rng);
+        match current.choose(rng) {
+            Some(&idx) => session.splice(idx..idx, word.chars()),
+            None => session.extend(word.chars()),
+        };
+    }
+
+    for _ in 0..rng.gen_range(0, 3) {
+        let current: Vec<_> = session.as_ref().iter().map(|(_, i)| i).collect();
+        if !current.is_empty() {
+            let length = usize::min(rng.gen_range(1, 4), current.len());
+            let start = rng.gen_range(0, current.len() - length + 1);
+            session.splice(current[start]..=current[start + length - 1], "".chars());
+        }
+    }
+
+    session.iter_ops().map(Op::cloned).collect()
+}
+
+/// Randomly interleaves multiple streams of ops, keeping the order within
+/// each stream.
+///
+/// This simulates ops from different authors arriving over a network.
+pub fn random_interleaving<O, R: Rng>(rng: &mut R, streams: Vec<Vec<O>>) -> Vec<O> {
+    let mut streams: Vec<_> = streams.into_iter().map(Vec::into_iter).collect();
+    let mut remaining: Vec<usize> = streams.iter().map(|s| s.len()).collect();
+    let total = remaining.iter().sum();
+    let mut result = Vec::with_capacity(total);
+    for n in (1..=total).rev() {
+        // Pick a stream weighted by its number of remaining ops.
+        let mut pick = rng.gen_range(0, n);
+        let stream = remaining
+            .iter()
+            .position(|&len| {
+                if pick < len {
+                    true
+                } else {
+                    pick -= len;
+                    false
+                }
+            })
+            .expect("remaining ops add up to n");
+        remaining[stream] -= 1;
+        result.extend(streams[stream].next());
+    }
+    result
+}
+
+fn random_word<R: Rng>(rng: &mut R) -> String {
+    let alphabet: Vec<_> = "abcdefghijklmnopqrstuvwxyz".chars().collect();
+    (0..rng.gen_range(1, 4))
+        .map(|_| alphabet[rng.gen_range(0, alphabet.len())])
+        .collect::<String>()
+        + " "
+}
@@ -0,0 +1,127 @@
+//! Canonical test vectors for other implementations.
+//!
+//! Each vector is a sequence of ops from several authors together with the
+//! text all replicas converge to and a snapshot of the resulting chronofold.
+//! Implementations in other languages can replay the ops to verify they
+//! converge the same way, and decode the ops and the snapshot to verify wire
+//! compatibility. Serialize the vectors with any serde format, e.g. JSON.
+//!
+//! This module is only available with the `test-vectors` feature.
+
+use crate::test_util::{concurrent_edits_at, paste, typing_burst};
+use crate::{to_cbor_bytes, Chronofold, Compression, LocalIndex, Op};
+
+/// Ops to replay and the expected outcome.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct TestVector {
+    pub name: String,
+    /// The ops in the order to apply them to a chronofold created by
+    /// `Chronofold::new(0)`.
+    ///
+    /// Every op's causal history comes before it, but concurrent ops are not
+    /// in the order they were created.
+    pub ops: Vec<Op<u8, char>>,
+    /// Each op of `ops` encoded as CBOR.
+    pub encoded_ops: Vec<Vec<u8>>,
+    /// The text after applying `ops`.
+    pub text: String,
+    /// An uncompressed snapshot of the chronofold after applying `ops`.
+    pub snapshot: Vec<u8>,
+}
+
+impl TestVector {
+    fn new(name: &str, ops: Vec<Op<u8, char>>) -> Self {
+        let mut cfold = Chronofold::<u8, char>::new(0);
+        cfold
+            .apply_all(ops.iter().cloned())
+            .expect("test vector ops are in causal order");
+        let mut snapshot = Vec::new();
+        cfold
+            .write_snapshot(&mut snapshot, Compression::None)
+            .expect("writing to a Vec doesn't fail");
+        Self {
+            name: name.to_owned(),
+            encoded_ops: ops
+                .iter()
+                .map(|op| to_cbor_bytes(op).expect("ops can be encoded"))
+                .collect(),
+            ops,
+            text: cfold.to_string(),
+            snapshot,
+        }
+    }
+
+    /// Replays the ops and returns an error describing the first mismatch
+    /// with the expected outcome, if any.
+    pub fn verify(&self) -> Result<(), String> {
+        let mut cfold = Chronofold::<u8, char>::new(0);
+        for (op, encoded) in self.ops.iter().zip(&self.encoded_ops) {
+            if to_cbor_bytes(op).ok().as_ref() != Some(encoded) {
+                return Err(format!("{}: encoding of {:?} differs", self.name, op.id));
+            }
+            cfold
+                .apply(op.clone())
+                .map_err(|err| format!("{}: {}", self.name, err))?;
+        }
+        if cfold.to_string() != self.text {
+            return Err(format!("{}: text differs", self.name));
+        }
+        let mut snapshot = Vec::new();
+        cfold
+            .write_snapshot(&mut snapshot, Compression::None)
+            .map_err(|err| format!("{}: {}", self.name, err))?;
+        if snapshot != self.snapshot {
+            return Err(format!("{}: snapshot differs", self.name));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the canonical test vectors.
+///
+/// The vectors are generated deterministically, so they only change if the
+/// wire format or the convergence behavior of this crate changes.
+pub fn test_vectors() -> Vec<TestVector> {
+    let root = LocalIndex(0);
+    let mut vectors = Vec::new();
+
+    let mut cfold = Chronofold::new(0);
+    let ops = typing_burst(&mut cfold, 1, root, "Hello world");
+    vectors.push(TestVector::new("sequential_typing", ops));
+
+    let mut cfold = Chronofold::new(0);
+    let mut ops = typing_burst(&mut cfold, 1, root, "ac");
+    let mut concurrent =
+        concurrent_edits_at(&cfold, LocalIndex(1), &[(2, "b"), (3, "B"), (4, "xy")]);
+    concurrent.reverse();
+    ops.extend(concurrent.into_iter().flatten());
+    vectors.push(TestVector::new("concurrent_inserts_at_same_position", ops));
+
+    let mut cfold = Chronofold::new(0);
+    let mut ops = typing_burst(&mut cfold, 1, root, "abc");
+    let mut deleting = cfold.clone();
+    deleting.session(2).remove(LocalIndex(2));
+    let inserting = typing_burst(&mut cfold, 3, LocalIndex(2), "X");
+    ops.extend(inserting);
+    ops.extend(deleting.iter_ops(LocalIndex(4)..).map(Op::cloned));
+    vectors.push(TestVector::new("concurrent_delete_and_insert", ops));
+
+    let mut cfold = Chronofold::new(0);
+    let mut ops = typing_burst(&mut cfold, 1, root, "abc");
+    for author in &[3, 2] {
+        let mut replica = cfold.clone();
+        replica.session(*author).remove(LocalIndex(2));
+        ops.extend(replica.iter_ops(LocalIndex(4)..).map(Op::cloned));
+    }
+    vectors.push(TestVector::new("concurrent_deletes_of_same_element", ops));
+
+    let mut cfold = Chronofold::new(0);
+    let mut ops = paste(&mut cfold, 1, root, "lorem ipsum");
+    let mut replica = cfold.clone();
+    let typed = typing_burst(&mut replica, 2, root, "dolor ");
+    ops.extend(paste(&mut cfold, 1, LocalIndex(11), " sit amet"));
+    ops.extend(typed);
+    vectors.push(TestVector::new("paste_and_concurrent_typing", ops));
+
+    vectors
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+use crate::{Author, Chronofold, LocalIndex};
+
+/// A chronofold of text, edited by a local author at character positions.
+///
+/// This wraps a `Chronofold<A, char>` with the string-oriented API text
+/// editors need. Positions count `char`s, not bytes. Remote ops are applied
+/// to the chronofold itself, see `AsMut`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Text<A> {
+    chronofold: Chronofold<A, char>,
+    author: A,
+}
+
+impl<A: Author> Text<A> {
+    /// Wraps `chronofold`, to be edited by `author`.
+    pub fn new(chronofold: Chronofold<A, char>, author: A) -> Self {
+        Self { chronofold, author }
+    }
+
+    /// Returns the author of local edits.
+    pub fn author(&self) -> A {
+        self.author.clone()
+    }
+
+    /// Consumes the text, returning the chronofold.
+    pub fn into_inner(self) -> Chronofold<A, char> {
+        self.chronofold
+    }
+
+    /// Returns the number of characters.
+    pub fn len(&self) -> usize {
+        self.chronofold.len()
+    }
+
+    /// Returns `true` if the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.chronofold.is_empty()
+    }
+
+    /// Returns the text as a `String`.
+    pub fn as_string(&self) -> String {
+        self.chronofold.iter_elements().collect()
+    }
+
+    /// Returns the character at `position`, or `None` if it is out of
+    /// bounds.
+    pub fn char_at(&self, position: usize) -> Option<char> {
+        self.chronofold.slice(position..).next().copied()
+    }
+
+    /// Returns the log index of the character at `position`, see
+    /// `Chronofold::position_to_index`.
+    pub fn position_to_index(&self, position: usize) -> Option<LocalIndex> {
+        self.chronofold.position_to_index(position)
+    }
+
+    /// Returns the position of the character at `index`, see
+    /// `Chronofold::index_to_position`.
+    pub fn index_to_position(&self, index: LocalIndex) -> Option<usize> {
+        self.chronofold.index_to_position(index)
+    }
+
+    /// Inserts `s` at `position` and returns the log index of its last
+    /// character, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position` is greater than the text's length.
+    pub fn insert_str(&mut self, position: usize, s: &str) -> Option<LocalIndex> {
+        let mut reference = match position.checked_sub(1) {
+            None => self.chronofold.root,
+            Some(previous) => self
+                .position_to_index(previous)
+                .unwrap_or_else(|| panic!("position {} out of bounds", position)),
+        };
+        let mut session = self.chronofold.session(self.author.clone());
+        let mut last = None;
+        for c in s.chars() {
+            reference = session.insert_after(reference, c);
+            last = Some(reference);
+        }
+        last
+    }
+
+    /// Deletes the characters at the positions in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn delete_range(&mut self, range: impl RangeBounds<usize>) {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end + 1,
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => self.len(),
+        };
+        let indices: Vec<LocalIndex> = self
+            .chronofold
+            .iter()
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .map(|(_, idx)| idx)
+            .collect();
+        if start > end || indices.len() != end - start {
+            panic!("range {}..{} out of bounds", start, end);
+        }
+        let mut session = self.chronofold.session(self.author.clone());
+        for idx in indices {
+            session.remove(idx);
+        }
+    }
+}
+
+impl<A> AsRef<Chronofold<A, char>> for Text<A> {
+    fn as_ref(&self) -> &Chronofold<A, char> {
+        &self.chronofold
+    }
+}
+
+impl<A> AsMut<Chronofold<A, char>> for Text<A> {
+    fn as_mut(&mut self) -> &mut Chronofold<A, char> {
+        &mut self.chronofold
+    }
+}
+
+impl<A> fmt::Display for Text<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.chronofold, f)
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{Author, Chronofold, LocalIndex, Timestamp};
+
+impl<A: Author, T> Chronofold<A, T> {
+    /// Returns the log index of the change `index` references, i.e. its
+    /// parent in the causal tree.
+    ///
+    /// Returns `None` for roots and if `index` is out of bounds.
+    pub fn parent(&self, index: LocalIndex) -> Option<LocalIndex> {
+        self.log.get(index.0)?;
+        self.get_reference(&index)
+    }
+
+    /// Returns an iterator over the log indices of the changes referencing
+    /// `index`, i.e. its children in the causal tree, in causal order.
+    ///
+    /// This iterates over the subtree of `index`.
+    pub fn children(&self, index: LocalIndex) -> impl Iterator<Item = LocalIndex> + '_ {
+        self.log
+            .get(index.0)
+            .map(|_| self.iter_subtree(index).skip(1))
+            .into_iter()
+            .flatten()
+            .filter(move |idx| self.get_reference(idx) == Some(index))
+    }
+
+    /// Returns an iterator over the changes referencing `reference`, with
+    /// their timestamps, in their resolved sibling order.
+    ///
+    /// This is the order concurrent insertions at `reference` were
+    /// interleaved in: by default newest first, unless a custom
+    /// `SiblingOrder` is set.
+    pub fn iter_siblings(
+        &self,
+        reference: LocalIndex,
+    ) -> impl Iterator<Item = (LocalIndex, Timestamp<A>)> + '_ {
+        self.children(reference).map(move |idx| {
+            let timestamp = self.timestamp(idx).expect("log entries have timestamps");
+            (idx, timestamp)
+        })
+    }
+
+    /// Returns `true` if `a` is an ancestor of `b` in the causal tree, i.e.
+    /// `b` references `a` directly or indirectly.
+    ///
+    /// Changes are always logged after the change they reference, so this
+    /// only walks up from `b` until it passes `a`.
+    pub fn is_ancestor(&self, a: LocalIndex, b: LocalIndex) -> bool {
+        if b.0 >= self.log.len() {
+            return false;
+        }
+        let mut current = b;
+        while let Some(parent) = self.get_reference(&current).filter(|parent| *parent >= a) {
+            if parent == a {
+                return true;
+            }
+            current = parent;
+        }
+        false
+    }
+
+    /// Returns the lowest common ancestor of `a` and `b` in the causal tree.
+    ///
+    /// A change counts as its own ancestor here, so if `a` is an ancestor of
+    /// `b`, the result is `a`. Returns `None` if either index is out of
+    /// bounds or they belong to different roots.
+    pub fn lca(&self, a: LocalIndex, b: LocalIndex) -> Option<LocalIndex> {
+        self.log.get(a.0)?;
+        self.log.get(b.0)?;
+        let (mut a, mut b) = (a, b);
+        // Ancestors are always logged before their descendants.
+        while a != b {
+            if a > b {
+                a = self.get_reference(&a)?;
+            } else {
+                b = self.get_reference(&b)?;
+            }
+        }
+        Some(a)
+    }
+
+    /// Returns the chain of references from the root to `index`, including
+    /// both, or `None` if `index` is out of bounds.
+    ///
+    /// Use `resolve` and `element_id` to translate from and to `ElementId`s.
+    pub fn path_to(&self, index: LocalIndex) -> Option<Vec<LocalIndex>> {
+        let mut path = Vec::with_capacity(self.depth(index)? + 1);
+        path.push(index);
+        while let Some(reference) = self.get_reference(path.last().expect("path isn't empty")) {
+            path.push(reference);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Returns the number of changes in the subtree of `index`, including
+    /// `index` itself, or 0 if it is out of bounds.
+    ///
+    /// This iterates over the subtree.
+    pub fn subtree_len(&self, index: LocalIndex) -> usize {
+        match self.log.get(index.0) {
+            Some(_) => self.iter_subtree(index).count(),
+            None => 0,
+        }
+    }
+
+    /// Returns the distance of `index` from its root in the causal tree.
+    pub fn depth(&self, index: LocalIndex) -> Option<usize> {
+        self.log.get(index.0)?;
+        let mut current = index;
+        let mut depth = 0;
+        // Depths are computed when changes are applied, but might be missing
+        // for the newest entries.
+        while self.depths.len() <= current.0 {
+            match self.get_reference(&current) {
+                Some(reference) => current = reference,
+                None => return Some(depth),
+            }
+            depth += 1;
+        }
+        Some(depth + self.depths[current.0])
+    }
+
+    /// Computes the depths of log entries added since the last call.
+    pub(crate) fn extend_depths(&mut self) {
+        for idx in self.depths.len()..self.log.len() {
+            let depth = self
+                .get_reference(&LocalIndex(idx))
+                .map_or(0, |reference| self.depths[reference.0] + 1);
+            self.depths.push(depth);
+        }
+    }
+}
@@ -1,11 +1,29 @@
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
 
-use crate::{Author, Chronofold, FromLocalValue, LocalIndex, Op, Timestamp, AuthorIndex, LogIndex};
+use crate::{
+    Author, AuthorIndex, Chronofold, FromLocalValue, LogIndex, Op, ParseVersionError, Timestamp,
+};
 
 /// A vector clock representing the chronofold's version.
-#[derive(PartialEq, Eq, Clone, Debug)]
+///
+/// Versions of few authors are kept in a sorted `Vec`. Beyond 32 authors,
+/// they switch to a sorted map, so adding authors stays cheap.
+#[derive(Clone)]
 pub struct Version<A> {
-    log_indices: Vec<Timestamp<A>>,
+    log_indices: LogIndices<A>,
+}
+
+/// The number of authors up to which a version is kept in a sorted `Vec`.
+const SMALL_VERSION_LEN: usize = 32;
+
+#[derive(Clone)]
+enum LogIndices<A> {
+    Small(Vec<Timestamp<A>>),
+    Large(BTreeMap<A, Timestamp<A>>),
 }
 
 impl<A: Author> Version<A> {
@@ -16,38 +34,275 @@ impl<A: Author> Version<A> {
 
     /// Increments the version using a timestamp.
     pub fn inc(&mut self, timestamp: &Timestamp<A>) {
-        match self.log_indices
-            .binary_search_by(|t| t.author.cmp(&timestamp.author)) {
-            Ok(idx) => self.log_indices[idx].idx.take_max(&timestamp.idx),
-            Err(idx) => self.log_indices.insert(idx, *timestamp),
-        };
+        match &mut self.log_indices {
+            LogIndices::Small(log_indices) => {
+                match log_indices.binary_search_by(|t| t.author.cmp(&timestamp.author)) {
+                    Ok(idx) => log_indices[idx].idx.take_max(&timestamp.idx),
+                    Err(idx) if log_indices.len() < SMALL_VERSION_LEN => {
+                        log_indices.insert(idx, timestamp.clone())
+                    }
+                    Err(_) => {
+                        let mut large: BTreeMap<A, Timestamp<A>> = log_indices
+                            .drain(..)
+                            .map(|t| (t.author.clone(), t))
+                            .collect();
+                        large.insert(timestamp.author.clone(), timestamp.clone());
+                        self.log_indices = LogIndices::Large(large);
+                    }
+                }
+            }
+            LogIndices::Large(log_indices) => {
+                log_indices
+                    .entry(timestamp.author.clone())
+                    .and_modify(|t| t.idx.take_max(&timestamp.idx))
+                    .or_insert_with(|| timestamp.clone());
+            }
+        }
+    }
+
+    /// Replaces the log index for `timestamp`'s author, even if it is lower
+    /// than the current one, or removes the author if `None`.
+    pub(crate) fn reset(&mut self, author: &A, timestamp: Option<Timestamp<A>>) {
+        match &mut self.log_indices {
+            LogIndices::Small(log_indices) => {
+                match (
+                    log_indices.binary_search_by(|t| t.author.cmp(author)),
+                    timestamp,
+                ) {
+                    (Ok(idx), Some(timestamp)) => log_indices[idx] = timestamp,
+                    (Ok(idx), None) => {
+                        log_indices.remove(idx);
+                    }
+                    (Err(_), Some(timestamp)) => self.inc(&timestamp),
+                    (Err(_), None) => {}
+                }
+            }
+            LogIndices::Large(log_indices) => match timestamp {
+                Some(timestamp) => {
+                    log_indices.insert(author.clone(), timestamp);
+                }
+                None => {
+                    log_indices.remove(author);
+                    if log_indices.len() <= SMALL_VERSION_LEN {
+                        let small = std::mem::take(log_indices).into_values().collect();
+                        self.log_indices = LogIndices::Small(small);
+                    }
+                }
+            },
+        }
+    }
+
+    /// Increments the version by all timestamps of `other`.
+    pub(crate) fn join(&mut self, other: &Self) {
+        for timestamp in other.iter() {
+            self.inc(&timestamp);
+        }
     }
 
-    /// Returns an iterator over the timestamps in this version.
+    /// Returns the pointwise minimum of two versions, i.e. the ops seen by
+    /// both.
+    pub(crate) fn meet(&self, other: &Self) -> Self {
+        self.iter()
+            .filter_map(|t| {
+                let idx = other.get(&t.author)?;
+                Some(Timestamp::new(idx.min(t.idx), t.author))
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over the timestamps in this version, ordered by
+    /// author.
     pub fn iter(&self) -> impl Iterator<Item = Timestamp<A>> + '_ {
-        self.log_indices.iter().map(Timestamp::clone)
+        let (small, large) = match &self.log_indices {
+            LogIndices::Small(log_indices) => (Some(log_indices.iter().cloned()), None),
+            LogIndices::Large(log_indices) => (None, Some(log_indices.values().cloned())),
+        };
+        small
+            .into_iter()
+            .flatten()
+            .chain(large.into_iter().flatten())
+    }
+
+    /// Returns the number of authors in this version.
+    pub fn len(&self) -> usize {
+        match &self.log_indices {
+            LogIndices::Small(log_indices) => log_indices.len(),
+            LogIndices::Large(log_indices) => log_indices.len(),
+        }
+    }
+
+    /// Returns `true` if the version contains no authors.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the op with the given timestamp is part of this
+    /// version.
+    pub fn contains(&self, timestamp: &Timestamp<A>) -> bool {
+        self.get(&timestamp.author)
+            .is_some_and(|idx| timestamp.idx <= idx)
     }
 
     /// Returns the version's log index for `author`.
     pub fn get(&self, author: &A) -> Option<AuthorIndex> {
-        let idx = self.log_indices
-            .binary_search_by(|t| t.author.cmp(author)).ok()?;
-        Some(self.log_indices[idx].idx)
+        match &self.log_indices {
+            LogIndices::Small(log_indices) => {
+                let idx = log_indices
+                    .binary_search_by(|t| t.author.cmp(author))
+                    .ok()?;
+                Some(log_indices[idx].idx)
+            }
+            LogIndices::Large(log_indices) => log_indices.get(author).map(|t| t.idx),
+        }
+    }
+}
+
+impl<A: Author> FromIterator<Timestamp<A>> for Version<A> {
+    fn from_iter<I: IntoIterator<Item = Timestamp<A>>>(iter: I) -> Self {
+        let mut version = Version::new();
+        for timestamp in iter {
+            version.inc(&timestamp);
+        }
+        version
+    }
+}
+
+// Versions of more than `SMALL_VERSION_LEN` authors are always large, so
+// they can be compared by representation.
+impl<A: PartialEq> PartialEq for Version<A> {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.log_indices, &other.log_indices) {
+            (LogIndices::Small(a), LogIndices::Small(b)) => a == b,
+            (LogIndices::Large(a), LogIndices::Large(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<A: Eq> Eq for Version<A> {}
+
+impl<A: fmt::Debug> fmt::Debug for Version<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let log_indices: Vec<&Timestamp<A>> = match &self.log_indices {
+            LogIndices::Small(log_indices) => log_indices.iter().collect(),
+            LogIndices::Large(log_indices) => log_indices.values().collect(),
+        };
+        f.debug_struct("Version")
+            .field("log_indices", &log_indices)
+            .finish()
     }
 }
 
 impl<A: Author> Default for Version<A> {
     fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<A> Version<A> {
+    /// Constructs a new, empty version, without requiring `A: Author`.
+    pub(crate) fn empty() -> Self {
         Self {
-            log_indices: Vec::new(),
+            log_indices: LogIndices::Small(Vec::new()),
         }
     }
+
+    /// Returns `true` if the version contains no authors, without requiring
+    /// `A: Author`.
+    #[cfg(feature = "serde")]
+    pub(crate) fn is_initial(&self) -> bool {
+        match &self.log_indices {
+            LogIndices::Small(log_indices) => log_indices.is_empty(),
+            LogIndices::Large(log_indices) => log_indices.is_empty(),
+        }
+    }
+}
+
+/// Formats the version as comma-separated `author:index` pairs, e.g.
+/// `1:42,2:17`.
+///
+/// Commas, colons and backslashes in authors are escaped with a backslash,
+/// e.g. `a\,b:3`.
+impl<A: Author + fmt::Display> fmt::Display for Version<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, t) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            for c in t.author.to_string().chars() {
+                if matches!(c, ',' | ':' | '\\') {
+                    f.write_str("\\")?;
+                }
+                write!(f, "{}", c)?;
+            }
+            write!(f, ":{}", t.idx)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the format written by `Display`.
+impl<A: Author + FromStr> FromStr for Version<A> {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut version = Version::new();
+        for entry in split_unescaped(s, ',')
+            .into_iter()
+            .filter(|entry| !entry.is_empty())
+        {
+            let idx = match split_unescaped(entry, ':').as_slice() {
+                [_, .., idx] => *idx,
+                _ => return Err(ParseVersionError::new(entry)),
+            };
+            let author = &entry[..entry.len() - idx.len() - 1];
+            let author = unescape(author.trim())
+                .and_then(|author| author.parse().ok())
+                .ok_or_else(|| ParseVersionError::new(entry))?;
+            let idx = idx
+                .trim()
+                .parse()
+                .map_err(|_| ParseVersionError::new(entry))?;
+            version.inc(&Timestamp::new(AuthorIndex(idx), author));
+        }
+        Ok(version)
+    }
+}
+
+/// Splits `s` at each `separator` that isn't escaped with a backslash.
+fn split_unescaped(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Removes the backslashes escaping characters, or returns `None` if `s`
+/// ends with an unescaped backslash.
+fn unescape(s: &str) -> Option<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        result.push(if c == '\\' { chars.next()? } else { c });
+    }
+    Some(result)
 }
 
 impl<A: Author> PartialOrd for Version<A> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         let gt = |lhs: &Self, rhs: &Self| {
-            rhs.log_indices.iter().all(|ref t| {
+            rhs.iter().all(|t| {
                 lhs.get(&t.author)
                     .map(|lhs_idx| lhs_idx >= t.idx)
                     .unwrap_or(false)
@@ -72,6 +327,24 @@ impl<A: Author, T> Chronofold<A, T> {
         &self.version
     }
 
+    /// Returns the version seen by this replica and all its peers, i.e. the
+    /// pointwise minimum of the versions.
+    ///
+    /// Pass the latest known versions of all peers. The result can be used
+    /// for `compact`.
+    pub fn stable_version(&self, peer_versions: &[Version<A>]) -> Version<A> {
+        peer_versions
+            .iter()
+            .fold(self.version.clone(), |stable, version| stable.meet(version))
+    }
+
+    /// Returns the newest known timestamp of `author`.
+    pub(crate) fn known_timestamp(&self, author: &A) -> Option<Timestamp<A>> {
+        self.version
+            .get(author)
+            .map(|idx| Timestamp::new(idx, author.clone()))
+    }
+
     /// Returns an iterator over ops newer than the given version in log order.
     pub fn iter_newer_ops<'a, V>(
         &'a self,
@@ -82,12 +355,25 @@ impl<A: Author, T> Chronofold<A, T> {
     {
         // TODO: Don't iterate over all ops in cases where that is not
         // necessary.
-        self.iter_ops(..)// O(nlog(n))
-            .filter(move |op| match version.log_indices
-                .binary_search_by(|t| t.author.cmp(&op.id.author)) {
-                Err(_) => true,
-                Ok(idx) => op.id.idx > version.log_indices[idx].idx,
-            })
+        self.iter_ops(..)
+            .filter(move |op| !version.contains(&op.id))
+    }
+
+    /// Returns an iterator over ops newer than the given version in log
+    /// order, skipping ops by `authors`.
+    ///
+    /// Pass the requesting peer's own author to avoid echoing its ops back
+    /// to it.
+    pub fn iter_ops_excluding<'a, V>(
+        &'a self,
+        version: &'a Version<A>,
+        authors: &'a [A],
+    ) -> impl Iterator<Item = Op<A, V>> + 'a
+    where
+        V: FromLocalValue<'a, A, T> + 'a,
+    {
+        self.iter_newer_ops(version)
+            .filter(move |op: &Op<A, V>| !authors.contains(&op.id.author))
     }
 }
 
@@ -95,34 +381,55 @@ impl<A: Author, T> Chronofold<A, T> {
 #[cfg(feature = "serde")]
 mod serde {
     use super::Version;
+    use crate::{Author, Timestamp};
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
-    use std::cmp::Ord;
-    use std::collections::BTreeMap;
 
+    /// Versions are serialized as a sequence of timestamps, ordered by author.
     impl<A> Serialize for Version<A>
     where
-        A: Serialize + Ord,
+        A: Serialize + Author,
     {
         #[inline]
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
             S: Serializer,
         {
-            self.log_indices.serialize(serializer)
+            serializer.collect_seq(self.iter())
         }
     }
 
     impl<'de, A> Deserialize<'de> for Version<A>
     where
-        A: Deserialize<'de> + Ord,
+        A: Deserialize<'de> + Author,
     {
         fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
         where
             D: Deserializer<'de>,
         {
-            Ok(Self {
-                log_indices: Vec::deserialize(deserializer)?,
-            })
+            Ok(Vec::<Timestamp<A>>::deserialize(deserializer)?
+                .into_iter()
+                .collect())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switch_representation() {
+        let mut version = Version::<u8>::new();
+        for author in 0..=SMALL_VERSION_LEN as u8 {
+            version.inc(&Timestamp::new(AuthorIndex(1), author));
+        }
+        assert!(matches!(version.log_indices, LogIndices::Large(_)));
+
+        version.reset(&3, Some(Timestamp::new(AuthorIndex(0), 3)));
+        assert_eq!(Some(AuthorIndex(0)), version.get(&3));
+        version.reset(&0, None);
+        assert!(matches!(version.log_indices, LogIndices::Small(_)));
+        let authors: Vec<u8> = version.iter().map(|t| t.author).collect();
+        assert_eq!((1..=SMALL_VERSION_LEN as u8).collect::<Vec<_>>(), authors);
+    }
+}
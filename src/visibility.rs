@@ -0,0 +1,544 @@
+use std::collections::BTreeMap;
+
+use crate::{Change, Chronofold, LocalIndex};
+
+/// Structures derived from the log that speed up skipping invisible entries.
+#[derive(Clone, Debug)]
+pub(crate) struct Visibility {
+    pub(crate) runs: InvisibleRuns,
+    chunks: VisibleChunks,
+}
+
+/// The runs of invisible log entries in causal order.
+///
+/// An entry is invisible if it is a delete or a deleted element, i.e. an
+/// element directly followed by a delete. Heavily edited documents consist
+/// mostly of such entries, which iterators can skip a run at a time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct InvisibleRuns {
+    /// The last entry of each run by its first entry.
+    ends: BTreeMap<LocalIndex, LocalIndex>,
+    /// The first entry of each run by the entry following the run.
+    starts: BTreeMap<Option<LocalIndex>, LocalIndex>,
+}
+
+impl InvisibleRuns {
+    /// Returns the last entry of the run starting at `start`.
+    pub(crate) fn end(&self, start: LocalIndex) -> Option<LocalIndex> {
+        self.ends.get(&start).copied()
+    }
+
+    fn insert(&mut self, start: LocalIndex, end: LocalIndex, follower: Option<LocalIndex>) {
+        self.ends.insert(start, end);
+        self.starts.insert(follower, start);
+    }
+
+    /// Inserts the runs formed by `items`, a contiguous sequence of
+    /// `(first, last, invisible)` entries followed by `follower`.
+    fn insert_items(
+        &mut self,
+        items: Vec<(LocalIndex, LocalIndex, bool)>,
+        follower: Option<LocalIndex>,
+    ) {
+        let mut current: Option<(LocalIndex, LocalIndex)> = None;
+        for (first, last, invisible) in items {
+            if invisible {
+                current = Some((current.map_or(first, |(start, _)| start), last));
+            } else if let Some((start, end)) = current.take() {
+                self.insert(start, end, Some(first));
+            }
+        }
+        if let Some((start, end)) = current {
+            self.insert(start, end, follower);
+        }
+    }
+}
+
+/// The maximum number of entries per chunk.
+const CHUNK_LEN: usize = 64;
+
+/// A chunk of consecutive entries in causal order.
+#[derive(Clone, Debug, Default)]
+struct Chunk {
+    entries: Vec<LocalIndex>,
+    /// One bit per entry, set if the entry is a visible element.
+    visible: u64,
+}
+
+/// The log indices in causal order split into chunks, with a visibility
+/// bitmap per chunk.
+///
+/// Counting visible elements is a popcount per chunk, so converting between
+/// positions and log indices doesn't have to look at every single entry.
+#[derive(Clone, Debug, Default)]
+struct VisibleChunks {
+    /// All chunks, including ones emptied by removals.
+    chunks: Vec<Chunk>,
+    /// The chunks in causal order.
+    order: Vec<usize>,
+    /// The chunk of each log index.
+    chunk_of: Vec<Option<usize>>,
+}
+
+impl VisibleChunks {
+    fn push(&mut self, idx: LocalIndex, visible: bool) {
+        let id = match self.order.last() {
+            Some(id) if self.chunks[*id].entries.len() < CHUNK_LEN => *id,
+            _ => {
+                self.chunks.push(Chunk::default());
+                self.order.push(self.chunks.len() - 1);
+                self.chunks.len() - 1
+            }
+        };
+        let chunk = &mut self.chunks[id];
+        chunk.visible |= (visible as u64) << chunk.entries.len();
+        chunk.entries.push(idx);
+        self.set_chunk(idx, Some(id));
+    }
+
+    fn set_chunk(&mut self, idx: LocalIndex, id: Option<usize>) {
+        if self.chunk_of.len() <= idx.0 {
+            self.chunk_of.resize(idx.0 + 1, None);
+        }
+        self.chunk_of[idx.0] = id;
+    }
+
+    /// Returns the chunk of `idx` and its offset in there.
+    fn locate(&self, idx: LocalIndex) -> Option<(usize, usize)> {
+        let id = (*self.chunk_of.get(idx.0)?)?;
+        let offset = self.chunks[id]
+            .entries
+            .iter()
+            .position(|entry| *entry == idx)?;
+        Some((id, offset))
+    }
+
+    fn insert_after(&mut self, predecessor: LocalIndex, idx: LocalIndex) -> Option<()> {
+        let (mut id, mut offset) = self.locate(predecessor)?;
+        if self.chunks[id].entries.len() == CHUNK_LEN {
+            self.split(id);
+            (id, offset) = self.locate(predecessor)?;
+        }
+        let chunk = &mut self.chunks[id];
+        let low = (1 << (offset + 1)) - 1;
+        chunk.visible = (chunk.visible & low) | ((chunk.visible & !low) << 1);
+        chunk.entries.insert(offset + 1, idx);
+        self.set_chunk(idx, Some(id));
+        Some(())
+    }
+
+    /// Moves the upper half of a full chunk into a new one.
+    fn split(&mut self, id: usize) {
+        let half = CHUNK_LEN / 2;
+        let entries = self.chunks[id].entries.split_off(half);
+        let visible = self.chunks[id].visible >> half;
+        self.chunks[id].visible &= (1 << half) - 1;
+        let new_id = self.chunks.len();
+        for idx in &entries {
+            self.chunk_of[idx.0] = Some(new_id);
+        }
+        self.chunks.push(Chunk { entries, visible });
+        let ordinal = self
+            .order
+            .iter()
+            .position(|other| *other == id)
+            .expect("chunks are ordered");
+        self.order.insert(ordinal + 1, new_id);
+    }
+
+    fn remove(&mut self, idx: LocalIndex) -> Option<()> {
+        let (id, offset) = self.locate(idx)?;
+        let chunk = &mut self.chunks[id];
+        let low = (1 << offset) - 1;
+        chunk.visible = (chunk.visible & low) | ((chunk.visible >> 1) & !low);
+        chunk.entries.remove(offset);
+        if chunk.entries.is_empty() {
+            self.order.retain(|other| *other != id);
+        }
+        self.set_chunk(idx, None);
+        Some(())
+    }
+
+    fn set_visible(&mut self, idx: LocalIndex, visible: bool) -> Option<()> {
+        let (id, offset) = self.locate(idx)?;
+        let chunk = &mut self.chunks[id];
+        chunk.visible = (chunk.visible & !(1 << offset)) | ((visible as u64) << offset);
+        Some(())
+    }
+
+    fn count(&self) -> usize {
+        self.order
+            .iter()
+            .map(|id| self.chunks[*id].visible.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the number of visible elements before `idx` in causal order.
+    fn count_before(&self, idx: LocalIndex) -> Option<usize> {
+        let (id, offset) = self.locate(idx)?;
+        let before: usize = self
+            .order
+            .iter()
+            .take_while(|other| **other != id)
+            .map(|other| self.chunks[*other].visible.count_ones() as usize)
+            .sum();
+        let low = (1u64 << offset) - 1;
+        Some(before + (self.chunks[id].visible & low).count_ones() as usize)
+    }
+
+    /// Returns the visible element at `position`.
+    fn nth_visible(&self, mut position: usize) -> Option<LocalIndex> {
+        for id in &self.order {
+            let chunk = &self.chunks[*id];
+            let count = chunk.visible.count_ones() as usize;
+            if position < count {
+                let mut visible = chunk.visible;
+                for _ in 0..position {
+                    visible &= visible - 1;
+                }
+                return Some(chunk.entries[visible.trailing_zeros() as usize]);
+            }
+            position -= count;
+        }
+        None
+    }
+}
+
+impl<A, T> Chronofold<A, T> {
+    /// Returns `true` if the entry at `index` is a delete or a deleted
+    /// element.
+    pub(crate) fn is_invisible(&self, index: LocalIndex) -> bool {
+        match self.log.get(index.0) {
+            Some(Change::Delete) => true,
+            Some(Change::Insert(_)) => self
+                .index_after(index)
+                .is_some_and(|next| self.is_delete_of(next, index)),
+            Some(Change::Root) | None => false,
+        }
+    }
+
+    /// Returns `true` if the entry at `next` deletes the element at `index`.
+    ///
+    /// An element's first delete directly follows it. Other deletes might
+    /// follow any entry, e.g. an element inserted after a concurrent delete
+    /// of the same element.
+    pub(crate) fn is_delete_of(&self, next: LocalIndex, index: LocalIndex) -> bool {
+        matches!(self.log[next.0], Change::Delete) && self.get_reference(&next) == Some(index)
+    }
+
+    /// Builds the visibility structures if they are missing, e.g. after
+    /// deserialization.
+    ///
+    /// This has to be called before changing the log. During a remote batch
+    /// they're left missing until the batch ends.
+    pub(crate) fn prepare_visibility(&mut self) {
+        if self.visibility.is_none() && self.remote_batch.is_none() {
+            self.rebuild_visibility();
+        }
+    }
+
+    /// Rebuilds the visibility structures from scratch.
+    pub(crate) fn rebuild_visibility(&mut self) {
+        let mut runs = InvisibleRuns::default();
+        let mut chunks = VisibleChunks::default();
+        chunks.push(self.root, false);
+        let mut items = Vec::new();
+        for (change, idx) in self.iter_log_indices_causal_range(..) {
+            let invisible = self.is_invisible(idx);
+            chunks.push(idx, !invisible && matches!(change, Change::Insert(_)));
+            items.push((idx, idx, invisible));
+        }
+        runs.insert_items(items, None);
+        self.len = Some(chunks.count());
+        self.visibility = Some(Visibility { runs, chunks });
+    }
+
+    /// Updates the number of visible elements for a change about to be
+    /// applied.
+    ///
+    /// `deletes_element` tells whether the change's reference is a visible
+    /// element, which a delete would hide.
+    pub(crate) fn count_change(&mut self, change: &Change<T>, deletes_element: bool) {
+        if let Some(len) = &mut self.len {
+            match change {
+                Change::Insert(_) => *len += 1,
+                Change::Delete if deletes_element => *len -= 1,
+                Change::Delete | Change::Root => {}
+            }
+        }
+    }
+
+    /// Returns `true` if `index` is a visible element.
+    pub(crate) fn is_visible_element(&self, idx: LocalIndex) -> bool {
+        matches!(self.log[idx.0], Change::Insert(_)) && !self.is_invisible(idx)
+    }
+
+    /// Updates the visibility structures after the entries from `first` to
+    /// `last` were linked in after `predecessor`.
+    pub(crate) fn link_visibility(
+        &mut self,
+        predecessor: LocalIndex,
+        first: LocalIndex,
+        last: LocalIndex,
+    ) {
+        // If anything is unexpected, they are rebuilt before the next change.
+        if let Some(mut visibility) = self.visibility.take() {
+            if self
+                .link_runs(&mut visibility.runs, predecessor, first, last)
+                .is_some()
+                && self
+                    .link_chunks(&mut visibility.chunks, predecessor, first, last)
+                    .is_some()
+            {
+                self.visibility = Some(visibility);
+            }
+        }
+    }
+
+    /// Updates the visibility structures after the visible element `removed`
+    /// was unlinked from after `predecessor`.
+    ///
+    /// This has to be called before `removed` is removed from the log.
+    pub(crate) fn unlink_visibility(&mut self, predecessor: LocalIndex, removed: LocalIndex) {
+        if let Some(mut visibility) = self.visibility.take() {
+            if self
+                .unlink_runs(&mut visibility.runs, predecessor, removed)
+                .is_some()
+                && visibility.chunks.remove(removed).is_some()
+            {
+                self.visibility = Some(visibility);
+            }
+        }
+    }
+
+    /// Returns the number of visible elements, if it can be counted without
+    /// iterating.
+    pub(crate) fn count_visible(&self) -> Option<usize> {
+        Some(self.visibility.as_ref()?.chunks.count())
+    }
+
+    /// Returns the number of visible elements before `idx` in causal order.
+    pub(crate) fn count_visible_before(&self, idx: LocalIndex) -> Option<usize> {
+        self.visibility.as_ref()?.chunks.count_before(idx)
+    }
+
+    /// Returns the visible element at `position`.
+    pub(crate) fn nth_visible(&self, position: usize) -> Option<LocalIndex> {
+        self.visibility.as_ref()?.chunks.nth_visible(position)
+    }
+
+    fn link_chunks(
+        &self,
+        chunks: &mut VisibleChunks,
+        predecessor: LocalIndex,
+        first: LocalIndex,
+        last: LocalIndex,
+    ) -> Option<()> {
+        let mut previous = predecessor;
+        let mut idx = first;
+        loop {
+            chunks.insert_after(previous, idx)?;
+            chunks.set_visible(idx, self.is_visible_element(idx))?;
+            if idx == last {
+                break;
+            }
+            previous = idx;
+            idx = self.index_after(idx)?;
+        }
+        chunks.set_visible(predecessor, self.is_visible_element(predecessor))
+    }
+
+    fn link_runs(
+        &self,
+        runs: &mut InvisibleRuns,
+        predecessor: LocalIndex,
+        first: LocalIndex,
+        last: LocalIndex,
+    ) -> Option<()> {
+        let successor = self.index_after(last);
+        let was_invisible = match self.log[predecessor.0] {
+            Change::Delete => true,
+            Change::Insert(_) => successor.is_some_and(|next| self.is_delete_of(next, predecessor)),
+            Change::Root => false,
+        };
+
+        let mut items = Vec::new();
+        let mut right = None;
+        if was_invisible {
+            // The predecessor stays invisible, as nothing is inserted between
+            // an element and its deletes. Find the run it was part of, which
+            // might continue after the new entries.
+            let mut end = predecessor;
+            if let Some(successor) = successor.filter(|idx| self.is_invisible(*idx)) {
+                end = successor;
+                while let Some(next) = self.index_after(end).filter(|idx| self.is_invisible(*idx)) {
+                    end = next;
+                }
+                right = Some((successor, end));
+            }
+            let follower = if end == predecessor {
+                successor
+            } else {
+                self.index_after(end)
+            };
+            let start = runs
+                .starts
+                .remove(&follower)
+                .filter(|_| self.is_invisible(predecessor))?;
+            runs.ends.remove(&start);
+            items.push((start, predecessor, true));
+        } else {
+            let is_invisible = self.is_invisible(predecessor);
+            if is_invisible {
+                if let Some(start) = runs.starts.remove(&Some(predecessor)) {
+                    let end = runs.ends.remove(&start).expect("runs have ends");
+                    items.push((start, end, true));
+                }
+            }
+            items.push((predecessor, predecessor, is_invisible));
+            if let Some(successor) = successor {
+                if let Some(end) = runs.ends.remove(&successor) {
+                    runs.starts.remove(&self.index_after(end));
+                    right = Some((successor, end));
+                }
+            }
+        }
+
+        let mut idx = first;
+        loop {
+            items.push((idx, idx, self.is_invisible(idx)));
+            if idx == last {
+                break;
+            }
+            idx = self.index_after(idx)?;
+        }
+        let follower = match right {
+            Some((start, end)) => {
+                items.push((start, end, true));
+                self.index_after(end)
+            }
+            None => successor,
+        };
+        runs.insert_items(items, follower);
+        Some(())
+    }
+
+    fn unlink_runs(
+        &self,
+        runs: &mut InvisibleRuns,
+        predecessor: LocalIndex,
+        removed: LocalIndex,
+    ) -> Option<()> {
+        let successor = self.index_after(predecessor);
+        let left = runs.starts.remove(&Some(removed));
+        if self.is_invisible(removed) || self.is_invisible(predecessor) != left.is_some() {
+            return None;
+        }
+        if let Some(start) = left {
+            match successor.and_then(|idx| runs.ends.get(&idx).copied()) {
+                Some(end) => {
+                    runs.ends.remove(&successor.expect("runs have starts"));
+                    runs.insert(start, end, self.index_after(end));
+                }
+                None => runs.insert(start, predecessor, successor),
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    impl VisibleChunks {
+        fn flatten(&self) -> Vec<(LocalIndex, bool)> {
+            self.order
+                .iter()
+                .flat_map(|id| {
+                    let chunk = &self.chunks[*id];
+                    chunk
+                        .entries
+                        .iter()
+                        .enumerate()
+                        .map(move |(offset, idx)| (*idx, chunk.visible & (1 << offset) != 0))
+                })
+                .collect()
+        }
+    }
+
+    fn assert_visibility_consistent(cfold: &mut Chronofold<u8, char>) {
+        cfold.prepare_visibility();
+        let mut rebuilt = cfold.clone();
+        rebuilt.rebuild_visibility();
+        let (visibility, rebuilt) = (
+            cfold.visibility.as_ref().unwrap(),
+            rebuilt.visibility.unwrap(),
+        );
+        assert_eq!(rebuilt.runs, visibility.runs, "{}", cfold.log_table());
+        assert_eq!(
+            rebuilt.chunks.flatten(),
+            visibility.chunks.flatten(),
+            "{}",
+            cfold.log_table()
+        );
+
+        let mut scanning = cfold.clone();
+        scanning.visibility = None;
+        let elements: Vec<_> = scanning.iter().collect();
+        assert!(cfold.iter().eq(elements.iter().copied()));
+        assert_eq!(elements.len(), cfold.len());
+        for (position, (_, idx)) in elements.iter().enumerate() {
+            assert_eq!(Some(*idx), cfold.nth_visible(position));
+            assert_eq!(Some(position), cfold.count_visible_before(*idx));
+            assert_eq!(Some(elements[position]), cfold.iter().nth(position));
+            assert_eq!(elements.len() - position, cfold.iter_range(*idx..).count());
+        }
+        assert_eq!(None, cfold.nth_visible(elements.len()));
+    }
+
+    #[test]
+    fn visibility_is_maintained() {
+        let mut rng = rand::thread_rng();
+        let mut cfold = Chronofold::<u8, char>::default();
+        cfold.session(1).extend("Hello".chars());
+        let mut replica = cfold.clone();
+        for _ in 0..50 {
+            for (author, cfold) in &mut [(1, &mut cfold), (2, &mut replica)] {
+                let indices: Vec<LocalIndex> = cfold.iter().map(|(_, idx)| idx).collect();
+                let mut session = cfold.session(*author);
+                match indices.len() {
+                    0 => {
+                        session.extend("abc".chars());
+                    }
+                    len => {
+                        let idx = indices[rng.gen_range(0, len)];
+                        if rng.gen_bool(0.5) {
+                            session.remove(idx);
+                        } else {
+                            session.insert_after(idx, 'x');
+                        }
+                    }
+                }
+            }
+            let ops: Vec<_> = cfold
+                .iter_newer_ops(replica.version())
+                .map(crate::Op::cloned)
+                .collect();
+            let replica_ops: Vec<_> = replica
+                .iter_newer_ops(cfold.version())
+                .map(crate::Op::cloned)
+                .collect();
+            for op in ops {
+                replica.apply(op).unwrap();
+            }
+            for op in replica_ops {
+                cfold.apply(op).unwrap();
+            }
+            assert_visibility_consistent(&mut cfold);
+            assert_visibility_consistent(&mut replica);
+        }
+    }
+}
@@ -3,7 +3,12 @@
 //! The main purpose of these tests is not to cover all corner cases, but
 //! rather to show that they behave like there counterparts on `Vec`.
 
-use chronofold::{Change, Chronofold, LocalIndex, Session};
+use chronofold::{
+    AuthorIndex, Change, ChangeEvent, Chronofold, FlushPolicy, LocalIndex, Op, OpPayload, Session,
+    Timestamp,
+};
+
+use std::time::{Duration, Instant};
 
 #[test]
 fn is_empty() {
@@ -135,17 +140,83 @@ fn splice() {
     );
 }
 
+#[test]
+fn replace() {
+    assert_elements_eq(
+        "foobar".chars(),
+        |vec| {
+            vec[3] = 'c';
+        },
+        |cfold_session| {
+            cfold_session.replace(LocalIndex(4), 'c');
+        },
+    );
+}
+
+#[test]
+fn swap() {
+    assert_elements_eq(
+        "foobar".chars(),
+        |vec| {
+            vec.swap(0, 3);
+        },
+        |cfold_session| {
+            cfold_session.swap(LocalIndex(1), LocalIndex(4));
+        },
+    );
+}
+
+#[test]
+fn splice_diff() {
+    assert_elements_eq(
+        "hello world".chars(),
+        |vec| {
+            vec.splice(.., "hello, wide world!".chars());
+        },
+        |cfold_session| {
+            cfold_session.splice_diff(.., "hello, wide world!".chars());
+        },
+    );
+}
+
+#[test]
+fn edit_by_timestamp() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".chars());
+    let b = cfold.timestamp(LocalIndex(2)).unwrap();
+    let c = cfold.timestamp(LocalIndex(3)).unwrap();
+    let unknown = Timestamp::new(AuthorIndex(9), 9);
+
+    let mut session = cfold.session(2);
+    assert!(session.insert_after_timestamp(&b, '!').is_some());
+    assert!(session.remove_by_timestamp(&c));
+    assert_eq!(None, session.insert_after_timestamp(&unknown, '?'));
+    assert!(!session.remove_by_timestamp(&unknown));
+    assert_eq!("ab!", format!("{}", cfold));
+}
+
+#[test]
+fn eq_str() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    assert_eq!(cfold, "");
+    cfold.session(1).extend("Hello".chars());
+    assert_eq!(cfold, "Hello");
+    assert!(cfold == *"Hello");
+    assert_ne!(cfold, "Hell");
+    assert_ne!(cfold, "Hello!");
+}
+
 fn assert_elements_eq<I, T, F, G>(initial_values: I, mutate_vec: F, mutate_chronofold: G)
 where
     I: Iterator<Item = T>,
-    F: FnOnce(&mut Vec<T>) -> (),
-    G: FnOnce(&mut Session<u8, T>) -> (),
+    F: FnOnce(&mut Vec<T>),
+    G: FnOnce(&mut Session<u8, T>),
     T: PartialEq + Clone + std::fmt::Debug,
 {
     let mut vec: Vec<T> = initial_values.collect();
     let mut cfold = Chronofold::<u8, T>::default();
     let mut cfold_session = cfold.session(1);
-    cfold_session.extend(vec.clone().into_iter());
+    cfold_session.extend(vec.clone());
 
     mutate_vec(&mut vec);
     mutate_chronofold(&mut cfold_session);
@@ -155,3 +226,488 @@ where
         cfold.iter_elements().collect::<Vec<_>>()
     );
 }
+
+#[test]
+fn checked_index_arithmetic() {
+    assert_eq!(Some(LocalIndex(3)), LocalIndex(1).checked_add(2));
+    assert_eq!(None, LocalIndex(usize::MAX).checked_add(1));
+    assert_eq!(None, AuthorIndex(1).checked_sub(2));
+    assert_eq!(
+        LocalIndex(usize::MAX),
+        LocalIndex(usize::MAX).saturating_add(1)
+    );
+    assert_eq!(AuthorIndex(0), AuthorIndex(1).saturating_sub(2));
+}
+
+#[test]
+fn len_after_concurrent_deletes() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("abc".chars());
+    let mut bob = alice.clone();
+    let ops_alice: Vec<Op<u8, char>> = {
+        let mut session = alice.session(1);
+        session.remove(LocalIndex(2));
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    let ops_bob: Vec<Op<u8, char>> = {
+        let mut session = bob.session(2);
+        session.remove(LocalIndex(2));
+        session.remove(LocalIndex(3));
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    alice.apply_all(ops_bob).unwrap();
+    bob.apply_all(ops_alice).unwrap();
+    assert_eq!(1, alice.len());
+    assert_eq!(1, bob.len());
+
+    let mut session = alice.session(1);
+    session.set_coalescing(true);
+    let idx = session.push_back('d');
+    session.remove(idx);
+    assert_eq!(1, alice.len());
+}
+
+#[test]
+fn as_vec_cached() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello".chars());
+    assert_eq!(&['H', 'e', 'l', 'l', 'o'], cfold.as_vec_cached());
+
+    cfold.session(1).remove(LocalIndex(1));
+    cfold.session(1).push_back('!');
+    assert_eq!(&['e', 'l', 'l', 'o', '!'], cfold.as_vec_cached());
+}
+
+#[test]
+fn iter_chunks() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello world".chars());
+    cfold
+        .session(1)
+        .splice(LocalIndex(6)..LocalIndex(7), ", ".chars());
+    assert_eq!(cfold, "Hello, world");
+
+    let chunks: Vec<String> = cfold
+        .iter_chunks()
+        .map(|chunk| chunk.iter().collect())
+        .collect();
+    assert_eq!(vec!["Hello", ", ", "world"], chunks);
+}
+
+#[test]
+fn freeze() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello world".chars());
+    cfold
+        .session(1)
+        .splice(LocalIndex(6)..LocalIndex(7), ", ".chars());
+    let frozen = cfold.freeze();
+
+    let reader = {
+        let frozen = frozen.clone();
+        std::thread::spawn(move || frozen.iter().collect::<String>())
+    };
+    cfold.session(1).push_back('!');
+    assert_eq!("Hello, world", reader.join().unwrap());
+    assert_eq!(12, frozen.len());
+
+    // Unchanged chunks are shared with the previous snapshot.
+    let refrozen = cfold.freeze();
+    assert_eq!("Hello, world!", refrozen.iter().collect::<String>());
+    let old: Vec<&[char]> = frozen.chunks().collect();
+    let new: Vec<&[char]> = refrozen.chunks().collect();
+    assert_eq!(4, new.len());
+    for (old, new) in old.iter().zip(&new) {
+        assert!(std::ptr::eq(*old, *new));
+    }
+}
+
+#[test]
+fn freeze_after_coalescing() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let frozen = {
+        let mut session = cfold.session(1);
+        session.set_coalescing(true);
+        session.extend("ab".chars());
+        let frozen = session.as_ref().freeze();
+        session.remove(LocalIndex(2));
+        session.push_back('c');
+        frozen
+    };
+    assert_eq!("ab", frozen.iter().collect::<String>());
+    assert_eq!("ac", cfold.freeze().iter().collect::<String>());
+}
+
+#[test]
+fn slice() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello world".chars());
+    cfold.session(1).remove(LocalIndex(2));
+    assert_eq!(cfold, "Hllo world");
+
+    assert_eq!("llo", cfold.slice(1..4).collect::<String>());
+    assert_eq!("world", cfold.slice_to_string(5..));
+    assert_eq!("Hl", cfold.slice_to_string(..=1));
+    assert_eq!("", cfold.slice_to_string(20..));
+    assert_eq!(&['l', 'o', ' '], &cfold[2..5]);
+}
+
+#[test]
+fn positions_and_indices() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello world".chars());
+    cfold.session(1).remove(LocalIndex(2));
+    cfold.session(1).insert_after(LocalIndex(5), ',');
+    assert_eq!(cfold, "Hllo, world");
+
+    for (position, (_, idx)) in cfold.iter().enumerate() {
+        assert_eq!(Some(idx), cfold.position_to_index(position));
+        assert_eq!(Some(position), cfold.index_to_position(idx));
+    }
+    assert_eq!(Some(LocalIndex(13)), cfold.position_to_index(4));
+    assert_eq!(None, cfold.position_to_index(11));
+    assert_eq!(None, cfold.index_to_position(LocalIndex(0)));
+    assert_eq!(None, cfold.index_to_position(LocalIndex(2)));
+    assert_eq!(None, cfold.index_to_position(LocalIndex(12)));
+    assert_eq!(None, cfold.index_to_position(LocalIndex(99)));
+}
+
+#[test]
+fn binary_search() {
+    let mut cfold = Chronofold::<u8, u32>::default();
+    cfold.session(1).extend(vec![1, 3, 5, 7, 9]);
+    cfold.session(1).remove(LocalIndex(2));
+    cfold.session(1).insert_after(LocalIndex(3), 6);
+
+    assert_eq!(
+        vec![1, 5, 6, 7, 9],
+        cfold.iter_elements().copied().collect::<Vec<_>>()
+    );
+    assert_eq!(2, cfold.partition_point(|x| *x < 6));
+    assert_eq!(Ok(3), cfold.binary_search_by(|x| x.cmp(&7)));
+    assert_eq!(Err(1), cfold.binary_search_by(|x| x.cmp(&3)));
+    assert_eq!(Err(5), cfold.binary_search_by(|x| x.cmp(&10)));
+}
+
+#[test]
+fn flush_policy() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut session = cfold.session(1);
+    session.set_flush_policy(FlushPolicy {
+        max_ops: Some(3),
+        max_latency: Some(Duration::from_secs(1)),
+    });
+    let now = Instant::now();
+    assert_eq!(None, session.poll_flush(now));
+
+    session.extend("ab".chars());
+    assert_eq!(2, session.staged_len());
+    assert_eq!(None, session.poll_flush(now));
+    session.push_back('c');
+    assert_eq!(3, session.poll_flush(now).unwrap().len());
+    assert_eq!(0, session.staged_len());
+
+    session.push_back('d');
+    assert_eq!(None, session.poll_flush(now));
+    let ops = session.poll_flush(now + Duration::from_secs(2)).unwrap();
+    assert_eq!(1, ops.len());
+    assert!(matches!(ops[0].payload, OpPayload::Insert(_, 'd')));
+
+    assert!(session.flush().is_empty());
+}
+
+#[test]
+fn authored_range() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".chars());
+    cfold.session(1).remove(LocalIndex(2));
+    let stable = cfold.version().clone();
+    cfold.compact(&stable);
+
+    let mut session = cfold.session(2);
+    assert_eq!(0, session.op_count());
+    assert_eq!(AuthorIndex(5)..AuthorIndex(5), session.authored_range());
+    session.extend("xy".chars());
+    let remote = Op::insert(
+        Timestamp::new(AuthorIndex(7), 3),
+        Some(Timestamp::new(AuthorIndex(6), 2)),
+        'z',
+    );
+    session.as_mut().apply(remote).unwrap();
+    assert_eq!(2, session.op_count());
+    assert_eq!(AuthorIndex(5)..AuthorIndex(7), session.authored_range());
+    session.remove(LocalIndex(1));
+    assert_eq!(3, session.op_count());
+    assert_eq!(AuthorIndex(5)..AuthorIndex(9), session.authored_range());
+    let ids: Vec<AuthorIndex> = session.iter_ops::<&char>().map(|op| op.id.idx).collect();
+    assert_eq!(vec![AuthorIndex(5), AuthorIndex(6), AuthorIndex(8)], ids);
+}
+
+#[test]
+fn session_reads() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut session = cfold.session(1);
+    assert!(session.is_empty());
+    session.extend("abc".chars());
+    session.remove(LocalIndex(2));
+    assert_eq!(2, session.len());
+    assert_eq!(Some(&Change::Insert('c')), session.get(LocalIndex(3)));
+    assert_eq!("ac", session.iter_elements().collect::<String>());
+    assert_eq!(
+        vec![LocalIndex(1), LocalIndex(3)],
+        session.iter().map(|(_, idx)| idx).collect::<Vec<_>>()
+    );
+    assert_eq!(Some(LocalIndex(3)), session.position_to_index(1));
+    assert_eq!(Some(1), session.index_to_position(LocalIndex(3)));
+}
+
+/// Renders a chronofold without requiring `A: Author`.
+fn render<A, T: std::fmt::Display>(cfold: &Chronofold<A, T>) -> (String, usize, Vec<&T>) {
+    let elements = cfold.iter_elements().collect();
+    (cfold.to_string(), cfold.len(), elements)
+}
+
+#[test]
+fn read_without_author_bound() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".chars());
+    let (text, len, elements) = render(&cfold);
+    assert_eq!("abc", text);
+    assert_eq!(3, len);
+    assert_eq!(vec![&'a', &'b', &'c'], elements);
+}
+
+/// An author type that doesn't fit into a `usize`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+struct PeerId(u128);
+
+impl std::fmt::Display for PeerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+#[test]
+fn rich_authors() {
+    let peer = |n: u128| PeerId(n << 100 | n);
+    let mut alice = Chronofold::<PeerId, char>::new(peer(0));
+    for n in 1..=40 {
+        alice.session(peer(n)).push_back('a');
+    }
+    let mut ops: Vec<Op<PeerId, char>> = alice.iter_ops(LocalIndex(1)..).map(Op::cloned).collect();
+    ops.reverse();
+
+    // Authors are seen in a different order, so they're interned differently.
+    let mut bob = Chronofold::<PeerId, char>::new(peer(0));
+    assert!(bob.apply_iter(ops).is_complete());
+    assert_eq!(alice, bob);
+    assert_eq!(40, bob.len());
+    assert_eq!(41, bob.version().len());
+    assert_eq!(Some(AuthorIndex(7)), bob.version().get(&peer(7)));
+    let idx = bob
+        .log_index(&Timestamp::new(AuthorIndex(7), peer(7)))
+        .unwrap();
+    assert_eq!(
+        Some(Timestamp::new(AuthorIndex(7), peer(7))),
+        bob.timestamp(idx)
+    );
+}
+
+/// An author type that is neither `Copy` nor `Display`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+struct PublicKey(Vec<u8>);
+
+#[test]
+fn owned_authors() {
+    let key = |name: &str| PublicKey(name.as_bytes().to_vec());
+    let mut alice = Chronofold::<PublicKey, char>::new(key("root"));
+    alice.session(key("alice")).extend("hello".chars());
+    let mut bob = alice.clone();
+    alice.session(key("alice")).push_back('!');
+    bob.session(key("bob")).push_front('>');
+
+    let ops_alice: Vec<Op<PublicKey, char>> = alice
+        .iter_newer_ops(bob.version())
+        .map(Op::cloned)
+        .collect();
+    let ops_bob: Vec<Op<PublicKey, char>> = bob
+        .iter_newer_ops(alice.version())
+        .map(Op::cloned)
+        .collect();
+    alice.apply_all(ops_bob).unwrap();
+    bob.apply_all(ops_alice).unwrap();
+    assert_eq!(">hello!", alice.to_string());
+    assert_eq!(">hello!", bob.to_string());
+    assert_eq!(1, bob.contributions()[&key("bob")].inserted);
+    assert_eq!(Some(AuthorIndex(6)), bob.version().get(&key("alice")));
+}
+
+#[test]
+fn element_ids() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let (a, c) = {
+        let mut session = cfold.session(1);
+        let a = session.insert_after_id(None, 'a').unwrap();
+        let c = session.insert_after_id(Some(&a), 'c').unwrap();
+        (a, c)
+    };
+    assert_eq!(Some(LocalIndex(1)), cfold.resolve(&a));
+    assert_eq!(Some(a), cfold.element_id(LocalIndex(1)));
+    assert_eq!(Some(c), cfold.id_at(1));
+    assert_eq!(Some(1), cfold.position_of(&c));
+    assert_eq!(Some(&'c'), cfold.get_by_id(&c));
+    assert_eq!(c, c.to_string().parse().unwrap());
+    assert_eq!(Timestamp::new(AuthorIndex(2), 1), c.into());
+
+    // Ids are the same on other replicas.
+    let mut replica = Chronofold::<u8, char>::default();
+    replica
+        .apply_all(cfold.iter_ops(LocalIndex(1)..).map(Op::cloned))
+        .unwrap();
+    let b = replica.session(2).insert_after_id(Some(&a), 'b').unwrap();
+    cfold
+        .apply_all(
+            replica
+                .iter_newer_ops(cfold.version())
+                .map(Op::cloned)
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+    assert_eq!("abc", format!("{}", cfold));
+    assert_eq!(Some(1), cfold.position_of(&b));
+
+    assert!(cfold.session(1).remove_by_id(&b));
+    assert_eq!(None, cfold.get_by_id(&b));
+    assert_eq!(None, cfold.position_of(&b));
+    assert_eq!(None, cfold.element_id(LocalIndex(4)));
+}
+
+#[test]
+fn log_index_stays_in_sync() {
+    let assert_indexed = |cfold: &Chronofold<u8, char>| {
+        for idx in (0..cfold.stats().log_len).map(LocalIndex) {
+            assert_eq!(Some(idx), cfold.log_index(&cfold.timestamp(idx).unwrap()));
+        }
+    };
+    let mut source = Chronofold::<u8, char>::default();
+    source.session(1).extend("abc".chars());
+    source.session(2).insert_after(LocalIndex(0), 'x');
+    let mut ops: Vec<Op<u8, char>> = source.iter_ops(LocalIndex(1)..).map(Op::cloned).collect();
+    ops.reverse();
+
+    let mut cfold = Chronofold::<u8, char>::default();
+    assert_indexed(&cfold);
+    cfold.apply_iter(ops);
+    assert_indexed(&cfold);
+    {
+        let mut session = cfold.session(3);
+        session.set_coalescing(true);
+        let idx = session.push_back('d');
+        session.remove(idx);
+    }
+    assert_indexed(&cfold);
+    assert_eq!(None, cfold.log_index(&Timestamp::new(AuthorIndex(5), 3)));
+    let deleted = Timestamp::new(AuthorIndex(3), 1);
+    cfold.session(3).remove_by_timestamp(&deleted);
+    let remap = cfold.compact(&cfold.version().clone());
+    assert_eq!(2, remap.removed());
+    assert_indexed(&cfold);
+    assert_eq!(None, cfold.log_index(&deleted));
+}
+
+#[test]
+fn subscribe() {
+    use std::sync::{Arc, Mutex};
+
+    // Mirrors the visible elements by applying the events.
+    let mirror = Arc::new(Mutex::new(Vec::new()));
+    let mut cfold = Chronofold::<u8, char>::default();
+    let view = mirror.clone();
+    let subscription = cfold.subscribe(move |event| {
+        let mut view = view.lock().unwrap();
+        match event {
+            ChangeEvent::Insert {
+                position, value, ..
+            } => view.insert(*position, **value),
+            ChangeEvent::Delete { position, .. } => {
+                view.remove(*position);
+            }
+        }
+    });
+    let check = |cfold: &Chronofold<u8, char>| {
+        assert_eq!(
+            format!("{}", cfold),
+            mirror.lock().unwrap().iter().collect::<String>()
+        );
+    };
+
+    cfold.session(1).extend("hello".chars());
+    cfold
+        .session(1)
+        .splice(LocalIndex(2)..LocalIndex(4), "ipp".chars());
+    check(&cfold);
+
+    let mut session = cfold.session(1);
+    session.set_coalescing(true);
+    let last = session.push_back('!');
+    session.remove(last);
+    check(&cfold);
+
+    // Remote changes in a batch.
+    let mut replica = cfold.clone();
+    let version = replica.version().clone();
+    replica.session(2).push_front('>');
+    replica.session(2).remove(LocalIndex(3));
+    let idx = replica.session(2).push_back('x');
+    replica.session(2).remove(idx);
+    let idx = replica.position_to_index(4).unwrap();
+    replica.session(2).replace(idx, 'O');
+    let ops: Vec<Op<u8, char>> = replica.iter_newer_ops(&version).map(Op::cloned).collect();
+    cfold.apply_batch(ops);
+    assert_eq!(format!("{}", replica), format!("{}", cfold));
+    check(&cfold);
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = events.clone();
+    cfold.subscribe(move |event| {
+        seen.lock()
+            .unwrap()
+            .push((event.position(), event.author()))
+    });
+    cfold.session(3).remove(LocalIndex(1));
+    assert_eq!(vec![(1, 3)], *events.lock().unwrap());
+    check(&cfold);
+
+    assert!(cfold.unsubscribe(subscription));
+    assert!(!cfold.unsubscribe(subscription));
+    cfold.session(1).push_back('?');
+    assert_eq!(2, events.lock().unwrap().len());
+}
+
+#[test]
+fn rebuild_indices() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("hello world".chars());
+    cfold
+        .session(2)
+        .splice(LocalIndex(1)..LocalIndex(6), "Hi".chars());
+    cfold.session(1).remove(LocalIndex(8));
+    let timestamp = cfold.timestamp(LocalIndex(9)).unwrap();
+    let contributions = cfold.contributions().clone();
+    let frozen = cfold.freeze();
+
+    let mut rebuilt = cfold.clone();
+    rebuilt.rebuild_indices();
+    assert_eq!(cfold, rebuilt);
+    assert_eq!("Hi wrld", format!("{}", rebuilt));
+    assert_eq!(cfold.len(), rebuilt.len());
+    assert_eq!(Some(LocalIndex(9)), rebuilt.log_index(&timestamp));
+    assert_eq!(&contributions, rebuilt.contributions());
+    assert!(frozen.iter().eq(rebuilt.freeze().iter()));
+    assert!(cfold.iter().eq(rebuilt.iter()));
+    assert!(cfold.iter_rev().eq(rebuilt.iter_rev()));
+
+    rebuilt.session(1).push_back('!');
+    cfold.session(1).push_back('!');
+    assert_eq!(cfold, rebuilt);
+}
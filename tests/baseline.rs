@@ -0,0 +1,39 @@
+use chronofold::{Chronofold, LocalIndex};
+
+mod common;
+use common::ops_since;
+
+#[test]
+fn bootstrap_from_baseline() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("Hello world".chars());
+    alice
+        .session(1)
+        .splice(LocalIndex(2)..LocalIndex(6), "i".chars());
+    let mut bob = alice.clone();
+    let stable = alice.version().clone();
+
+    // Edits after the stable version aren't part of the baseline.
+    alice.session(1).remove(LocalIndex(6));
+    bob.session(2).extend("!".chars());
+    alice.apply_all(ops_since(&bob, alice.version())).unwrap();
+    assert_eq!("Hiworld!", format!("{}", alice));
+
+    let baseline = alice.baseline(&stable);
+    assert_eq!(&stable, baseline.version());
+    assert_eq!("Hi world", format!("{}", baseline.chronofold()));
+    assert_eq!(9, baseline.chronofold().iter_changes().count());
+
+    // Carol starts from the baseline and catches up with Alice.
+    let mut carol = baseline.into_chronofold();
+    carol.apply_all(ops_since(&alice, carol.version())).unwrap();
+    assert_eq!("Hiworld!", format!("{}", carol));
+    assert_eq!(alice.version(), carol.version());
+
+    // All three keep converging.
+    carol.session(3).push_front('>');
+    alice.apply_all(ops_since(&carol, alice.version())).unwrap();
+    bob.apply_all(ops_since(&alice, bob.version())).unwrap();
+    assert_eq!(">Hiworld!", format!("{}", alice));
+    assert_eq!(format!("{}", alice), format!("{}", bob));
+}
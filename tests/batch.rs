@@ -0,0 +1,235 @@
+use chronofold::{Chronofold, ChronofoldError, LocalIndex, Op, OpBuffer, OpPayload};
+use std::time::{Duration, Instant};
+
+mod common;
+use common::t;
+
+#[test]
+fn apply_iter_out_of_order() {
+    let mut source = Chronofold::<u8, char>::default();
+    source.session(1).extend("abc".chars());
+    let mut ops: Vec<Op<u8, char>> = source.iter_ops(..).map(Op::cloned).collect();
+    ops.reverse();
+
+    let mut cfold = Chronofold::<u8, char>::default();
+    let summary = cfold.apply_iter(ops);
+    assert_eq!(3, summary.applied);
+    assert_eq!(vec![t(0, 0)], summary.duplicates);
+    assert!(summary.is_complete());
+    assert_eq!("abc", format!("{}", cfold));
+}
+
+#[test]
+fn apply_iter_missing_reference() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let blocked = Op::insert(t(2, 1), Some(t(1, 1)), '!');
+    let ok = Op::insert(t(1, 2), Some(t(0, 0)), '?');
+    let summary = cfold.apply_iter(vec![blocked.clone(), ok]);
+    assert_eq!(1, summary.applied);
+    assert!(summary.duplicates.is_empty());
+    assert_eq!(
+        vec![ChronofoldError::UnknownReference(blocked, None)],
+        summary.deferred
+    );
+    assert_eq!(
+        vec![&t(1, 1)],
+        summary.waiting_for().into_iter().collect::<Vec<_>>()
+    );
+    assert_eq!("?", format!("{}", cfold));
+}
+
+#[test]
+fn remote_batch() {
+    let mut source = Chronofold::<u8, char>::default();
+    source.session(1).extend("hello world".chars());
+    source.session(2).remove(LocalIndex(5));
+    let ops: Vec<Op<u8, char>> = source.iter_ops(LocalIndex(1)..).map(Op::cloned).collect();
+
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut batch = cfold.begin_remote_batch();
+    batch.apply_all(ops[..6].to_vec()).unwrap();
+    assert_eq!(6, batch.as_ref().len());
+    // The version is only updated when the batch ends, but ops applied
+    // during the batch are still recognized:
+    assert_eq!(None, batch.as_ref().version().get(&1));
+    assert_eq!(
+        Err(ChronofoldError::AlreadyApplied(ops[0].clone())),
+        batch.apply(ops[0].clone())
+    );
+    assert_eq!(6, batch.apply_iter(ops.clone()).applied);
+    batch.end();
+    assert_eq!(source.version(), cfold.version());
+
+    assert_eq!(cfold, source);
+    assert_eq!(10, cfold.len());
+    assert_eq!(
+        "hell world",
+        cfold.iter().map(|(c, _)| c).collect::<String>()
+    );
+    cfold.session(1).push_front('!');
+    assert_eq!("!hell world", format!("{}", cfold));
+}
+
+#[test]
+fn apply_all_keeps_remainder() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let first = Op::insert(t(1, 1), Some(t(0, 0)), 'a');
+    let failing = Op::insert(t(3, 1), Some(t(2, 1)), 'c');
+    let rest = Op::insert(t(2, 1), Some(t(1, 1)), 'b');
+    let err = cfold
+        .apply_all(vec![first, failing.clone(), rest.clone()])
+        .unwrap_err();
+    assert_eq!(1, err.applied);
+    assert_eq!(ChronofoldError::FutureTimestamp(failing.clone()), err.error);
+    assert_eq!(vec![rest.clone()], err.remaining);
+    assert_eq!(
+        "future timestamp <3, 1> after applying 1 ops (1 remaining)",
+        format!("{}", err)
+    );
+
+    // Retrying in a different order succeeds:
+    let mut unapplied = err.into_unapplied();
+    unapplied.reverse();
+    assert_eq!(Ok(()), cfold.apply_all(unapplied));
+    assert_eq!("abc", format!("{}", cfold));
+}
+
+#[test]
+fn apply_all_drops_rejected_op() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.set_validator(|op: &Op<u8, &char>| match op.payload {
+        OpPayload::Insert(_, c) if !c.is_ascii() => Err(format!("{:?} is not ascii", c)),
+        _ => Ok(()),
+    });
+    let rejected = Op::insert(t(1, 1), Some(t(0, 0)), 'ä');
+    let rest = Op::insert(t(2, 1), Some(t(1, 1)), 'b');
+    let err = cfold
+        .apply_all(vec![rejected.clone(), rest.clone()])
+        .unwrap_err();
+    assert_eq!(
+        ChronofoldError::Rejected(rejected.id, "'ä' is not ascii".to_owned()),
+        err.error
+    );
+    assert_eq!(vec![rest], err.into_unapplied());
+}
+
+#[test]
+fn apply_with_resolver_fetches_history() {
+    let mut source = Chronofold::<u8, char>::default();
+    source.session(1).extend("abc".chars());
+    source.session(2).extend("d".chars());
+    let mut ops: Vec<Op<u8, char>> = source.iter_ops(..).map(Op::cloned).collect();
+    let last = ops.pop().unwrap();
+
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut requests = Vec::new();
+    cfold
+        .apply_with_resolver(last, |missing| {
+            requests.push(*missing);
+            Some(ops.clone())
+        })
+        .unwrap();
+    assert_eq!(vec![t(3, 1)], requests);
+    assert_eq!("abcd", format!("{}", cfold));
+}
+
+#[test]
+fn apply_with_resolver_gives_up() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let op = Op::insert(t(1, 1), Some(t(1, 2)), '!');
+    let mut calls = 0;
+    let err = cfold
+        .apply_with_resolver(op.clone(), |_| {
+            calls += 1;
+            Some(vec![])
+        })
+        .unwrap_err();
+    assert_eq!(1, calls);
+    assert_eq!(ChronofoldError::UnknownReference(op.clone(), None), err);
+
+    let err = cfold.apply_with_resolver(op.clone(), |_| None).unwrap_err();
+    assert_eq!(ChronofoldError::UnknownReference(op, None), err);
+}
+
+#[test]
+fn op_buffer() {
+    let mut source = Chronofold::<u8, char>::default();
+    source.session(1).extend("abc".chars());
+    let ops: Vec<Op<u8, char>> = source.iter_ops(LocalIndex(1)..).map(Op::cloned).collect();
+
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut buffer = OpBuffer::new();
+    assert_eq!(Ok(vec![]), buffer.apply(&mut cfold, ops[2].clone()));
+    assert_eq!(Ok(vec![]), buffer.apply(&mut cfold, ops[1].clone()));
+    assert_eq!(2, buffer.len());
+    assert_eq!("", format!("{}", cfold));
+
+    // The third op waits for the second, which waits for the first:
+    assert_eq!(
+        vec![&t(1, 1), &t(2, 1)],
+        buffer.waiting_for().into_iter().collect::<Vec<_>>()
+    );
+    assert_eq!(ops[2], buffer.oldest().unwrap().op);
+    let later = Instant::now() + Duration::from_secs(5);
+    assert!(buffer.oldest().unwrap().waited(later) >= Duration::from_secs(5));
+
+    assert_eq!(
+        Ok(vec![t(2, 1), t(3, 1)]),
+        buffer.apply(&mut cfold, ops[0].clone())
+    );
+    assert!(buffer.is_empty());
+    assert!(buffer.take_rejected().is_empty());
+    assert_eq!("abc", format!("{}", cfold));
+    assert_eq!(
+        Err(ChronofoldError::AlreadyApplied(ops[0].clone())),
+        buffer.apply(&mut cfold, ops[0].clone())
+    );
+}
+
+#[test]
+fn apply_batch() {
+    let mut source = Chronofold::<u8, char>::default();
+    source.session(1).extend("hello".chars());
+    source.session(2).extend(" world".chars());
+    source.session(1).remove(LocalIndex(1));
+    let mut ops: Vec<Op<u8, char>> = source.iter_ops(..).map(Op::cloned).collect();
+    ops.reverse();
+    let blocked = Op::insert(t(20, 3), Some(t(19, 3)), '!');
+    ops.push(blocked.clone());
+
+    let mut cfold = Chronofold::<u8, char>::default();
+    let summary = cfold.apply_batch(ops);
+    assert_eq!(12, summary.applied);
+    assert_eq!(vec![t(0, 0)], summary.duplicates);
+    assert_eq!(
+        vec![ChronofoldError::FutureTimestamp(blocked)],
+        summary.deferred
+    );
+    assert_eq!(cfold, source);
+    assert_eq!("ello world", format!("{}", cfold));
+
+    // Rejected ops don't stop the batch.
+    let reused = Op::insert(t(1, 1), None, 'x');
+    let appended = Op::insert(t(13, 2), Some(t(11, 2)), '?');
+    let summary = cfold.apply_batch(vec![reused.clone(), appended]);
+    assert_eq!(1, summary.applied);
+    assert_eq!(
+        vec![ChronofoldError::ExistingTimestamp(reused)],
+        summary.rejected
+    );
+    assert_eq!("ello world?", format!("{}", cfold));
+    assert!(cfold.version().contains(&t(13, 2)));
+}
+
+#[test]
+fn apply_batch_retries_deferred_ops() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).push_back('a');
+    // Outside of strict mode, ops may reference changes sorted after them.
+    let backwards = Op::insert(t(2, 1), Some(t(2, 2)), 'c');
+    let referenced = Op::insert(t(2, 2), Some(t(1, 1)), 'b');
+    let summary = cfold.apply_batch(vec![backwards, referenced]);
+    assert_eq!(2, summary.applied);
+    assert!(summary.deferred.is_empty());
+    assert_eq!("abc", format!("{}", cfold));
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "interop")]
+use std::convert::Infallible;
+
+use chronofold::{Chronofold, LocalIndex, Op};
+
+fn encode(c: &char) -> Result<Vec<u8>, Infallible> {
+    Ok(c.to_string().into_bytes())
+}
+
+#[test]
+fn converged_replicas_export_identical_bytes() {
+    let mut alice = Chronofold::<u8, char>::default();
+    let mut bob = Chronofold::<u8, char>::default();
+    let ops_alice: Vec<Op<u8, char>> = {
+        let mut session = alice.session(1);
+        session.extend("ab".chars());
+        session.remove(LocalIndex(1));
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    let ops_bob: Vec<Op<u8, char>> = {
+        let mut session = bob.session(2);
+        session.extend("xy".chars());
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    alice.apply_all(ops_bob).unwrap();
+    bob.apply_all(ops_alice).unwrap();
+    assert_eq!(alice.to_string(), bob.to_string());
+    assert_ne!(
+        alice.iter_ops::<&char>(..).collect::<Vec<_>>(),
+        bob.iter_ops::<&char>(..).collect::<Vec<_>>()
+    );
+
+    let export = alice.export_canonical(encode).unwrap();
+    assert_eq!(export, bob.export_canonical(encode).unwrap());
+
+    // Canonical ops are in causal order.
+    let mut carol = Chronofold::<u8, char>::default();
+    carol
+        .apply_all(alice.canonical_ops().into_iter().skip(1).map(Op::cloned))
+        .unwrap();
+    assert_eq!(export, carol.export_canonical(encode).unwrap());
+}
+
+#[test]
+fn canonical_format() {
+    let mut cfold = Chronofold::<u8, char>::new(0);
+    cfold.session(1).push_back('a');
+    let u64 = |n: u64| n.to_le_bytes().to_vec();
+    let expected = [
+        vec![0],
+        u64(0),
+        u64(0),
+        vec![1],
+        u64(1),
+        u64(1),
+        vec![1],
+        u64(0),
+        u64(0),
+        u64(1),
+        vec![b'a'],
+    ]
+    .concat();
+    assert_eq!(expected, cfold.export_canonical(encode).unwrap());
+}
+
+#[test]
+fn canonical_string_authors() {
+    let cfold = Chronofold::<String, char>::new("alice".to_owned());
+    let expected = [
+        vec![0],
+        0u64.to_le_bytes().to_vec(),
+        5u64.to_le_bytes().to_vec(),
+        b"alice".to_vec(),
+    ]
+    .concat();
+    assert_eq!(expected, cfold.export_canonical(encode).unwrap());
+}
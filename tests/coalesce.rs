@@ -0,0 +1,233 @@
+use chronofold::{
+    coalesce_ops, compress_ops, AuthorIndex, CharBatch, Chronofold, CoalescedOp, LocalIndex, Op,
+    Timestamp,
+};
+
+#[test]
+fn typing_is_coalesced_into_runs() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut session = cfold.session(1);
+    let mut idx = LocalIndex(0);
+    for c in "hello".chars() {
+        idx = session.insert_after(idx, c);
+    }
+    let ops: Vec<Op<u8, char>> = session.iter_ops().map(Op::cloned).collect();
+    let coalesced = coalesce_ops(ops.clone());
+    assert_eq!(1, coalesced.len());
+    assert_eq!(5, coalesced[0].len());
+
+    let expanded: Vec<_> = coalesced
+        .into_iter()
+        .flat_map(CoalescedOp::into_ops)
+        .collect();
+    assert_eq!(ops, expanded);
+}
+
+#[test]
+fn corrections_are_cancelled() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("hi ".chars());
+    let mut replica = cfold.clone();
+
+    let mut session = cfold.session(2);
+    session.set_coalescing(true);
+    let mut last = session.extend("wrold".chars()).unwrap();
+    for _ in 0..4 {
+        session.remove(last);
+        last = LocalIndex(last.0 - 1);
+    }
+    session.extend("orld".chars());
+    let ops = session.coalesced_ops();
+    assert_eq!(1, ops.len());
+    assert_eq!(5, ops[0].len());
+    assert_eq!("hi world", format!("{}", cfold));
+
+    replica
+        .apply_all(ops.into_iter().flat_map(CoalescedOp::into_ops))
+        .unwrap();
+    assert_eq!(cfold, replica);
+}
+
+#[test]
+fn earlier_edits_are_not_cancelled() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let idx = cfold.session(1).push_back('a');
+    let mut session = cfold.session(1);
+    session.set_coalescing(true);
+    session.remove(idx);
+    let ops: Vec<Op<u8, char>> = session.iter_ops().map(Op::cloned).collect();
+    assert_eq!(1, ops.len());
+    assert!(cfold.is_empty());
+}
+
+#[test]
+fn replace_last_insert() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ab".chars());
+    let mut replica = cfold.clone();
+
+    let mut session = cfold.session(1);
+    session.set_coalescing(true);
+    let idx = session.push_back('x');
+    session.replace(idx, 'c');
+    assert_eq!(1, session.coalesced_ops().len());
+    for op in session.iter_ops::<&char>().map(Op::cloned) {
+        replica.apply(op).unwrap();
+    }
+    assert_eq!("abc", format!("{}", cfold));
+    assert_eq!(cfold, replica);
+}
+
+#[test]
+fn cancelled_insert_is_observed() {
+    use std::sync::{Arc, Mutex};
+
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ab".chars());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    cfold.set_version_observer(move |version| log.lock().unwrap().push(version.to_string()));
+
+    // The replacement reuses the author index of the cancelled insert:
+    let mut session = cfold.session(1);
+    session.set_coalescing(true);
+    let idx = session.push_back('x');
+    session.replace(idx, 'c');
+    assert_eq!(vec!["0:0,1:3", "0:0,1:2", "0:0,1:3"], *seen.lock().unwrap());
+}
+
+#[test]
+fn cancel_first_element() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut session = cfold.session(1);
+    session.set_coalescing(true);
+    let idx = session.push_back('a');
+    session.remove(idx);
+    assert_eq!(0, session.coalesced_ops().len());
+    assert_eq!(Chronofold::<u8, char>::default(), cfold);
+}
+
+#[test]
+fn char_batch_roundtrip() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Grüße".chars());
+    cfold.session(2).remove(LocalIndex(3));
+    cfold.session(2).insert_after(LocalIndex(2), 'ö');
+    let ops: Vec<Op<u8, char>> = cfold.iter_ops(..).map(Op::cloned).collect();
+
+    let batch = CharBatch::from_ops(ops.clone());
+    assert_eq!("Grüßeö", batch.text);
+    assert_eq!(4, batch.entries.len());
+    assert_eq!(Some(ops), batch.into_ops());
+}
+
+#[test]
+fn char_batch_invalid() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".chars());
+    let mut batch = CharBatch::from_ops(cfold.iter_ops(..).map(Op::cloned));
+    batch.text.pop();
+    assert_eq!(None, batch.clone().into_ops());
+    batch.text.push_str("cd");
+    assert_eq!(None, batch.into_ops());
+}
+
+#[test]
+fn flushed_edits_are_not_cancelled() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut session = cfold.session(1);
+    session.set_coalescing(true);
+    let idx = session.push_back('a');
+    assert_eq!(1, session.flush().len());
+    session.remove(idx);
+    assert_eq!(1, session.flush().len());
+    assert!(cfold.is_empty());
+}
+
+#[test]
+fn compress_ops_drops_corrections() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("ab".chars());
+    let mut bob = alice.clone();
+
+    let mut draft = alice.clone();
+    let mut session = draft.session(1);
+    let x = session.insert_after(LocalIndex(2), 'x');
+    session.insert_after(x, 'y');
+    let z = session.insert_after(x, 'z');
+    session.remove(x);
+    session.remove(z);
+    session.remove(LocalIndex(1));
+    assert_eq!("by", format!("{}", draft));
+    let ops: Vec<Op<u8, char>> = draft
+        .iter_newer_ops(alice.version())
+        .map(Op::cloned)
+        .collect();
+    let ops = compress_ops(ops);
+    // `y` is attached to `b` instead, and deleting `a` isn't a correction.
+    assert_eq!(2, ops.len());
+    assert_eq!(
+        Op::insert(
+            Timestamp::new(AuthorIndex(3), 1),
+            Some(Timestamp::new(AuthorIndex(2), 1)),
+            'y'
+        ),
+        ops[0]
+    );
+
+    alice.apply_all(ops.clone()).unwrap();
+    assert_eq!("by", format!("{}", alice));
+    bob.apply_all(ops).unwrap();
+    assert_eq!(alice, bob);
+}
+
+#[test]
+fn compress_ops_keeps_order() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut draft = cfold.clone();
+    let mut session = draft.session(1);
+    let x = session.insert_after(LocalIndex(0), 'x');
+    session.insert_after(LocalIndex(0), 'a');
+    session.insert_after(x, 'b');
+    session.remove(x);
+    assert_eq!("ab", format!("{}", draft));
+    let ops: Vec<Op<u8, char>> = draft
+        .iter_newer_ops(cfold.version())
+        .map(Op::cloned)
+        .collect();
+    // Attaching `b` to the root would put it before `a`.
+    let compressed = compress_ops(ops.clone());
+    assert_eq!(ops, compressed);
+    cfold.apply_all(compressed).unwrap();
+    assert_eq!("ab", format!("{}", cfold));
+}
+
+#[test]
+fn compressed_runs_are_merged() {
+    let mut alice = Chronofold::<u8, char>::default();
+    let mut bob = alice.clone();
+
+    let mut draft = alice.clone();
+    let mut session = draft.session(1);
+    session.extend("helo".chars());
+    session.remove(LocalIndex(4));
+    session.extend("lo".chars());
+    let ops: Vec<Op<u8, char>> = draft
+        .iter_newer_ops(alice.version())
+        .map(Op::cloned)
+        .collect();
+    assert_eq!(3, coalesce_ops(ops.clone()).len());
+    let ops = compress_ops(ops);
+    assert_eq!(1, coalesce_ops(ops.clone()).len());
+
+    alice.apply_all(ops.clone()).unwrap();
+    bob.apply_all(ops).unwrap();
+    assert_eq!("hello", format!("{}", bob));
+
+    // Later edits still line up.
+    let version = bob.version().clone();
+    alice.session(1).push_back('!');
+    bob.apply_all(alice.iter_newer_ops(&version).map(Op::cloned))
+        .unwrap();
+    assert_eq!(alice, bob);
+}
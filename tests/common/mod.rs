@@ -0,0 +1,14 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use chronofold::{AuthorIndex, Chronofold, Op, Timestamp, Version};
+
+/// Returns the ops `cfold` has beyond `version`.
+pub fn ops_since(cfold: &Chronofold<u8, char>, version: &Version<u8>) -> Vec<Op<u8, char>> {
+    cfold.iter_newer_ops(version).map(Op::cloned).collect()
+}
+
+/// Returns the timestamp of `author`'s change at `log_index`.
+pub fn t(log_index: usize, author: u8) -> Timestamp<u8> {
+    Timestamp::new(AuthorIndex(log_index), author)
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use chronofold::{
+    Change, Chronofold, ChronofoldError, KeepFor, KeepForever, LocalIndex, Op, Timestamp, Version,
+};
+
+mod common;
+use common::ops_since;
+
+fn sync(a: &mut Chronofold<u8, char>, b: &mut Chronofold<u8, char>) {
+    let ops_a = ops_since(a, b.version());
+    let ops_b = ops_since(b, a.version());
+    a.apply_all(ops_b).unwrap();
+    b.apply_all(ops_a).unwrap();
+}
+
+#[test]
+fn compact_removes_tombstones() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("hello world".chars());
+    cfold
+        .session(1)
+        .splice(LocalIndex(1)..LocalIndex(7), "".chars());
+    assert_eq!("world", format!("{}", cfold));
+    let timestamps: Vec<_> = cfold
+        .iter()
+        .map(|(_, idx)| cfold.timestamp(idx).unwrap())
+        .collect();
+
+    let stable = cfold.version().clone();
+    assert_eq!(12, cfold.compact(&stable).removed());
+    assert_eq!(6, cfold.iter_changes().count());
+    assert_eq!("world", format!("{}", cfold));
+    for (t, (_, idx)) in timestamps.iter().zip(cfold.iter()) {
+        assert_eq!(Some(idx), cfold.log_index(t));
+        assert_eq!(Some(*t), cfold.timestamp(idx));
+    }
+
+    cfold.session(1).push_front('W');
+    cfold.session(1).remove(LocalIndex(1));
+    assert_eq!("World", format!("{}", cfold));
+}
+
+#[test]
+fn compact_remaps_indices() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abcd".chars());
+    cfold.session(1).remove(LocalIndex(2));
+    cfold.session(1).remove(LocalIndex(3));
+    let mut cursors = vec![LocalIndex(1), LocalIndex(3), LocalIndex(4), LocalIndex(99)];
+
+    let stable = cfold.version().clone();
+    let remap = cfold.compact(&stable);
+    assert_eq!(4, remap.removed());
+    assert_eq!(Some(LocalIndex(0)), remap.get(LocalIndex(0)));
+    assert_eq!(None, remap.get(LocalIndex(2)));
+    assert_eq!(Some(LocalIndex(2)), remap.get(LocalIndex(4)));
+    assert_eq!(None, remap.get(LocalIndex(99)));
+
+    remap.translate_cursors(&mut cursors);
+    assert_eq!(
+        vec![LocalIndex(1), LocalIndex(1), LocalIndex(2), LocalIndex(99)],
+        cursors
+    );
+    cfold.session(1).insert_after(cursors[1], 'x');
+    assert_eq!("axd", format!("{}", cfold));
+
+    let remap = cfold.compact(&stable);
+    assert_eq!(0, remap.removed());
+    assert_eq!(Some(LocalIndex(3)), remap.get(LocalIndex(3)));
+}
+
+#[test]
+fn compact_keeps_unstable_tombstones() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".chars());
+    let stable = cfold.version().clone();
+    cfold.session(1).remove(LocalIndex(2));
+    assert_eq!(0, cfold.compact(&stable).removed());
+    assert_eq!("ac", format!("{}", cfold));
+}
+
+#[test]
+fn compact_reattaches_children() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".chars());
+    cfold.session(1).remove(LocalIndex(2));
+    cfold.session(1).insert_after(LocalIndex(2), 'x');
+    let stable = cfold.version().clone();
+    let mut replica = cfold.clone();
+    assert_eq!(2, cfold.compact(&stable).removed());
+    assert_eq!("axc", format!("{}", cfold));
+
+    // New insertions are placed the same way with and without compaction.
+    for cfold in [&mut cfold, &mut replica] {
+        cfold.session(1).insert_after(LocalIndex(1), 'y');
+    }
+    assert_eq!("ayxc", format!("{}", cfold));
+    assert_eq!(format!("{}", replica), format!("{}", cfold));
+}
+
+#[test]
+fn compact_keeps_tombstones_if_siblings_would_be_reordered() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("ab".chars());
+    let mut bob = alice.clone();
+    bob.session(2).insert_after(LocalIndex(1), 's');
+    sync(&mut alice, &mut bob);
+    alice.session(1).insert_after(LocalIndex(2), 'c');
+    alice.session(1).remove(LocalIndex(2));
+    assert_eq!("asc", format!("{}", alice));
+
+    // 'c' would be placed before 's' if it was attached to 'a'.
+    let stable = alice.version().clone();
+    assert_eq!(0, alice.compact(&stable).removed());
+}
+
+#[test]
+fn compacted_replicas_converge() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("Hello world!".chars());
+    let mut bob = alice.clone();
+    bob.session(2)
+        .splice(LocalIndex(7)..LocalIndex(12), "Bob".chars());
+    alice
+        .session(1)
+        .splice(LocalIndex(2)..LocalIndex(6), "i".chars());
+    sync(&mut alice, &mut bob);
+    assert_eq!("Hi Bob!", format!("{}", alice));
+
+    // Only Alice compacts.
+    let stable = alice.version().clone();
+    assert!(alice.compact(&stable).removed() > 0);
+
+    alice.session(1).extend(" How are you?".chars());
+    let idx = bob.iter().nth(2).unwrap().1;
+    bob.session(2).splice(idx..=idx, "".chars());
+    sync(&mut alice, &mut bob);
+    assert_eq!("HiBob! How are you?", format!("{}", alice));
+    assert_eq!(format!("{}", alice), format!("{}", bob));
+    assert_eq!(alice.version(), bob.version());
+
+    // Now Bob compacts, too.
+    let stable = bob.version().clone();
+    bob.compact(&stable);
+    alice.compact(&stable);
+    alice.session(1).push_front('>');
+    bob.session(2).push_back('<');
+    sync(&mut alice, &mut bob);
+    assert_eq!(">HiBob! How are you?<", format!("{}", alice));
+    assert_eq!(format!("{}", alice), format!("{}", bob));
+}
+
+#[test]
+fn retention_policies() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".chars());
+    cfold.session(1).remove(LocalIndex(1));
+    cfold.session(1).remove(LocalIndex(2));
+    let stable = cfold.version().clone();
+
+    let mut keep = cfold.clone();
+    keep.set_retention_policy(KeepForever);
+    assert_eq!(0, keep.compact(&stable).removed());
+
+    // Only the first deletion is old enough, compared to the newest op of the
+    // stable version rather than the local clock.
+    let start = SystemTime::UNIX_EPOCH;
+    let times: HashMap<Timestamp<u8>, SystemTime> = cfold
+        .iter_ops::<&char>(..)
+        .skip(4)
+        .zip(vec![start, start + Duration::from_secs(3600)])
+        .map(|(op, time)| (op.id, time))
+        .collect();
+    cfold.set_retention_policy(KeepFor::new(Duration::from_secs(60), move |t| {
+        times.get(t).copied()
+    }));
+    assert_eq!(2, cfold.compact(&stable).removed());
+    assert_eq!("c", format!("{}", cfold));
+    assert_eq!(0, cfold.compact(&stable).removed());
+}
+
+#[test]
+fn drop_tombstone_payloads() {
+    let mut cfold = Chronofold::<u8, String>::default();
+    cfold
+        .session(1)
+        .extend(vec!["keep".to_owned(), "gone".to_owned()]);
+    let before_delete = cfold.version().clone();
+    cfold.session(1).remove(LocalIndex(2));
+    let stable = cfold.version().clone();
+
+    assert_eq!(0, cfold.drop_tombstone_payloads(&before_delete));
+    let mut keep = cfold.clone();
+    keep.set_retention_policy(KeepForever);
+    assert_eq!(0, keep.drop_tombstone_payloads(&stable));
+
+    assert_eq!(1, cfold.drop_tombstone_payloads(&stable));
+    assert_eq!(4, cfold.iter_changes().count());
+    assert_eq!(
+        Some(&Change::Insert(String::new())),
+        cfold.get(LocalIndex(2))
+    );
+    assert_eq!(vec!["keep"], cfold.iter_elements().collect::<Vec<_>>());
+
+    // The dropped element can still be referenced.
+    let mut peer = keep;
+    peer.session(2)
+        .insert_after(LocalIndex(2), "new".to_owned());
+    cfold
+        .apply_all(peer.iter_newer_ops(&stable).map(Op::cloned))
+        .unwrap();
+    assert_eq!(
+        vec!["keep", "new"],
+        cfold.iter_elements().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn compact_keeps_element_ids() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("abcd".chars());
+    alice.session(1).remove(LocalIndex(2));
+    alice.session(1).insert_after(LocalIndex(2), 'x');
+    let mut bob = alice.clone();
+    let ids: Vec<_> = alice
+        .iter()
+        .map(|(_, idx)| alice.element_id(idx).unwrap())
+        .collect();
+    let removed = alice.element_id(LocalIndex(2)).unwrap();
+    assert_eq!(Some(LocalIndex(6)), alice.resolve(&ids[1]));
+
+    let stable = alice.version().clone();
+    assert_eq!(2, alice.compact(&stable).removed());
+    assert_eq!(None, alice.resolve(&removed));
+    for (position, id) in ids.iter().enumerate() {
+        assert_eq!(Some(position), alice.position_of(id));
+    }
+
+    // Bob didn't compact, but his ops still resolve on Alice's side.
+    let idx = bob.resolve(&ids[1]).unwrap();
+    bob.session(2).remove(idx);
+    bob.session(2).insert_after(LocalIndex(3), 'y');
+    sync(&mut alice, &mut bob);
+    assert_eq!("acyd", format!("{}", alice));
+    assert_eq!(format!("{}", alice), format!("{}", bob));
+}
+
+#[test]
+fn compacted_ops_are_already_applied() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".chars());
+    let ops = ops_since(&cfold, &Version::default());
+    cfold.session(1).remove(LocalIndex(2));
+
+    let stable = cfold.version().clone();
+    assert_eq!(2, cfold.compact(&stable).removed());
+    for op in ops {
+        assert!(matches!(
+            cfold.apply(op),
+            Err(ChronofoldError::AlreadyApplied(_))
+        ));
+    }
+    assert_eq!("ac", format!("{}", cfold));
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeSet;
+
+use chronofold::{Chronofold, LocalIndex};
+
+mod common;
+use common::ops_since;
+
+#[test]
+fn concurrent_runs_are_recorded() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("ab".chars());
+    alice.set_conflict_tracking(true);
+    let mut bob = alice.clone();
+    let version = alice.version().clone();
+
+    let mut session = alice.session(1);
+    let idx = session.insert_after(LocalIndex(1), '1');
+    let idx = session.insert_after(idx, '2');
+    session.insert_after(idx, '3');
+    let idx = bob.session(2).insert_after(LocalIndex(1), 'x');
+    bob.session(2).insert_after(idx, 'y');
+    alice.apply_all(ops_since(&bob, &version)).unwrap();
+    assert_eq!("axy123b", format!("{}", alice));
+
+    assert_eq!(1, alice.conflicts().len());
+    let region = &alice.conflicts()[0];
+    assert_eq!(BTreeSet::from([1, 2]), region.authors());
+    assert_eq!(2, region.runs().count());
+    assert_eq!(Some(1..6), alice.conflict_range(region));
+
+    // Bob continues his run, which grows the region.
+    let version = bob.version().clone();
+    bob.session(2).insert_after(LocalIndex(4), 'z');
+    alice.apply_all(ops_since(&bob, &version)).unwrap();
+    assert_eq!("axyz123b", format!("{}", alice));
+    assert_eq!(1, alice.conflicts().len());
+    assert_eq!(Some(1..7), alice.conflict_range(&alice.conflicts()[0]));
+
+    assert_eq!(1, alice.take_conflicts().len());
+    assert!(alice.conflicts().is_empty());
+}
+
+#[test]
+fn compaction_prunes_removed_runs() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("ab".chars());
+    alice.set_conflict_tracking(true);
+    let mut bob = alice.clone();
+    let version = alice.version().clone();
+
+    alice.session(1).insert_after(LocalIndex(1), '1');
+    let idx = bob.session(2).insert_after(LocalIndex(1), 'x');
+    bob.session(2).insert_after(idx, 'y');
+    alice.apply_all(ops_since(&bob, &version)).unwrap();
+    assert_eq!("axy1b", format!("{}", alice));
+    assert_eq!(1, alice.conflicts().len());
+
+    // Compaction keeps the region as long as its runs are there.
+    let stable = alice.version().clone();
+    alice.compact(&stable);
+    assert_eq!(1, alice.conflicts().len());
+    assert_eq!(Some(1..4), alice.conflict_range(&alice.conflicts()[0]));
+
+    alice.session(1).remove(LocalIndex(4));
+    alice.session(1).remove(LocalIndex(5));
+    assert_eq!("a1b", format!("{}", alice));
+    let stable = alice.version().clone();
+    alice.compact(&stable);
+    assert!(alice.conflicts().is_empty());
+}
+
+#[test]
+fn sequential_insertions_are_no_conflicts() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("ab".chars());
+    alice.session(1).insert_after(LocalIndex(1), '1');
+    let mut bob = alice.clone();
+    let version = alice.version().clone();
+    alice.set_conflict_tracking(true);
+
+    // Bob has seen Alice's insertion.
+    bob.session(2).insert_after(LocalIndex(1), 'x');
+    alice.apply_all(ops_since(&bob, &version)).unwrap();
+    assert_eq!("ax1b", format!("{}", alice));
+    assert!(alice.conflicts().is_empty());
+
+    // Tracking is disabled by default.
+    let mut carol = bob.clone();
+    let version = bob.version().clone();
+    carol.session(3).insert_after(LocalIndex(1), 'c');
+    bob.session(2).insert_after(LocalIndex(1), 'y');
+    bob.apply_all(ops_since(&carol, &version)).unwrap();
+    assert!(bob.conflicts().is_empty());
+}
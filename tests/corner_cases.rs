@@ -88,6 +88,22 @@ fn concurrent_insertion_deletion() {
     );
 }
 
+#[test]
+fn concurrent_splice_diff() {
+    // Alice replaces the text with a nearly identical one, while Bob edits a
+    // word that stays the same:
+    assert_concurrent_eq(
+        "Hi worlds!",
+        "Hello world!",
+        |s| {
+            s.splice_diff(.., "Hi world!".chars());
+        },
+        |s| {
+            s.insert_after(LocalIndex(11), 's');
+        },
+    );
+}
+
 #[test]
 fn insert_referencing_deleted_element() {
     let mut cfold = Chronofold::<u8, char>::default();
@@ -98,10 +114,90 @@ fn insert_referencing_deleted_element() {
     assert_eq!("?", format!("{}", cfold));
 }
 
+#[test]
+fn typing_after_removing_last_element() {
+    // Typing is appended directly, unless the last element was removed:
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut session = cfold.session(1);
+    let mut idx = LocalIndex(0);
+    for c in "abc".chars() {
+        idx = session.insert_after(idx, c);
+    }
+    session.remove(idx);
+    let idx = session.insert_after(idx, 'd');
+    session.insert_after(idx, 'e');
+    assert_eq!("abde", format!("{}", cfold));
+
+    let mut expected = Chronofold::<u8, char>::default();
+    expected.session(1).extend("abc".chars());
+    expected.session(1).remove(LocalIndex(3));
+    expected.session(1).insert_after(LocalIndex(3), 'd');
+    expected.session(1).insert_after(LocalIndex(5), 'e');
+    assert_eq!(expected, cfold);
+}
+
+#[test]
+fn insert_after_concurrently_deleted_element() {
+    // Both delete the same character, then both insert after it:
+    let mut cfold_left = Chronofold::<u8, char>::default();
+    cfold_left.session(1).extend("abc".chars());
+    let mut cfold_right = cfold_left.clone();
+
+    let ops_left: Vec<_> = {
+        let mut session = cfold_left.session(1);
+        session.remove(LocalIndex(2));
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    let ops_right: Vec<_> = {
+        let mut session = cfold_right.session(2);
+        session.remove(LocalIndex(2));
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    cfold_right.apply_all(ops_left).unwrap();
+    cfold_left.apply_all(ops_right).unwrap();
+
+    let ops_left: Vec<_> = {
+        let mut session = cfold_left.session(1);
+        session.insert_after(LocalIndex(2), 'x');
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    let ops_right: Vec<_> = {
+        let mut session = cfold_right.session(2);
+        session.insert_after(LocalIndex(2), 'y');
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    cfold_right.apply_all(ops_left).unwrap();
+    cfold_left.apply_all(ops_right).unwrap();
+
+    assert_eq!(format!("{}", cfold_left), format!("{}", cfold_right));
+}
+
+#[test]
+fn concurrent_removal_and_replacement() {
+    // One removes a character, the other replaces it. The replacement
+    // survives regardless of which delete sorts first:
+    assert_concurrent_eq(
+        "ar",
+        "ab",
+        |s| s.remove(LocalIndex(2)),
+        |s| {
+            s.replace(LocalIndex(2), 'r');
+        },
+    );
+    assert_concurrent_eq(
+        "ar",
+        "ab",
+        |s| {
+            s.replace(LocalIndex(2), 'r');
+        },
+        |s| s.remove(LocalIndex(2)),
+    );
+}
+
 fn assert_concurrent_eq<F, G>(expected: &str, initial: &str, mutate_left: F, mutate_right: G)
 where
-    F: FnOnce(&mut Session<u8, char>) -> (),
-    G: FnOnce(&mut Session<u8, char>) -> (),
+    F: FnOnce(&mut Session<u8, char>),
+    G: FnOnce(&mut Session<u8, char>),
 {
     let mut cfold_left = Chronofold::<u8, char>::default();
     cfold_left.session(1).extend(initial.chars());
@@ -137,4 +233,16 @@ where
         "Right ops:\n{:#?}",
         cfold_right.iter_ops(..).collect::<Vec<Op<_, &char>>>()
     );
+
+    // Incrementally updated counts match counts from scratch.
+    for mut cfold in [cfold_left, cfold_right] {
+        let (len, contributions) = (cfold.len(), cfold.contributions().clone());
+        assert_eq!(expected.chars().count(), len);
+        cfold.rebuild_indices();
+        assert_eq!(len, cfold.len());
+        assert_eq!(&contributions, cfold.contributions());
+        let mut reversed: Vec<_> = cfold.iter_rev().collect();
+        reversed.reverse();
+        assert_eq!(cfold.iter().collect::<Vec<_>>(), reversed);
+    }
 }
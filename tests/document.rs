@@ -0,0 +1,49 @@
+#![cfg(feature = "derive")]
+use chronofold::{ChronoDoc, Chronofold, DocOp, Version};
+
+use std::collections::BTreeMap;
+
+#[derive(ChronoDoc, Default)]
+struct Article {
+    title: Chronofold<u8, char>,
+    body_text: Chronofold<u8, char>,
+}
+
+#[test]
+fn fields_are_synced() {
+    let mut alice = Article::default();
+    alice.title.session(1).extend("Hi".chars());
+    alice.body_text.session(1).extend("there".chars());
+    let mut bob = Article::default();
+
+    let versions = bob.versions();
+    assert_eq!(2, versions.len());
+    let ops: Vec<DocOp<ArticleField, u8, char>> = alice
+        .newer_doc_ops(&versions)
+        .into_iter()
+        .map(DocOp::cloned)
+        .collect();
+    assert_eq!(7, ops.len());
+    let summaries = bob.apply_doc_ops(ops);
+    assert_eq!(2, summaries[&ArticleField::Title].applied);
+    assert_eq!(5, summaries[&ArticleField::BodyText].applied);
+    assert_eq!("Hi", format!("{}", bob.title));
+    assert_eq!("there", format!("{}", bob.body_text));
+
+    bob.field_mut(ArticleField::Title).session(2).push_back('!');
+    let ops: Vec<DocOp<ArticleField, u8, char>> = bob
+        .newer_doc_ops(&alice.versions())
+        .into_iter()
+        .map(DocOp::cloned)
+        .collect();
+    assert_eq!(1, ops.len());
+    assert_eq!(ArticleField::Title, ops[0].doc_id);
+    alice.apply_doc_ops(ops);
+    assert_eq!("Hi!", format!("{}", alice.title));
+    assert!(alice.newer_doc_ops::<&char>(&bob.versions()).is_empty());
+    assert!(
+        bob.newer_doc_ops::<&char>(&BTreeMap::<_, Version<u8>>::new())
+            .len()
+            > 7
+    );
+}
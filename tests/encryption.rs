@@ -0,0 +1,170 @@
+#![cfg(feature = "encryption")]
+use chronofold::{
+    rotate_op_log_key, rotate_snapshot_key, Aead, Chronofold, Compression, SnapshotError, Version,
+};
+
+use std::collections::BTreeMap;
+
+/// A toy cipher for testing, which obviously must not be used in practice.
+struct XorCipher {
+    key_id: u32,
+    key: u8,
+}
+
+impl XorCipher {
+    fn tag(&self, plaintext: &[u8], aad: &[u8]) -> u8 {
+        plaintext
+            .iter()
+            .chain(aad)
+            .fold(self.key, |tag, byte| tag.rotate_left(3) ^ byte)
+    }
+}
+
+impl Aead for XorCipher {
+    fn key_id(&self) -> u32 {
+        self.key_id
+    }
+
+    fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+        let mut ciphertext: Vec<u8> = plaintext.iter().map(|byte| byte ^ self.key).collect();
+        ciphertext.push(self.tag(plaintext, aad));
+        ciphertext
+    }
+
+    fn open(&self, ciphertext: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+        let (tag, ciphertext) = ciphertext.split_last()?;
+        let plaintext: Vec<u8> = ciphertext.iter().map(|byte| byte ^ self.key).collect();
+        Some(plaintext).filter(|plaintext| self.tag(plaintext, aad) == *tag)
+    }
+}
+
+const CIPHER: XorCipher = XorCipher {
+    key_id: 7,
+    key: 0x5a,
+};
+
+#[test]
+fn encrypted_snapshot() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("secret".chars());
+    let mut bytes = Vec::new();
+    cfold
+        .write_encrypted_snapshot(&mut bytes, Compression::None, &CIPHER)
+        .unwrap();
+    assert!(!bytes.windows(6).any(|w| w == b"secret"));
+    assert_eq!(
+        cfold,
+        Chronofold::read_encrypted_snapshot(&bytes[..], &CIPHER).unwrap()
+    );
+
+    let other_key = XorCipher {
+        key_id: 8,
+        key: 0x5a,
+    };
+    assert!(matches!(
+        Chronofold::<u8, char>::read_encrypted_snapshot(&bytes[..], &other_key),
+        Err(SnapshotError::UnknownKey(7))
+    ));
+
+    let mut tampered = bytes.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        Chronofold::<u8, char>::read_encrypted_snapshot(&tampered[..], &CIPHER),
+        Err(SnapshotError::DecryptionFailed)
+    ));
+}
+
+#[test]
+fn encrypted_op_log() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut log = Vec::new();
+    let mut written = Version::new();
+    cfold.session(1).extend("ab".chars());
+    assert_eq!(
+        3,
+        cfold
+            .write_encrypted_ops_since(&written, &mut log, &CIPHER)
+            .unwrap()
+    );
+    written = cfold.version().clone();
+    cfold.session(1).push_back('c');
+    assert_eq!(
+        1,
+        cfold
+            .write_encrypted_ops_since(&written, &mut log, &CIPHER)
+            .unwrap()
+    );
+    written = cfold.version().clone();
+    assert_eq!(
+        0,
+        cfold
+            .write_encrypted_ops_since(&written, &mut log, &CIPHER)
+            .unwrap()
+    );
+
+    let mut replica = Chronofold::<u8, char>::default();
+    let summary = replica.apply_encrypted_ops(&log[..], &CIPHER).unwrap();
+    assert_eq!(3, summary.applied);
+    assert_eq!(1, summary.duplicates.len());
+    assert_eq!("abc", format!("{}", replica));
+}
+
+#[test]
+fn key_rotation() {
+    let new_cipher = XorCipher {
+        key_id: 8,
+        key: 0x33,
+    };
+    let mut keys = BTreeMap::new();
+    keys.insert(CIPHER.key_id, CIPHER);
+    keys.insert(
+        new_cipher.key_id,
+        XorCipher {
+            key_id: 8,
+            key: 0x33,
+        },
+    );
+
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ab".chars());
+    let mut log = Vec::new();
+    cfold
+        .write_encrypted_ops_since(&Version::new(), &mut log, &CIPHER)
+        .unwrap();
+    let written = cfold.version().clone();
+    cfold.session(1).push_back('c');
+    cfold
+        .write_encrypted_ops_since(&written, &mut log, &new_cipher)
+        .unwrap();
+
+    // Old and new records can be read side by side.
+    let mut replica = Chronofold::<u8, char>::default();
+    replica.apply_encrypted_ops(&log[..], &keys).unwrap();
+    assert_eq!("abc", format!("{}", replica));
+    assert!(matches!(
+        replica.apply_encrypted_ops(&log[..], &new_cipher),
+        Err(SnapshotError::UnknownKey(7))
+    ));
+
+    let mut rotated = Vec::new();
+    assert_eq!(
+        2,
+        rotate_op_log_key(&log[..], &mut rotated, &keys, &new_cipher).unwrap()
+    );
+    let mut replica = Chronofold::<u8, char>::default();
+    replica
+        .apply_encrypted_ops(&rotated[..], &new_cipher)
+        .unwrap();
+    assert_eq!("abc", format!("{}", replica));
+
+    let mut snapshot = Vec::new();
+    cfold
+        .write_encrypted_snapshot(&mut snapshot, Compression::None, &CIPHER)
+        .unwrap();
+    let mut rotated = Vec::new();
+    rotate_snapshot_key(&snapshot[..], &mut rotated, &keys, &new_cipher).unwrap();
+    assert_eq!(
+        cfold,
+        Chronofold::read_encrypted_snapshot(&rotated[..], &new_cipher).unwrap()
+    );
+}
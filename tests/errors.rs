@@ -1,4 +1,11 @@
-use chronofold::{Chronofold, ChronofoldError, LocalIndex, Op, Timestamp, AuthorIndex};
+use std::error::Error;
+
+use chronofold::{
+    AuthorIndex, Chronofold, ChronofoldError, ErrorCategory, Op, OpPayload, Timestamp,
+};
+
+mod common;
+use common::t;
 
 #[test]
 fn unknown_timestamp() {
@@ -6,8 +13,34 @@ fn unknown_timestamp() {
     let unknown = Timestamp::new(AuthorIndex(1), 42);
     let op = Op::insert(Timestamp::new(AuthorIndex(1), 1), Some(unknown), '!');
     let err = cfold.apply(op.clone()).unwrap_err();
-    assert_eq!(ChronofoldError::UnknownReference(op), err);
-    assert_eq!("unknown reference <1, 42>", format!("{}", err));
+    assert_eq!(ChronofoldError::UnknownReference(op, None), err);
+    assert_eq!(
+        "unknown reference <1, 42> (no ops by 42 known)",
+        format!("{}", err)
+    );
+}
+
+#[test]
+fn unknown_reference_known_version() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold
+        .apply(Op::insert(
+            Timestamp::new(AuthorIndex(1), 2),
+            Some(Timestamp::new(AuthorIndex(0), 0)),
+            'a',
+        ))
+        .unwrap();
+    let missing = Timestamp::new(AuthorIndex(3), 2);
+    let op: Op<u8, char> = Op::delete(Timestamp::new(AuthorIndex(2), 2), missing);
+    let err = cfold.apply(op.clone()).unwrap_err();
+    assert_eq!(
+        ChronofoldError::UnknownReference(op, Some(Timestamp::new(AuthorIndex(1), 2))),
+        err
+    );
+    assert_eq!(
+        "unknown reference <3, 2> (known up to <1, 2>)",
+        format!("{}", err)
+    );
 }
 
 #[test]
@@ -25,7 +58,7 @@ fn future_timestamp() {
 
 #[test]
 fn existing_timestamp() {
-    // Applying the same op twice results in a
+    // Applying a different op with the same timestamp results in a
     // `ChronofoldError::ExistingTimestamp`:
     let mut cfold = Chronofold::<u8, char>::default();
     let op = Op::insert(
@@ -33,8 +66,134 @@ fn existing_timestamp() {
         Some(Timestamp::new(AuthorIndex(0), 0)),
         '.',
     );
-    assert_eq!(Ok(()), cfold.apply(op.clone()));
+    assert_eq!(Ok(()), cfold.apply(op));
+    let op = Op::<u8, char>::delete(
+        Timestamp::new(AuthorIndex(1), 1),
+        Timestamp::new(AuthorIndex(0), 0),
+    );
     let err = cfold.apply(op.clone()).unwrap_err();
     assert_eq!(ChronofoldError::ExistingTimestamp(op), err);
     assert_eq!("existing timestamp <1, 1>", format!("{}", err));
 }
+
+#[test]
+fn already_applied() {
+    // Applying the same op twice results in a
+    // `ChronofoldError::AlreadyApplied`:
+    let mut cfold = Chronofold::<u8, char>::default();
+    let op = Op::insert(
+        Timestamp::new(AuthorIndex(1), 1),
+        Some(Timestamp::new(AuthorIndex(0), 0)),
+        '.',
+    );
+    assert_eq!(Ok(()), cfold.apply(op.clone()));
+    let err = cfold.apply(op.clone()).unwrap_err();
+    assert_eq!(ChronofoldError::AlreadyApplied(op), err);
+    assert_eq!("already applied <1, 1>", format!("{}", err));
+}
+
+#[test]
+fn existing_timestamp_idempotent() {
+    // `apply_idempotent` skips ops that were applied before:
+    let mut cfold = Chronofold::<u8, char>::default();
+    let op = Op::insert(
+        Timestamp::new(AuthorIndex(1), 1),
+        Some(Timestamp::new(AuthorIndex(0), 0)),
+        '.',
+    );
+    assert_eq!(Ok(()), cfold.apply_idempotent(op.clone()));
+    assert_eq!(Ok(()), cfold.apply_idempotent(op));
+    assert_eq!(".", format!("{}", cfold));
+
+    // Reusing the timestamp for a different op is still an error:
+    let op = Op::insert(Timestamp::new(AuthorIndex(1), 1), None, '!');
+    assert_eq!(
+        Err(ChronofoldError::ExistingTimestamp(op.clone())),
+        cfold.apply_idempotent(op)
+    );
+}
+
+#[test]
+fn rejected() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.set_validator(|op: &Op<u8, &char>| match op.payload {
+        OpPayload::Insert(_, c) if !c.is_ascii() => Err(format!("{:?} is not ascii", c)),
+        _ => Ok(()),
+    });
+    let op = Op::insert(
+        Timestamp::new(AuthorIndex(1), 1),
+        Some(Timestamp::new(AuthorIndex(0), 0)),
+        'ä',
+    );
+    let err = cfold.apply(op.clone()).unwrap_err();
+    assert_eq!(
+        ChronofoldError::Rejected(op.id, "'ä' is not ascii".to_owned()),
+        err
+    );
+    assert_eq!("rejected <1, 1>: 'ä' is not ascii", format!("{}", err));
+    assert!(cfold.is_empty());
+
+    cfold.remove_validator();
+    assert_eq!(Ok(()), cfold.apply(op));
+}
+
+#[test]
+fn rejected_after_conversion() {
+    // Validators see the value converted to the chronofold's type.
+    let mut cfold = Chronofold::<u8, String>::default();
+    cfold.set_validator(|op: &Op<u8, &String>| match op.payload {
+        OpPayload::Insert(_, s) if s.len() > 3 => Err("too long".to_owned()),
+        _ => Ok(()),
+    });
+    let root = Some(Timestamp::new(AuthorIndex(0), 0));
+    let long: Op<u8, &str> = Op::insert(Timestamp::new(AuthorIndex(1), 1), root, "long");
+    let short: Op<u8, &str> = Op::insert(Timestamp::new(AuthorIndex(1), 2), root, "ok");
+    assert_eq!(
+        Err(ChronofoldError::Rejected(long.id, "too long".to_owned())),
+        cfold.apply(long)
+    );
+    assert_eq!(Ok(()), cfold.apply(short));
+}
+
+#[test]
+fn categories() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).push_back('a');
+    let unknown = Op::insert(t(2, 2), Some(t(1, 2)), 'b');
+    let err = cfold.apply(unknown.clone()).unwrap_err();
+    assert_eq!(ErrorCategory::Protocol, err.category());
+    let err = cfold.apply(Op::insert(t(1, 1), None, 'x')).unwrap_err();
+    assert_eq!(ErrorCategory::Integrity, err.category());
+
+    let batch = cfold.apply_all(vec![unknown]).unwrap_err();
+    assert_eq!(ErrorCategory::Protocol, batch.category());
+    let source = batch.source().unwrap();
+    assert_eq!(batch.error.to_string(), source.to_string());
+}
+
+#[test]
+fn strict_mode() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ab".chars());
+    let backwards = Op::insert(t(2, 2), Some(t(2, 1)), 'x');
+    let concurrent: Op<u8, char> = Op::delete(t(3, 3), t(3, 2));
+
+    cfold.set_strict(true);
+    let err = cfold.apply(backwards.clone()).unwrap_err();
+    assert_eq!(
+        ChronofoldError::ImplausibleReference(backwards.clone()),
+        err
+    );
+    assert_eq!(ErrorCategory::Integrity, err.category());
+    assert_eq!(
+        Err(ChronofoldError::ImplausibleReference(concurrent.clone())),
+        cfold.apply(concurrent)
+    );
+    cfold
+        .apply(Op::insert(t(3, 2), Some(t(2, 1)), 'c'))
+        .unwrap();
+    assert_eq!("abc", format!("{}", cfold));
+
+    cfold.set_strict(false);
+    cfold.apply(backwards).unwrap();
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "sync")]
+use chronofold::{Chronofold, LocalIndex, Op};
+
+#[test]
+fn export_and_import_contributions() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("hello".chars());
+    let since = cfold.version().clone();
+    cfold.session(2).extend(" world".chars());
+    cfold.session(1).push_back('!');
+    let l = cfold.log_index(&"<3, 1>".parse().unwrap()).unwrap();
+    cfold.session(2).remove(l);
+    cfold.session(2).insert_after(LocalIndex(1), 'x');
+
+    let bundle = cfold.export_contributions(2, &since);
+    assert_eq!(8, bundle.ops.len());
+    assert!(bundle.ops.iter().all(|op| op.id.author == 2));
+    // The root, "h" for the insert after it, "hello" for the world and "l"
+    // for its deletion.
+    assert_eq!(6, bundle.ancestry.len());
+
+    // A replica with the shared history gets the contributions.
+    let mut replica = Chronofold::<u8, char>::default();
+    replica
+        .apply_all(
+            cfold
+                .iter_ops(LocalIndex(1)..)
+                .filter(|op| since.contains(&op.id))
+                .map(Op::cloned),
+        )
+        .unwrap();
+    let summary = replica.import_contributions(bundle.clone());
+    assert_eq!(8, summary.applied);
+    assert_eq!(6, summary.duplicates.len());
+    assert_eq!("hxelo world", format!("{}", replica));
+
+    // A new home only has the contributions and their ancestry.
+    let mut home = bundle.into_chronofold().unwrap();
+    assert_eq!("hxelo world", format!("{}", home));
+    home.session(2).push_back('?');
+    let ops: Vec<Op<u8, char>> = home
+        .iter_newer_ops(cfold.version())
+        .map(Op::cloned)
+        .collect();
+    cfold.apply_all(ops).unwrap();
+    // '?' and '!' both follow 'd', and newer siblings come first.
+    assert_eq!("hxelo world?!", format!("{}", cfold));
+}
@@ -0,0 +1,66 @@
+use chronofold::{Chronofold, LocalIndex};
+
+#[test]
+fn log_table() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ab".chars());
+    cfold.session(2).remove(LocalIndex(1));
+    let expected = "\
+idx | val  | author | andx | next | ref
+0   | root | 0      | 0    | 1    | -
+1   | 'a'  | 1      | 1    | 3    | 0
+2   | 'b'  | 1      | 2    | -    | 1
+3   | ⌫    | 2      | 3    | 2    | 1
+";
+    assert_eq!(expected, cfold.log_table().to_string());
+}
+
+#[test]
+fn display_with_tombstones() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello world".chars());
+    cfold
+        .session(1)
+        .splice(LocalIndex(1)..LocalIndex(3), "J".chars());
+    cfold.session(1).remove(LocalIndex(11));
+    assert_eq!("Jllo worl", format!("{}", cfold));
+    assert_eq!(
+        "J[-He-]llo worl[-d-]",
+        cfold.display_with_tombstones().to_string()
+    );
+    assert_eq!(
+        "J<del>He</del>llo worl<del>d</del>",
+        cfold
+            .display_with_tombstones()
+            .with_markers("<del>", "</del>")
+            .to_string()
+    );
+}
+
+#[test]
+fn to_html() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("a<b".chars());
+    cfold.session(2).extend(" & c".chars());
+    cfold.session(2).remove(LocalIndex(3));
+    assert_eq!(
+        r#"<span data-author="1">a&lt;</span><span data-author="2"> &amp; c</span>"#,
+        cfold.to_html()
+    );
+    assert_eq!(
+        r#"<span data-author="1">a&lt;</span><del data-author="1">b</del><span data-author="2"> &amp; c</span>"#,
+        cfold.to_html_with_deletions()
+    );
+    assert_eq!("", Chronofold::<u8, char>::default().to_html());
+}
+
+#[test]
+fn render_with() {
+    let mut cfold = Chronofold::<u8, &str>::default();
+    cfold.session(1).extend(vec!["fn", "main"]);
+    cfold.session(2).push_back("()");
+    let rendered: Vec<String> = cfold
+        .render_with(|token, idx, author| format!("{}@{}:{}", token, idx, author))
+        .collect();
+    assert_eq!(vec!["fn@1:1", "main@2:1", "()@3:2"], rendered);
+}
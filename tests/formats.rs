@@ -0,0 +1,56 @@
+#![cfg(any(feature = "cbor", feature = "msgpack"))]
+use std::fmt::Debug;
+
+use chronofold::{AuthorIndex, Chronofold, FormatError, LocalIndex, Op, Timestamp, Version};
+
+#[cfg(feature = "cbor")]
+#[test]
+fn cbor_roundtrip() {
+    use chronofold::{from_cbor_slice, to_cbor_bytes};
+    let (op, version, cfold) = values();
+    roundtrip(to_cbor_bytes, from_cbor_slice, op);
+    roundtrip(to_cbor_bytes, from_cbor_slice, version);
+    roundtrip(to_cbor_bytes, from_cbor_slice, cfold);
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn msgpack_roundtrip() {
+    use chronofold::{from_msgpack_slice, to_msgpack_bytes};
+    let (op, version, cfold) = values();
+    roundtrip(to_msgpack_bytes, from_msgpack_slice, op);
+    roundtrip(to_msgpack_bytes, from_msgpack_slice, version);
+    roundtrip(to_msgpack_bytes, from_msgpack_slice, cfold);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn cbor_error() {
+    let err = chronofold::from_cbor_slice::<Version<u8>>(&[0xff]).unwrap_err();
+    assert!(format!("{}", err).starts_with("CBOR error: "));
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+fn values() -> (Op<u8, char>, Version<u8>, Chronofold<u8, char>) {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello world!".chars());
+    cfold
+        .session(2)
+        .splice(LocalIndex(6)..LocalIndex(11), "cfold".chars());
+    let op = Op::delete(
+        Timestamp::new(AuthorIndex(42), 2),
+        Timestamp::new(AuthorIndex(7), 1),
+    );
+    (op, cfold.version().clone(), cfold)
+}
+
+fn roundtrip<T>(
+    serialize: fn(&T) -> Result<Vec<u8>, FormatError>,
+    deserialize: fn(&[u8]) -> Result<T, FormatError>,
+    value: T,
+) where
+    T: PartialEq + Debug,
+{
+    let bytes = serialize(&value).unwrap();
+    assert_eq!(value, deserialize(&bytes).unwrap());
+}
@@ -0,0 +1,46 @@
+use chronofold::{Chronofold, LocalIndex, Op};
+
+#[test]
+fn history_hash_ignores_application_order() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("abc".chars());
+    let mut bob = alice.clone();
+    let mut carol = alice.clone();
+
+    let ops_alice: Vec<Op<u8, char>> = {
+        let mut session = alice.session(1);
+        session.remove(LocalIndex(2));
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    let ops_bob: Vec<Op<u8, char>> = {
+        let mut session = bob.session(2);
+        session.push_back('d');
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    for op in ops_bob.iter().chain(&ops_alice).cloned() {
+        carol.apply(op).unwrap();
+    }
+    for op in ops_alice {
+        bob.apply(op).unwrap();
+    }
+    for op in ops_bob {
+        alice.apply(op).unwrap();
+    }
+
+    assert_eq!(alice.history_hash(), bob.history_hash());
+    assert_eq!(alice.history_hash(), carol.history_hash());
+    assert_eq!(alice.content_hash(), carol.content_hash());
+}
+
+#[test]
+fn history_hash_covers_tombstones() {
+    let mut left = Chronofold::<u8, char>::default();
+    left.session(1).extend("ab".chars());
+    let mut right = Chronofold::<u8, char>::default();
+    let mut session = right.session(1);
+    session.extend("axb".chars());
+    session.remove(LocalIndex(2));
+
+    assert_eq!(left.content_hash(), right.content_hash());
+    assert_ne!(left.history_hash(), right.history_hash());
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "sync")]
+use chronofold::{
+    AuthorIndex, AuthorKeys, Chronofold, ChronofoldError, ErrorCategory, Op, SignatureScheme,
+    SignedOp, VerifyError,
+};
+use std::error::Error;
+
+/// A toy scheme for testing: The "signature" is the key followed by the op's
+/// author index.
+struct ToyScheme;
+
+impl SignatureScheme<u8, char> for ToyScheme {
+    type PublicKey = u8;
+
+    fn verify(&self, key: &u8, op: &Op<u8, char>, signature: &[u8]) -> bool {
+        signature == [*key, op.id.idx.0 as u8]
+    }
+}
+
+fn sign(key: u8, op: Op<u8, char>) -> SignedOp<u8, char> {
+    let signature = vec![key, op.id.idx.0 as u8];
+    SignedOp::new(op, signature)
+}
+
+#[test]
+fn signed_ops_are_verified() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("abc".chars());
+    let ops: Vec<Op<u8, char>> = alice.iter_ops(..).skip(1).map(Op::cloned).collect();
+
+    let mut keys = AuthorKeys::new();
+    assert!(keys.register(1, 10));
+    assert!(!keys.register(1, 11));
+    assert!(keys.rotate(1, 20, AuthorIndex(3)));
+    assert!(!keys.rotate(1, 30, AuthorIndex(3)));
+
+    let mut bob = Chronofold::<u8, char>::default();
+    bob.apply_signed(sign(10, ops[0].clone()), &keys, &ToyScheme)
+        .unwrap();
+    // The old key isn't valid anymore after the rotation.
+    assert!(matches!(
+        bob.apply_signed(sign(10, ops[2].clone()), &keys, &ToyScheme),
+        Err(ChronofoldError::Unverified(
+            _,
+            VerifyError::InvalidSignature
+        ))
+    ));
+    bob.apply_signed(sign(10, ops[1].clone()), &keys, &ToyScheme)
+        .unwrap();
+    bob.apply_signed(sign(20, ops[2].clone()), &keys, &ToyScheme)
+        .unwrap();
+    assert_eq!("abc", format!("{}", bob));
+
+    assert!(keys.revoke(&1, &10));
+    assert!(!keys.revoke(&2, &10));
+    let mut carol = Chronofold::<u8, char>::default();
+    assert!(matches!(
+        carol.apply_signed(sign(10, ops[0].clone()), &keys, &ToyScheme),
+        Err(ChronofoldError::Unverified(_, VerifyError::NoKey))
+    ));
+    assert!(matches!(
+        carol.apply_signed(sign(20, ops[0].clone()), &AuthorKeys::new(), &ToyScheme),
+        Err(ChronofoldError::Unverified(_, VerifyError::NoKey))
+    ));
+
+    // Failed verification is an integrity error, caused by the verify error:
+    let err = carol
+        .apply_signed(sign(10, ops[0].clone()), &keys, &ToyScheme)
+        .unwrap_err();
+    assert_eq!(ErrorCategory::Integrity, err.category());
+    assert_eq!("no valid key", err.source().unwrap().to_string());
+    assert_eq!(ErrorCategory::Integrity, VerifyError::NoKey.category());
+}
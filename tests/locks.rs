@@ -0,0 +1,61 @@
+#![cfg(feature = "sync")]
+use chronofold::{Chronofold, ChronofoldError, LocalIndex, LockMode, LockOp, Op, RegionLocks};
+
+#[test]
+fn locks_are_replicated() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("abc".chars());
+    let start = alice.element_id(LocalIndex(1)).unwrap();
+    let end = alice.element_id(LocalIndex(2)).unwrap();
+
+    let mut locks = RegionLocks::new();
+    let lock = locks.lock(1, start, end, LockMode::Enforced);
+    let id = match lock {
+        LockOp::Lock(id, _) => id,
+        LockOp::Unlock(_) => unreachable!(),
+    };
+    let unlock = locks.unlock(id).unwrap();
+    assert_eq!(None, locks.unlock(id));
+
+    // The unlock wins, regardless of the order.
+    let mut replica = RegionLocks::new();
+    replica.apply(unlock);
+    replica.apply(lock);
+    assert_eq!(locks, replica);
+    assert_eq!(0, replica.iter().count());
+
+    let second = locks.lock(1, start, end, LockMode::Advisory);
+    assert!(matches!(second, LockOp::Lock(id, _) if id.seq == 1));
+}
+
+#[test]
+fn enforced_locks_reject_edits() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("abcd".chars());
+    let mut locks = RegionLocks::new();
+    let start = alice.element_id(LocalIndex(2)).unwrap();
+    let end = alice.element_id(LocalIndex(3)).unwrap();
+    locks.lock(1, start, end, LockMode::Enforced);
+    assert_eq!(1, locks.covering(&alice, LocalIndex(3)).count());
+    assert_eq!(0, locks.covering(&alice, LocalIndex(4)).count());
+
+    let mut bob = alice.clone();
+    let session = bob.session(2);
+    assert!(session.can_insert_after(LocalIndex(1), &locks));
+    assert!(!session.can_insert_after(LocalIndex(2), &locks));
+    assert!(session.can_insert_after(LocalIndex(3), &locks));
+    assert!(!session.can_remove(LocalIndex(3), &locks));
+    assert!(bob.session(1).can_remove(LocalIndex(3), &locks));
+
+    let mut carol = bob.clone();
+    let inside = bob.session(2).insert_after(LocalIndex(2), 'x');
+    let after = carol.session(2).insert_after(LocalIndex(3), 'y');
+    let inside: Op<u8, char> = bob.iter_ops(inside..).map(Op::cloned).next().unwrap();
+    let after: Op<u8, char> = carol.iter_ops(after..).map(Op::cloned).next().unwrap();
+    assert!(matches!(
+        alice.apply_respecting_locks(inside, &locks),
+        Err(ChronofoldError::Rejected(..))
+    ));
+    alice.apply_respecting_locks(after, &locks).unwrap();
+    assert_eq!("abcyd", format!("{}", alice));
+}
@@ -0,0 +1,21 @@
+#![cfg(feature = "mmap")]
+use chronofold::{Chronofold, LocalIndex, MappedOpLog, Op};
+
+#[test]
+fn lazy_ranges() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello world!".chars());
+    cfold.session(2).remove(LocalIndex(6));
+    let path = std::env::temp_dir().join(format!("chronofold-{}.oplog", std::process::id()));
+    cfold.save_op_log_to(&path, 5).unwrap();
+
+    let log = MappedOpLog::<u8, char>::open(&path).unwrap();
+    let expected: Vec<Op<u8, char>> = cfold.iter_ops(..).map(Op::cloned).collect();
+    assert_eq!(expected.len(), log.len());
+    assert_eq!(expected[3..12], log.ops(3..12).unwrap()[..]);
+    assert_eq!(expected[13..], log.ops(13..log.len()).unwrap()[..]);
+    assert!(log.ops(4..4).unwrap().is_empty());
+    assert_eq!(cfold, log.load().unwrap());
+    drop(log);
+    std::fs::remove_file(&path).unwrap();
+}
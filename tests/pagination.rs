@@ -0,0 +1,62 @@
+#![cfg(feature = "sync")]
+use chronofold::{Chronofold, Op};
+
+#[test]
+fn pages_cover_newer_ops() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello".chars());
+    let since = cfold.version().clone();
+    cfold.session(1).extend(" World".chars());
+    cfold.session(2).extend("!".chars());
+
+    let expected: Vec<Op<u8, &char>> = cfold.iter_newer_ops(&since).collect();
+    let mut ops = Vec::new();
+    let mut token = None;
+    loop {
+        let page = cfold.ops_page::<&char>(&since, token, 3);
+        assert!(!page.ops.is_empty() && page.ops.len() <= 3);
+        ops.extend(page.ops);
+        match page.next {
+            Some(next) => token = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(expected, ops);
+
+    let pages: Vec<Vec<Op<u8, &char>>> = cfold.iter_ops_paginated(&since, 4).collect();
+    assert_eq!(vec![4, 3], pages.iter().map(Vec::len).collect::<Vec<_>>());
+    assert_eq!(expected, pages.concat());
+}
+
+#[test]
+fn token_survives_new_ops() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let since = cfold.version().clone();
+    cfold.session(1).extend("abc".chars());
+    let page = cfold.ops_page::<&char>(&since, None, 2);
+    let token = page.next.unwrap();
+    let first: Vec<Op<u8, char>> = page.ops.into_iter().map(Op::cloned).collect();
+
+    cfold.session(1).extend("d".chars());
+    let rest = cfold.ops_page::<&char>(&since, Some(token), 10);
+    assert_eq!(None, rest.next);
+
+    let mut replica = Chronofold::<u8, char>::default();
+    for op in first
+        .into_iter()
+        .chain(rest.ops.into_iter().map(Op::cloned))
+    {
+        replica.apply(op).unwrap();
+    }
+    assert_eq!("abcd", format!("{}", replica));
+}
+
+#[test]
+fn empty_history() {
+    let cfold = Chronofold::<u8, char>::default();
+    let version = cfold.version().clone();
+    let page = cfold.ops_page::<&char>(&version, None, 1);
+    assert!(page.ops.is_empty());
+    assert_eq!(None, page.next);
+    assert_eq!(0, cfold.iter_ops_paginated::<&char>(&version, 1).count());
+}
@@ -2,73 +2,172 @@
 //!
 //! TODO: Replace by property based tests.
 
-use chronofold::{Chronofold, Op};
+use std::sync::{Arc, Mutex};
+
+use chronofold::{ChangeEvent, Chronofold, Op};
 use rand::{rngs::ThreadRng, Rng};
 
-type AuthorId = &'static str;
-
-// #[test]
-// fn random_edits_by_two_authors() {
-//     let mut rng = rand::thread_rng();
-
-//     // Alice creates a chronofold and makes some edits before sending Bob a
-//     // copy.
-//     let mut cfold_alice = Chronofold::<AuthorId, char>::default();
-//     random_edits(&mut rng, "alice", &mut cfold_alice);
-//     let mut cfold_bob = cfold_alice.clone();
-
-//     // Alice and Bob both work on an their own copy, sending each other their
-//     // ops after they finish their edits each day. After ten days, they compare
-//     // their results.
-//     for _ in 0..10 {
-//         let ops_alice = random_edits(&mut rng, "alice", &mut cfold_alice);
-//         let ops_bob = random_edits(&mut rng, "bob", &mut cfold_bob);
-//         for op in ops_alice {
-//             cfold_bob.apply(op).unwrap();
-//         }
-//         for op in ops_bob {
-//             cfold_alice.apply(op).unwrap();
-//         }
-//     }
-//     assert_eq!(format!("{}", cfold_alice), format!("{}", cfold_bob));
-// }
-
-// fn random_edits(
-//     rng: &mut ThreadRng,
-//     author: AuthorId,
-//     cfold: &mut Chronofold<AuthorId, char>,
-// ) -> Vec<Op<AuthorId, char>> {
-//     let mut session = cfold.session(author);
-
-//     // 1 to 5 inserts of random words at random positions
-//     for _ in 0..rng.gen_range(1, 6) {
-//         let current = session.as_ref().iter().map(|(_, i)| i).collect::<Vec<_>>();
-//         if !current.is_empty() {
-//             let idx = current[rng.gen_range(0, current.len())];
-//             session.splice(idx..idx, random_word(rng).chars());
-//         } else {
-//             session.extend(random_word(rng).chars());
-//         }
-//     }
-
-//     // 1 to 2 deletions of 1 to 3 characters at random positions
-//     for _ in 0..rng.gen_range(0, 2) {
-//         let current = session.as_ref().iter().map(|(_, i)| i).collect::<Vec<_>>();
-//         if !current.is_empty() {
-//             let length = usize::min(rng.gen_range(1, 4), current.len());
-//             let start = current[rng.gen_range(0, current.len() - length + 1)];
-//             let (_, end) = session
-//                 .as_ref()
-//                 .iter_range(start..)
-//                 .take(length)
-//                 .last()
-//                 .unwrap();
-//             session.splice(start..=end, "".chars());
-//         }
-//     }
-
-//     session.iter_ops().map(Op::cloned).collect()
-// }
+type AuthorId = u8;
+
+const ALICE: AuthorId = 1;
+const BOB: AuthorId = 2;
+
+#[test]
+fn random_edits_by_two_authors() {
+    let mut rng = rand::thread_rng();
+
+    // Alice creates a chronofold and makes some edits before sending Bob a
+    // copy.
+    let mut cfold_alice = Chronofold::<AuthorId, char>::default();
+    random_edits(&mut rng, ALICE, &mut cfold_alice);
+    let mut cfold_bob = cfold_alice.clone();
+    let view_alice = mirror(&mut cfold_alice);
+    let view_bob = mirror(&mut cfold_bob);
+
+    // Alice and Bob both work on an their own copy, sending each other their
+    // ops after they finish their edits each day. After ten days, they compare
+    // their results.
+    for _ in 0..10 {
+        let ops_alice = random_edits(&mut rng, ALICE, &mut cfold_alice);
+        let ops_bob = random_edits(&mut rng, BOB, &mut cfold_bob);
+        cfold_bob.apply_batch(ops_alice);
+        for op in ops_bob {
+            cfold_alice.apply(op).unwrap();
+        }
+    }
+    assert_eq!(format!("{}", cfold_alice), format!("{}", cfold_bob));
+    assert_eq!(
+        format!("{}", cfold_alice),
+        view_alice.lock().unwrap().iter().collect::<String>()
+    );
+    assert_eq!(
+        format!("{}", cfold_bob),
+        view_bob.lock().unwrap().iter().collect::<String>()
+    );
+    assert_reversible(&cfold_alice);
+    assert_reversible(&cfold_bob);
+    assert_counted(&cfold_alice);
+    assert_counted(&cfold_bob);
+}
+
+#[test]
+fn random_edits_with_compaction() {
+    let mut rng = rand::thread_rng();
+    let mut cfold_alice = Chronofold::<AuthorId, char>::default();
+    random_edits(&mut rng, ALICE, &mut cfold_alice);
+    let mut cfold_bob = cfold_alice.clone();
+    let mut cfold_carol = cfold_alice.clone();
+
+    // Same as above, but Alice and Bob compact their chronofolds after
+    // synchronizing, while Carol keeps the full history.
+    for _ in 0..10 {
+        let ops_alice = random_edits(&mut rng, ALICE, &mut cfold_alice);
+        let ops_bob = random_edits(&mut rng, BOB, &mut cfold_bob);
+        for op in ops_alice.iter().chain(&ops_bob).cloned() {
+            cfold_carol.apply(op).unwrap();
+        }
+        for op in ops_alice {
+            cfold_bob.apply(op).unwrap();
+        }
+        for op in ops_bob {
+            cfold_alice.apply(op).unwrap();
+        }
+        let stable = cfold_alice.version().clone();
+        cfold_alice.compact(&stable);
+        cfold_bob.compact(&stable);
+        assert_eq!(format!("{}", cfold_carol), format!("{}", cfold_alice));
+        assert_eq!(format!("{}", cfold_carol), format!("{}", cfold_bob));
+        assert_reversible(&cfold_alice);
+        assert_reversible(&cfold_bob);
+        assert_counted(&cfold_alice);
+        assert_counted(&cfold_carol);
+    }
+}
+
+/// Keeps a copy of the visible elements up to date by subscribing to
+/// changes.
+fn mirror(cfold: &mut Chronofold<AuthorId, char>) -> Arc<Mutex<Vec<char>>> {
+    let view = Arc::new(Mutex::new(
+        cfold.iter_elements().copied().collect::<Vec<_>>(),
+    ));
+    let mirror = view.clone();
+    cfold.subscribe(move |event| {
+        let mut view = mirror.lock().unwrap();
+        match event {
+            ChangeEvent::Insert {
+                position, value, ..
+            } => view.insert(*position, **value),
+            ChangeEvent::Delete { position, .. } => {
+                view.remove(*position);
+            }
+        }
+    });
+    view
+}
+
+fn assert_reversible(cfold: &Chronofold<AuthorId, char>) {
+    let mut reversed: Vec<_> = cfold.iter_rev().collect();
+    reversed.reverse();
+    assert_eq!(cfold.iter().collect::<Vec<_>>(), reversed);
+}
+
+fn assert_counted(cfold: &Chronofold<AuthorId, char>) {
+    assert_eq!(cfold.iter_elements().count(), cfold.len());
+    let visible: usize = cfold.contributions().values().map(|c| c.visible).sum();
+    assert_eq!(cfold.len(), visible);
+
+    let mut rebuilt = cfold.clone();
+    rebuilt.rebuild_indices();
+    assert_eq!(cfold.len(), rebuilt.len());
+    assert_eq!(cfold.contributions(), rebuilt.contributions());
+    assert_eq!(format!("{}", cfold), format!("{}", rebuilt));
+}
+
+fn random_edits(
+    rng: &mut ThreadRng,
+    author: AuthorId,
+    cfold: &mut Chronofold<AuthorId, char>,
+) -> Vec<Op<AuthorId, char>> {
+    let mut session = cfold.session(author);
+
+    // 1 to 5 inserts of random words at random positions
+    for _ in 0..rng.gen_range(1, 6) {
+        let current = session.as_ref().iter().map(|(_, i)| i).collect::<Vec<_>>();
+        if !current.is_empty() {
+            let idx = current[rng.gen_range(0, current.len())];
+            session.splice(idx..idx, random_word(rng).chars());
+        } else {
+            session.extend(random_word(rng).chars());
+        }
+    }
+
+    // 1 to 2 deletions of 1 to 3 characters at random positions
+    for _ in 0..rng.gen_range(0, 2) {
+        let current = session.as_ref().iter().map(|(_, i)| i).collect::<Vec<_>>();
+        if !current.is_empty() {
+            let length = usize::min(rng.gen_range(1, 4), current.len());
+            let start = current[rng.gen_range(0, current.len() - length + 1)];
+            let (_, end) = session
+                .as_ref()
+                .iter_range(start..)
+                .take(length)
+                .last()
+                .unwrap();
+            session.splice(start..=end, "".chars());
+        }
+    }
+
+    // 0 to 1 replacements of a random character
+    for _ in 0..rng.gen_range(0, 2) {
+        let current = session.as_ref().iter().map(|(_, i)| i).collect::<Vec<_>>();
+        if !current.is_empty() {
+            let idx = current[rng.gen_range(0, current.len())];
+            session.replace(idx, '*');
+        }
+    }
+
+    session.iter_ops().map(Op::cloned).collect()
+}
 
 fn random_word(rng: &mut ThreadRng) -> String {
     let alphabet: Vec<_> = "abcdefghijklmnopqrstuvwxyz".chars().collect();
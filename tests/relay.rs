@@ -0,0 +1,62 @@
+#![cfg(feature = "sync")]
+use std::convert::Infallible;
+
+use chronofold::{Chronofold, LocalIndex, Op, OpaqueOp, Relay, Version};
+
+fn encode(value: char) -> Result<Vec<u8>, Infallible> {
+    Ok(value.to_string().into_bytes())
+}
+
+fn decode(bytes: &[u8]) -> Result<char, String> {
+    let s = std::str::from_utf8(bytes).map_err(|err| err.to_string())?;
+    s.chars().next().ok_or_else(|| "empty value".to_owned())
+}
+
+fn opaque_ops(cfold: &Chronofold<u8, char>, version: &Version<u8>) -> Vec<OpaqueOp<u8>> {
+    cfold
+        .iter_newer_ops::<&char>(version)
+        .map(|op| op.cloned().into_opaque(encode).unwrap())
+        .collect()
+}
+
+#[test]
+fn relay_ops() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("abc".chars());
+    let mut bob = alice.clone();
+    bob.session(2).insert_after(LocalIndex(1), 'x');
+    alice.session(1).remove(LocalIndex(2));
+
+    let mut relay = Relay::new();
+    let mut ops = opaque_ops(&alice, &Version::new());
+    let root = ops.remove(0);
+    ops.reverse();
+    assert_eq!(0, relay.receive_all(ops.clone()));
+    assert_eq!(4, relay.pending().len());
+    assert_eq!(5, relay.receive(root));
+    assert!(relay.pending().is_empty());
+    assert_eq!(0, relay.receive_all(ops));
+
+    let bob_ops = opaque_ops(&bob, alice.version());
+    assert_eq!(1, relay.receive_all(bob_ops));
+    assert_eq!(6, relay.ops().len());
+
+    // A new client gets everything, Alice only Bob's op.
+    let mut carol = Chronofold::<u8, char>::new(0);
+    let ops: Vec<Op<u8, char>> = relay
+        .ops_since(carol.version())
+        .map(|op| op.clone().decode(decode).unwrap())
+        .collect();
+    carol.apply_all(ops).unwrap();
+    assert_eq!(1, relay.ops_since(alice.version()).count());
+    for op in relay
+        .ops_since(&alice.version().clone())
+        .cloned()
+        .collect::<Vec<_>>()
+    {
+        alice.apply(op.decode(decode).unwrap()).unwrap();
+    }
+    assert_eq!("axc", format!("{}", alice));
+    assert_eq!(format!("{}", alice), format!("{}", carol));
+    assert_eq!(relay.version(), carol.version());
+}
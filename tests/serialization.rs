@@ -1,5 +1,5 @@
 #![cfg(feature = "serde")]
-use chronofold::{Chronofold, LocalIndex};
+use chronofold::{CharBatch, Chronofold, LocalIndex, Op};
 
 #[test]
 fn roundtrip() {
@@ -7,7 +7,82 @@ fn roundtrip() {
     cfold.session(1).extend("Hello world!".chars());
     let json = serde_json::to_string(&cfold).unwrap();
     eprintln!("{}", json);
-    assert_eq!(cfold, serde_json::from_str(&json).unwrap());
+    assert_eq!(
+        cfold,
+        serde_json::from_str::<Chronofold<usize, char>>(&json).unwrap()
+    );
+}
+
+#[test]
+fn roundtrip_concurrent_edits() {
+    let mut cfold = Chronofold::<usize, char>::default();
+    cfold.session(1).extend("Hello world!".chars());
+    let mut remote = cfold.clone();
+    remote
+        .session(2)
+        .splice(LocalIndex(1)..LocalIndex(6), "Bye".chars());
+    cfold.session(1).insert_after(LocalIndex(11), 's');
+    for op in remote.iter_ops(LocalIndex(13)..).map(Op::cloned) {
+        cfold.apply(op).unwrap();
+    }
+    assert_eq!("Bye worlds!", format!("{}", cfold));
+
+    let json = serde_json::to_string(&cfold).unwrap();
+    let deserialized: Chronofold<usize, char> = serde_json::from_str(&json).unwrap();
+    assert_eq!(cfold, deserialized);
+    assert_eq!("Bye worlds!", format!("{}", deserialized));
+}
+
+#[test]
+#[cfg(feature = "persistence")]
+fn ops_since_checkpoint() {
+    let mut cfold = Chronofold::<usize, char>::default();
+    cfold.session(1).extend("Hello".chars());
+    let mut persisted = cfold.clone();
+    let checkpoint = cfold.version().clone();
+    cfold.session(2).extend(" world!".chars());
+
+    let mut json = Vec::new();
+    cfold
+        .serialize_ops_since(&checkpoint, &mut serde_json::Serializer::new(&mut json))
+        .unwrap();
+    let summary = persisted
+        .apply_serialized_ops(&mut serde_json::Deserializer::from_slice(&json))
+        .unwrap();
+    assert_eq!(7, summary.applied);
+    assert_eq!(cfold, persisted);
+}
+
+#[test]
+#[cfg(feature = "persistence")]
+fn apply_serialized_ops_out_of_order() {
+    let mut cfold = Chronofold::<usize, char>::default();
+    cfold.session(1).extend("abc".chars());
+    let mut ops: Vec<Op<usize, &char>> = cfold.iter_ops(..).collect();
+    ops.reverse();
+    let json = serde_json::to_vec(&ops).unwrap();
+
+    let mut loaded = Chronofold::<usize, char>::default();
+    let summary = loaded
+        .apply_serialized_ops(&mut serde_json::Deserializer::from_slice(&json))
+        .unwrap();
+    assert_eq!(3, summary.applied);
+    assert_eq!(1, summary.duplicates.len());
+    assert!(summary.is_complete());
+    assert_eq!(cfold, loaded);
+}
+
+#[test]
+fn char_batch_is_compact() {
+    let mut cfold = Chronofold::<usize, char>::default();
+    cfold.session(1).extend("Hello world!".chars());
+    let ops: Vec<Op<usize, char>> = cfold.iter_ops(..).map(Op::cloned).collect();
+    let batch = CharBatch::from_ops(ops.clone());
+    let ops_json = serde_json::to_string(&ops).unwrap();
+    let batch_json = serde_json::to_string(&batch).unwrap();
+    assert!(batch_json.len() * 4 < ops_json.len());
+    let deserialized: CharBatch<usize> = serde_json::from_str(&batch_json).unwrap();
+    assert_eq!(Some(ops), deserialized.into_ops());
 }
 
 #[test]
@@ -26,6 +101,20 @@ fn local_edits_only() {
     assert_json_max_len(&cfold, 616);
 }
 
+#[test]
+#[cfg(feature = "documents")]
+fn doc_op() {
+    use chronofold::{AuthorIndex, DocOp, Timestamp};
+
+    let root = Timestamp::new(AuthorIndex(0), 0);
+    let doc_op = DocOp::new(
+        "notes",
+        Op::insert(Timestamp::new(AuthorIndex(1), 1), Some(root), 'a'),
+    );
+    let json = serde_json::to_string(&doc_op).unwrap();
+    assert_eq!(doc_op, serde_json::from_str(&json).unwrap());
+}
+
 fn assert_json_max_len(cfold: &Chronofold<usize, char>, max_len: usize) {
     let json = serde_json::to_string(&cfold).unwrap();
     assert!(
@@ -36,3 +125,34 @@ fn assert_json_max_len(cfold: &Chronofold<usize, char>, max_len: usize) {
         json.len()
     );
 }
+
+#[test]
+#[cfg(feature = "sync")]
+fn outbound_queue() {
+    use chronofold::OutboundQueue;
+
+    let mut cfold = Chronofold::<u8, char>::default();
+    let mut session = cfold.session(1);
+    session.extend("hi".chars());
+    let mut queue = OutboundQueue::new();
+    queue.extend(session.iter_ops().map(Op::cloned));
+    queue.ack(&"1:1".parse().unwrap());
+    assert_eq!(1, queue.len());
+
+    let json = serde_json::to_string(&queue).unwrap();
+    assert_eq!(queue, serde_json::from_str(&json).unwrap());
+}
+
+#[test]
+fn runs_beyond_log_are_rejected() {
+    let json = |next: &str| {
+        format!(
+            r#"{{"log":["Root"],"root":0,"version":[{{"idx":0,"author":0}}],"costructures":{{"next":[{}],"references":[[0,1,null]],"authors":[[0,0]],"shifts":[[0,0]]}}}}"#,
+            next
+        )
+    };
+    assert!(serde_json::from_str::<Chronofold<usize, char>>(&json("[0,1,null]")).is_ok());
+    // Expanding this run would take ages, if it wasn't rejected upfront.
+    let huge = json("[0,4611686018427387903,null]");
+    assert!(serde_json::from_str::<Chronofold<usize, char>>(&huge).is_err());
+}
@@ -0,0 +1,93 @@
+use std::cmp::Ordering;
+
+use chronofold::{Chronofold, LocalIndex, Op, SiblingOrder, Timestamp};
+
+/// Concurrent insertions by author 1 always come first.
+#[derive(Debug)]
+struct AuthorPriority;
+
+impl SiblingOrder<u8> for AuthorPriority {
+    fn cmp(&self, a: &Timestamp<u8>, b: &Timestamp<u8>) -> Ordering {
+        (a.author == 1).cmp(&(b.author == 1)).then_with(|| a.cmp(b))
+    }
+}
+
+#[test]
+fn default_order() {
+    // The newer insertion (by timestamp) comes first:
+    assert_eq!("0bbbaa", concurrent_inserts(None));
+}
+
+#[test]
+fn author_priority() {
+    assert_eq!("0aabbb", concurrent_inserts(Some(AuthorPriority)));
+}
+
+#[test]
+fn author_priority_local_insert() {
+    // Local insertions are placed after preemptive siblings, too:
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.set_sibling_order(AuthorPriority);
+    cfold.session(1).extend("0a".chars());
+    cfold.session(2).insert_after(LocalIndex(1), 'b');
+    assert_eq!("0ab", format!("{}", cfold));
+
+    let mut replica = Chronofold::<u8, char>::default();
+    replica.set_sibling_order(AuthorPriority);
+    for op in cfold.iter_ops(LocalIndex(1)..).map(Op::cloned) {
+        replica.apply(op).unwrap();
+    }
+    assert_eq!(cfold, replica);
+}
+
+#[test]
+fn iter_siblings() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("0a".chars());
+    cfold.session(2).insert_after(LocalIndex(1), 'b');
+    let siblings = |cfold: &Chronofold<u8, char>| {
+        cfold
+            .iter_siblings(LocalIndex(1))
+            .map(|(_, timestamp)| timestamp.author)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec![2, 1], siblings(&cfold));
+
+    let mut replica = Chronofold::<u8, char>::default();
+    replica.set_sibling_order(AuthorPriority);
+    for op in cfold.iter_ops(LocalIndex(1)..).map(Op::cloned) {
+        replica.apply(op).unwrap();
+    }
+    assert_eq!(vec![1, 2], siblings(&replica));
+}
+
+/// Author 1 inserts "aa" and author 2 concurrently inserts "bbb" after the
+/// same element, then both synchronize.
+fn concurrent_inserts(order: Option<AuthorPriority>) -> String {
+    let mut cfold_1 = Chronofold::<u8, char>::default();
+    if let Some(order) = order {
+        cfold_1.set_sibling_order(order);
+    }
+    cfold_1.session(1).extend("0".chars());
+    let mut cfold_2 = cfold_1.clone();
+
+    let ops_1: Vec<_> = {
+        let mut session = cfold_1.session(1);
+        session.extend("aa".chars());
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    let ops_2: Vec<_> = {
+        let mut session = cfold_2.session(2);
+        session.extend("bbb".chars());
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    for op in ops_1 {
+        cfold_2.apply(op).unwrap();
+    }
+    for op in ops_2 {
+        cfold_1.apply(op).unwrap();
+    }
+
+    assert_eq!(format!("{}", cfold_1), format!("{}", cfold_2));
+    format!("{}", cfold_1)
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "snapshot")]
+use std::error::Error;
+
+use chronofold::{Chronofold, Compression, ErrorCategory, SnapshotError};
+
+#[test]
+fn save_and_load() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello world!".chars());
+    let path = std::env::temp_dir().join(format!("chronofold-{}.snapshot", std::process::id()));
+    cfold.save_to(&path).unwrap();
+    let loaded = Chronofold::<u8, char>::load_from(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(cfold, loaded);
+}
+
+#[cfg(feature = "snapshot-lz4")]
+#[test]
+fn compressed() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".repeat(100).chars());
+    let mut uncompressed = Vec::new();
+    cfold
+        .write_snapshot(&mut uncompressed, Compression::None)
+        .unwrap();
+    let mut compressed = Vec::new();
+    cfold
+        .write_snapshot(&mut compressed, Compression::Lz4)
+        .unwrap();
+    assert!(compressed.len() < uncompressed.len());
+    assert_eq!(cfold, Chronofold::read_snapshot(&compressed[..]).unwrap());
+}
+
+#[test]
+fn corrupted() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("abc".chars());
+    let mut bytes = Vec::new();
+    cfold.write_snapshot(&mut bytes, Compression::None).unwrap();
+
+    let mut flipped = bytes.clone();
+    *flipped.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        Chronofold::<u8, char>::read_snapshot(&flipped[..]),
+        Err(SnapshotError::ChecksumMismatch)
+    ));
+
+    let mut magic = bytes.clone();
+    magic[0] = b'X';
+    assert!(matches!(
+        Chronofold::<u8, char>::read_snapshot(&magic[..]),
+        Err(SnapshotError::InvalidMagic)
+    ));
+
+    let truncated = Chronofold::<u8, char>::read_snapshot(&bytes[..bytes.len() - 1]).unwrap_err();
+    assert!(matches!(truncated, SnapshotError::Io(_)));
+    assert_eq!(ErrorCategory::Capacity, truncated.category());
+    assert!(truncated.source().unwrap().is::<std::io::Error>());
+    assert_eq!(
+        ErrorCategory::Integrity,
+        Chronofold::<u8, char>::read_snapshot(&magic[..])
+            .unwrap_err()
+            .category()
+    );
+}
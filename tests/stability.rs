@@ -0,0 +1,71 @@
+#![cfg(feature = "sync")]
+use chronofold::{Chronofold, LocalIndex, Op, StabilityMessage, StabilityTracker};
+
+#[test]
+fn agree_on_stable_version() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("abc".chars());
+    let mut carol = alice.clone();
+    alice.session(1).remove(LocalIndex(2));
+    let mut bob = alice.clone();
+    let before_delete = carol.version().clone();
+
+    let mut tracker = StabilityTracker::new(vec!["bob", "carol"]);
+    let proposal = tracker.propose(alice.version().clone());
+    let ack_bob = proposal.reply(bob.version()).unwrap();
+    tracker.receive(&"bob", ack_bob);
+    assert!(tracker.has_acked(&"bob"));
+    assert_eq!(None, tracker.stable_version());
+    assert_eq!(None, tracker.commit());
+
+    // Carol didn't see the delete yet.
+    let ack_carol = proposal.reply(carol.version()).unwrap();
+    tracker.receive(&"carol", ack_carol);
+    assert_eq!(Some(before_delete.clone()), tracker.stable_version());
+    assert_eq!(
+        Some(StabilityMessage::Commit(before_delete.clone())),
+        tracker.commit()
+    );
+    assert_eq!(None, tracker.proposal());
+
+    for cfold in [&mut alice, &mut bob, &mut carol] {
+        assert_eq!(0, cfold.compact(&before_delete).removed());
+    }
+
+    // Once Carol catches up, the delete becomes stable.
+    let ops: Vec<Op<u8, char>> = alice
+        .iter_newer_ops(carol.version())
+        .map(Op::cloned)
+        .collect();
+    carol.apply_all(ops).unwrap();
+    let proposal = tracker.propose(alice.version().clone());
+    for (peer, cfold) in [("bob", &bob), ("carol", &carol)] {
+        tracker.receive(&peer, proposal.reply(cfold.version()).unwrap());
+    }
+    let stable = match tracker.commit() {
+        Some(StabilityMessage::Commit(stable)) => stable,
+        message => panic!("unexpected {:?}", message),
+    };
+    for cfold in [&mut alice, &mut bob, &mut carol] {
+        assert_eq!(2, cfold.compact(&stable).removed());
+    }
+    assert_eq!(alice, bob);
+    assert_eq!(alice, carol);
+}
+
+#[test]
+fn new_proposal_resets_acks() {
+    let cfold = Chronofold::<u8, char>::default();
+    let mut tracker = StabilityTracker::new(vec![1, 2]);
+    let proposal = tracker.propose(cfold.version().clone());
+    tracker.receive(&1, proposal.reply(cfold.version()).unwrap());
+    tracker.receive(&3, proposal.reply(cfold.version()).unwrap());
+    assert!(tracker.has_acked(&1));
+    assert!(!tracker.has_acked(&3));
+
+    tracker.propose(cfold.version().clone());
+    assert!(!tracker.has_acked(&1));
+    tracker.remove_peer(&2);
+    tracker.receive(&1, proposal.reply(cfold.version()).unwrap());
+    assert_eq!(Some(cfold.version().clone()), tracker.stable_version());
+}
@@ -0,0 +1,110 @@
+use chronofold::{Chronofold, Contribution, LocalIndex, Op};
+
+#[test]
+fn stats() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello".chars());
+    cfold.session(2).extend(" world".chars());
+    cfold.session(2).remove(LocalIndex(1));
+    cfold.session(1).insert_after(LocalIndex(0), 'h');
+
+    let stats = cfold.stats();
+    assert_eq!(14, stats.log_len);
+    assert_eq!(12, stats.elements);
+    assert_eq!(11, stats.visible);
+    assert_eq!(1, stats.tombstones);
+    assert_eq!(0, stats.compacted);
+    assert_eq!(
+        vec![(0, 1), (1, 6), (2, 7)],
+        stats.ops_per_author.into_iter().collect::<Vec<_>>()
+    );
+    assert_eq!(3, stats.runs);
+    assert_eq!(11, format!("{}", cfold).len());
+}
+
+#[test]
+fn contributions() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("Hello".chars());
+    assert_eq!(
+        vec![(
+            1,
+            Contribution {
+                inserted: 5,
+                deleted: 0,
+                visible: 5
+            }
+        )],
+        cfold
+            .contributions()
+            .clone()
+            .into_iter()
+            .collect::<Vec<_>>()
+    );
+
+    // Kept up to date from now on.
+    cfold.session(2).extend(" world".chars());
+    cfold.session(2).remove(LocalIndex(1));
+    cfold.session(1).remove(LocalIndex(7));
+    cfold.session(1).insert_after(LocalIndex(0), 'h');
+    let expected = vec![
+        (
+            1,
+            Contribution {
+                inserted: 6,
+                deleted: 1,
+                visible: 5,
+            },
+        ),
+        (
+            2,
+            Contribution {
+                inserted: 6,
+                deleted: 1,
+                visible: 5,
+            },
+        ),
+    ];
+    assert_eq!(
+        expected,
+        cfold
+            .contributions()
+            .clone()
+            .into_iter()
+            .collect::<Vec<_>>()
+    );
+
+    let mut replica = Chronofold::<u8, char>::default();
+    replica
+        .apply_all(cfold.iter_ops(LocalIndex(1)..).map(Op::cloned))
+        .unwrap();
+    assert_eq!(cfold.contributions(), replica.contributions());
+}
+
+#[test]
+fn tombstone_ratio() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    assert_eq!(0.0, cfold.stats().tombstone_ratio());
+    cfold.session(1).extend("abcd".chars());
+    cfold.session(1).remove(LocalIndex(1));
+    assert_eq!(0.25, cfold.stats().tombstone_ratio());
+}
+
+#[test]
+fn tree_shape() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    let shape = cfold.tree_shape();
+    assert_eq!(0, shape.max_depth);
+    assert_eq!(0, shape.max_children);
+
+    cfold.session(1).extend("abc".chars());
+    cfold.session(2).insert_after(LocalIndex(0), 'x');
+    cfold.session(2).insert_after(LocalIndex(0), 'y');
+    cfold.session(2).remove(LocalIndex(2));
+    let shape = cfold.tree_shape();
+    assert_eq!(3, shape.max_depth);
+    assert_eq!(8.0 / 5.0, shape.average_depth);
+    assert_eq!(3, shape.max_children);
+    assert_eq!(5.0 / 3.0, shape.average_children);
+    assert_eq!(1, shape.branching_points);
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "persistence")]
+use std::error::Error;
+use std::io;
+use std::ops::Range;
+
+use chronofold::{AsyncPersistentChronofold, AsyncStorage, Chronofold, ErrorCategory, Op};
+use futures_executor::block_on;
+
+/// A storage that is always offline.
+struct Offline;
+
+impl AsyncStorage<u8, char> for Offline {
+    type Error = io::Error;
+
+    async fn append(&mut self, _ops: Vec<Op<u8, char>>) -> Result<(), Self::Error> {
+        Err(io::ErrorKind::NotConnected.into())
+    }
+
+    async fn load_range(&self, _range: Range<usize>) -> Result<Vec<Op<u8, char>>, Self::Error> {
+        Err(io::ErrorKind::NotConnected.into())
+    }
+}
+
+#[test]
+fn flush_and_reopen() {
+    block_on(async {
+        let storage: Vec<Op<u8, char>> = Vec::new();
+        let mut persistent = AsyncPersistentChronofold::open(Chronofold::new(0), storage)
+            .await
+            .unwrap();
+        persistent
+            .chronofold_mut()
+            .session(1)
+            .extend("Hello".chars());
+        assert_eq!(6, persistent.flush().await.unwrap());
+        assert_eq!(0, persistent.flush().await.unwrap());
+
+        persistent.chronofold_mut().session(2).extend("!".chars());
+        assert_eq!(1, persistent.flush().await.unwrap());
+        assert_eq!(
+            persistent.chronofold().version(),
+            persistent.persisted_version()
+        );
+
+        let (cfold, storage) = persistent.into_inner();
+        assert_eq!(7, storage.len());
+        let reopened = AsyncPersistentChronofold::open(Chronofold::new(0), storage)
+            .await
+            .unwrap();
+        assert_eq!("Hello!", format!("{}", reopened.chronofold()));
+        assert_eq!(cfold.version(), reopened.persisted_version());
+    });
+}
+
+#[test]
+fn open_large_storage() {
+    block_on(async {
+        let mut source = Chronofold::<u8, char>::new(0);
+        source.session(1).extend(std::iter::repeat_n('x', 3000));
+        let storage: Vec<Op<u8, char>> = source.iter_ops(..).map(Op::cloned).collect();
+        let persistent = AsyncPersistentChronofold::open(Chronofold::new(0), storage)
+            .await
+            .unwrap();
+        assert_eq!(
+            format!("{}", source),
+            format!("{}", persistent.chronofold())
+        );
+    });
+}
+
+#[test]
+fn storage_errors() {
+    block_on(async {
+        let err = AsyncPersistentChronofold::open(Chronofold::new(0), Offline)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(ErrorCategory::Capacity, err.category());
+        assert!(err.source().unwrap().is::<io::Error>());
+        assert_eq!(io::ErrorKind::NotConnected, err.into_inner().kind());
+    });
+}
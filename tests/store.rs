@@ -0,0 +1,98 @@
+#![cfg(feature = "documents")]
+use std::collections::BTreeMap;
+
+use chronofold::{Chronofold, ChronofoldError, DocOp, DocumentStore, Op};
+
+mod common;
+use common::t;
+
+type Store = DocumentStore<&'static str, u8, char>;
+
+#[test]
+fn route_ops_by_document() {
+    let mut store = Store::new();
+    let mut notes = Chronofold::new(0);
+    notes.session(1).extend("notes".chars());
+    let ops: Vec<Op<u8, char>> = notes.iter_ops(..).map(Op::cloned).collect();
+    for op in ops {
+        store.apply(&"notes", op).unwrap();
+    }
+    store.insert("todo", Chronofold::new(0));
+
+    assert_eq!(2, store.len());
+    assert_eq!("notes", format!("{}", store.get(&"notes").unwrap()));
+    assert_eq!(Some(notes.version()), store.version(&"notes"));
+    assert_eq!(
+        vec!["notes", "todo"],
+        store.iter().map(|(doc_id, _)| *doc_id).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn unknown_document() {
+    let mut store = Store::new();
+    let op = Op::insert(t(1, 1), Some(t(0, 0)), 'a');
+    assert_eq!(
+        Err(ChronofoldError::UnknownReference(op.clone(), None)),
+        store.apply(&"notes", op)
+    );
+    assert!(store.is_empty());
+}
+
+#[test]
+fn bulk_sync() {
+    let mut alice = Store::new();
+    alice.insert("notes", Chronofold::new(0));
+    alice.insert("todo", Chronofold::new(0));
+    alice
+        .get_mut(&"notes")
+        .unwrap()
+        .session(1)
+        .extend("abc".chars());
+    let mut bob = Store::new();
+    bob.insert("notes", Chronofold::new(0));
+
+    let ops: BTreeMap<_, Vec<Op<u8, char>>> = alice
+        .newer_ops::<&char>(&bob.versions())
+        .into_iter()
+        .map(|(doc_id, ops)| (doc_id, ops.into_iter().map(Op::cloned).collect()))
+        .collect();
+    assert_eq!(vec![3, 1], ops.values().map(Vec::len).collect::<Vec<_>>());
+
+    let summaries = bob.apply_bulk(ops);
+    assert!(summaries.values().all(|summary| summary.is_complete()));
+    assert_eq!(alice.versions(), bob.versions());
+    assert_eq!("abc", format!("{}", bob.get(&"notes").unwrap()));
+    assert!(alice.newer_ops::<&char>(&bob.versions()).is_empty());
+}
+
+#[test]
+fn doc_op_batch() {
+    let mut alice = Store::new();
+    alice.insert("notes", Chronofold::new(0));
+    alice.insert("todo", Chronofold::new(0));
+    alice
+        .get_mut(&"notes")
+        .unwrap()
+        .session(1)
+        .extend("ab".chars());
+    alice
+        .get_mut(&"todo")
+        .unwrap()
+        .session(1)
+        .extend("c".chars());
+
+    let mut batch: Vec<DocOp<&str, u8, char>> = alice
+        .newer_doc_ops::<&char>(&BTreeMap::new())
+        .into_iter()
+        .map(DocOp::cloned)
+        .collect();
+    assert_eq!(5, batch.len());
+    batch.reverse();
+
+    let mut bob = Store::new();
+    let summaries = bob.apply_doc_ops(batch);
+    assert!(summaries.values().all(|summary| summary.is_complete()));
+    assert_eq!(alice.versions(), bob.versions());
+    assert_eq!("c", format!("{}", bob.get(&"todo").unwrap()));
+}
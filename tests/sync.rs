@@ -0,0 +1,124 @@
+#![cfg(feature = "sync")]
+use std::time::{Duration, Instant};
+
+use chronofold::{Chronofold, Op, OutboundQueue, SyncState, Version};
+
+#[test]
+fn retransmit_lost_ops() {
+    let mut alice = Chronofold::<u8, char>::default();
+    let mut bob = alice.clone();
+    let mut state = SyncState::new();
+    state.add_peer("bob", bob.version().clone());
+    alice.session(1).extend("hi".chars());
+
+    let start = Instant::now();
+    let timeout = Duration::from_secs(5);
+    let ops: Vec<Op<u8, char>> = state
+        .unacked_ops_for(&alice, &"bob")
+        .unwrap()
+        .map(Op::cloned)
+        .collect();
+    assert_eq!(2, ops.len());
+    state.mark_sent(&"bob", alice.version().clone(), start);
+    assert!(state.peers_to_retransmit(start, timeout).is_empty());
+
+    // The message got lost, so Alice sends the ops again.
+    let later = start + timeout;
+    assert_eq!(vec![&"bob"], state.peers_to_retransmit(later, timeout));
+    let ops: Vec<Op<u8, char>> = state
+        .unacked_ops_for(&alice, &"bob")
+        .unwrap()
+        .map(Op::cloned)
+        .collect();
+    state.mark_sent(&"bob", alice.version().clone(), later);
+    bob.apply_all(ops).unwrap();
+    state.ack(&"bob", bob.version());
+
+    assert!(state
+        .peers_to_retransmit(later + timeout, timeout)
+        .is_empty());
+    assert_eq!(
+        0,
+        state
+            .unacked_ops_for::<_, &char>(&alice, &"bob")
+            .unwrap()
+            .count()
+    );
+    assert_eq!(Some(alice.version()), state.acked(&"bob"));
+}
+
+#[test]
+fn partial_ack() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("abc".chars());
+    let mut state = SyncState::new();
+    state.add_peer(2, Version::new());
+    state.mark_sent(&2, alice.version().clone(), Instant::now());
+
+    let mut bob = Chronofold::<u8, char>::default();
+    let first: Vec<Op<u8, char>> = alice.iter_ops(..).take(2).map(Op::cloned).collect();
+    bob.apply_iter(first);
+    state.ack(&2, bob.version());
+    assert_eq!(
+        2,
+        state
+            .unacked_ops_for::<_, &char>(&alice, &2)
+            .unwrap()
+            .count()
+    );
+    assert_eq!(
+        vec![&2],
+        state.peers_to_retransmit(Instant::now(), Duration::ZERO)
+    );
+    assert!(state.unacked_ops_for::<_, &char>(&alice, &3).is_none());
+}
+
+#[test]
+fn outbound_queue() {
+    let mut alice = Chronofold::<u8, char>::default();
+    let mut server = alice.clone();
+    let mut queue = OutboundQueue::new();
+
+    // Alice edits offline.
+    let mut session = alice.session(1);
+    session.extend("hi".chars());
+    queue.extend(session.iter_ops().map(Op::cloned));
+    assert_eq!(2, queue.len());
+
+    // The server applies the first op only before the connection drops.
+    server.apply(queue.pending()[0].clone()).unwrap();
+    assert_eq!(1, queue.ack(server.version()));
+    assert_eq!(1, queue.len());
+
+    for op in queue.pending().to_vec() {
+        server.apply(op).unwrap();
+    }
+    assert_eq!(1, queue.ack(server.version()));
+    assert!(queue.is_empty());
+    assert_eq!(alice, server);
+
+    // Acknowledged ops aren't queued again.
+    queue.extend(alice.iter_ops(..).map(Op::cloned));
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn resync() {
+    let mut alice = Chronofold::<u8, char>::default();
+    alice.session(1).extend("hi".chars());
+    let mut bob = alice.clone();
+    alice.session(1).extend("!".chars());
+    bob.session(2).push_front('>');
+
+    // Alice reconnects and sends her ops and version to Bob, who replies
+    // with his.
+    let (ops, version) = alice.resync(bob.version());
+    assert_eq!(1, ops.len());
+    bob.apply_all(ops).unwrap();
+    let (ops, _) = bob.resync(&version);
+    assert_eq!(1, ops.len());
+    alice.apply_all(ops).unwrap();
+
+    assert_eq!(format!("{}", alice), format!("{}", bob));
+    assert_eq!(alice, ">hi!");
+}
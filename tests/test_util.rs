@@ -0,0 +1,51 @@
+#![cfg(feature = "test-util")]
+use chronofold::test_util::*;
+use chronofold::{Chronofold, LocalIndex};
+
+#[test]
+fn concurrent_typing_converges() {
+    let mut rng = rand::thread_rng();
+    let mut base = Chronofold::<u8, char>::default();
+    paste(&mut base, 1, LocalIndex(0), "Hello!");
+
+    let streams = concurrent_edits_at(&base, LocalIndex(5), &[(2, " world"), (3, " there")]);
+    let mut left = base.clone();
+    let mut right = base.clone();
+    for op in random_interleaving(&mut rng, streams.clone()) {
+        left.apply(op).unwrap();
+    }
+    for op in streams.into_iter().rev().flatten() {
+        right.apply(op).unwrap();
+    }
+    assert_eq!(format!("{}", left), format!("{}", right));
+    assert_eq!("Hello there world!", format!("{}", left));
+}
+
+#[test]
+fn random_edits_converge() {
+    let mut rng = rand::thread_rng();
+    let mut left = Chronofold::<u8, char>::default();
+    let mut right = left.clone();
+    for _ in 0..10 {
+        let ops_left = random_edits(&mut rng, &mut left, 1);
+        let ops_right = random_edits(&mut rng, &mut right, 2);
+        for op in ops_left {
+            right.apply(op).unwrap();
+        }
+        for op in ops_right {
+            left.apply(op).unwrap();
+        }
+    }
+    assert_eq!(format!("{}", left), format!("{}", right));
+}
+
+#[test]
+fn random_interleaving_keeps_stream_order() {
+    let mut rng = rand::thread_rng();
+    let interleaved = random_interleaving(&mut rng, vec![vec![1, 2, 3], vec![10, 20]]);
+    assert_eq!(5, interleaved.len());
+    let ones: Vec<_> = interleaved.iter().filter(|&&x| x < 10).collect();
+    let tens: Vec<_> = interleaved.iter().filter(|&&x| x >= 10).collect();
+    assert_eq!(vec![&1, &2, &3], ones);
+    assert_eq!(vec![&10, &20], tens);
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "test-vectors")]
+use chronofold::test_vectors::*;
+
+#[test]
+fn test_vectors_verify() {
+    let vectors = test_vectors();
+    assert_eq!(vectors, test_vectors());
+    for vector in &vectors {
+        vector.verify().unwrap();
+    }
+    let texts: Vec<&str> = vectors.iter().map(|v| v.text.as_str()).collect();
+    assert_eq!("Hello world", texts[0]);
+    assert_eq!(6, texts[1].len());
+    assert_eq!("aXc", texts[2]);
+    assert_eq!("ac", texts[3]);
+}
+
+#[test]
+fn tampered_vectors_fail() {
+    let mut vector = test_vectors().remove(0);
+    vector.text.push('!');
+    assert!(vector.verify().is_err());
+}
+
+#[test]
+fn json_round_trip() {
+    let vectors = test_vectors();
+    let json = serde_json::to_string(&vectors).unwrap();
+    assert_eq!(
+        vectors,
+        serde_json::from_str::<Vec<TestVector>>(&json).unwrap()
+    );
+}
@@ -0,0 +1,55 @@
+use chronofold::{Chronofold, LocalIndex, Op, Text};
+
+#[test]
+fn edit() {
+    let mut text = Text::new(Chronofold::<u8, char>::default(), 1);
+    assert!(text.is_empty());
+    assert_eq!(None, text.insert_str(0, ""));
+    text.insert_str(0, "wörld");
+    text.insert_str(0, "Hello ");
+    text.insert_str(11, "!");
+    assert_eq!("Hello wörld!", text.as_string());
+    assert_eq!(12, text.len());
+    assert_eq!(Some('ö'), text.char_at(7));
+    assert_eq!(None, text.char_at(12));
+
+    text.delete_range(5..11);
+    assert_eq!("Hello!", text.to_string());
+    text.delete_range(..=0);
+    assert_eq!("ello!", text.as_string());
+
+    let idx = text.position_to_index(1).unwrap();
+    assert_eq!(Some(1), text.index_to_position(idx));
+}
+
+#[test]
+fn concurrent_edits() {
+    let mut alice = Text::new(Chronofold::<u8, char>::default(), 1);
+    alice.insert_str(0, "ac");
+    let mut bob = Text::new(alice.as_ref().clone(), 2);
+
+    alice.insert_str(1, "b");
+    bob.insert_str(2, "d");
+    let ops: Vec<Op<u8, char>> = bob
+        .as_ref()
+        .iter_ops(LocalIndex(1)..)
+        .map(Op::cloned)
+        .collect();
+    alice.as_mut().apply_iter(ops);
+    assert_eq!("abcd", alice.as_string());
+    assert_eq!(1, alice.author());
+}
+
+#[test]
+#[should_panic]
+fn insert_out_of_bounds() {
+    Text::new(Chronofold::<u8, char>::default(), 1).insert_str(1, "a");
+}
+
+#[test]
+#[should_panic]
+fn delete_out_of_bounds() {
+    let mut text = Text::new(Chronofold::<u8, char>::default(), 1);
+    text.insert_str(0, "ab");
+    text.delete_range(1..3);
+}
@@ -0,0 +1,98 @@
+use chronofold::{Chronofold, LocalIndex};
+
+#[test]
+fn parent_and_children() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ac".chars());
+    cfold.session(1).insert_after(LocalIndex(1), 'b');
+    cfold.session(1).remove(LocalIndex(2));
+    assert_eq!(cfold, "ab");
+
+    assert_eq!(None, cfold.parent(LocalIndex(0)));
+    assert_eq!(Some(LocalIndex(1)), cfold.parent(LocalIndex(3)));
+    assert_eq!(Some(LocalIndex(2)), cfold.parent(LocalIndex(4)));
+    assert_eq!(None, cfold.parent(LocalIndex(5)));
+
+    // Newer siblings come first.
+    assert_eq!(
+        vec![LocalIndex(3), LocalIndex(2)],
+        cfold.children(LocalIndex(1)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![LocalIndex(4)],
+        cfold.children(LocalIndex(2)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![LocalIndex(1)],
+        cfold.children(LocalIndex(0)).collect::<Vec<_>>()
+    );
+    assert_eq!(0, cfold.children(LocalIndex(3)).count());
+    assert_eq!(0, cfold.children(LocalIndex(5)).count());
+}
+
+#[test]
+fn is_ancestor() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ac".chars());
+    cfold.session(1).insert_after(LocalIndex(1), 'b');
+
+    assert!(cfold.is_ancestor(LocalIndex(0), LocalIndex(2)));
+    assert!(cfold.is_ancestor(LocalIndex(1), LocalIndex(3)));
+    assert!(!cfold.is_ancestor(LocalIndex(2), LocalIndex(3)));
+    assert!(!cfold.is_ancestor(LocalIndex(3), LocalIndex(3)));
+    assert!(!cfold.is_ancestor(LocalIndex(2), LocalIndex(1)));
+    assert!(!cfold.is_ancestor(LocalIndex(0), LocalIndex(4)));
+}
+
+#[test]
+fn lca() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ace".chars());
+    cfold.session(1).insert_after(LocalIndex(1), 'b');
+    cfold.session(1).insert_after(LocalIndex(2), 'd');
+    assert_eq!(cfold, "abcde");
+
+    assert_eq!(Some(LocalIndex(1)), cfold.lca(LocalIndex(4), LocalIndex(5)));
+    assert_eq!(Some(LocalIndex(1)), cfold.lca(LocalIndex(3), LocalIndex(4)));
+    assert_eq!(Some(LocalIndex(2)), cfold.lca(LocalIndex(2), LocalIndex(5)));
+    assert_eq!(Some(LocalIndex(3)), cfold.lca(LocalIndex(3), LocalIndex(3)));
+    assert_eq!(None, cfold.lca(LocalIndex(3), LocalIndex(6)));
+}
+
+#[test]
+fn subtree_len_and_depth() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ace".chars());
+    cfold.session(1).insert_after(LocalIndex(1), 'b');
+    cfold.session(1).remove(LocalIndex(2));
+
+    assert_eq!(6, cfold.subtree_len(LocalIndex(0)));
+    assert_eq!(5, cfold.subtree_len(LocalIndex(1)));
+    assert_eq!(3, cfold.subtree_len(LocalIndex(2)));
+    assert_eq!(1, cfold.subtree_len(LocalIndex(4)));
+    assert_eq!(0, cfold.subtree_len(LocalIndex(6)));
+
+    assert_eq!(Some(0), cfold.depth(LocalIndex(0)));
+    assert_eq!(Some(3), cfold.depth(LocalIndex(3)));
+    assert_eq!(Some(2), cfold.depth(LocalIndex(4)));
+    assert_eq!(Some(3), cfold.depth(LocalIndex(5)));
+    assert_eq!(None, cfold.depth(LocalIndex(6)));
+}
+
+#[test]
+fn path_to() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ac".chars());
+    cfold.session(1).insert_after(LocalIndex(1), 'b');
+
+    assert_eq!(Some(vec![LocalIndex(0)]), cfold.path_to(LocalIndex(0)));
+    assert_eq!(
+        Some(vec![LocalIndex(0), LocalIndex(1), LocalIndex(2)]),
+        cfold.path_to(LocalIndex(2))
+    );
+    assert_eq!(
+        Some(vec![LocalIndex(0), LocalIndex(1), LocalIndex(3)]),
+        cfold.path_to(LocalIndex(3))
+    );
+    assert_eq!(None, cfold.path_to(LocalIndex(4)));
+}
@@ -1,4 +1,7 @@
-use chronofold::{Chronofold, LocalIndex, Op, Timestamp, Version, AuthorIndex};
+use chronofold::{AuthorIndex, Chronofold, LocalIndex, Op, Timestamp, Version};
+
+mod common;
+use common::t;
 
 #[test]
 fn partial_order() {
@@ -10,9 +13,55 @@ fn partial_order() {
     assert!(v(vec![t(0, 1)]) < v(vec![t(1, 1)]));
     assert!(v(vec![t(1, 1)]) > v(vec![t(0, 1)]));
 
-    assert!(!(v(vec![t(0, 1)]) == v(vec![t(0, 2)])));
-    assert!(!(v(vec![t(0, 1)]) < v(vec![t(0, 2)])));
-    assert!(!(v(vec![t(0, 1)]) > v(vec![t(0, 2)])));
+    assert!(v(vec![t(0, 1)]) != v(vec![t(0, 2)]));
+    assert_eq!(None, v(vec![t(0, 1)]).partial_cmp(&v(vec![t(0, 2)])));
+}
+
+#[test]
+fn display_and_parse() {
+    let version = v(vec![t(42, 1), t(17, 2)]);
+    assert_eq!("1:42,2:17", version.to_string());
+    assert_eq!(Ok(version), "2:17, 1:42".parse());
+    assert_eq!(Ok(v(vec![])), "".parse::<Version<u8>>());
+    assert!("1:42,2".parse::<Version<u8>>().is_err());
+    assert!("x:1".parse::<Version<u8>>().is_err());
+}
+
+#[test]
+fn display_and_parse_escaped_authors() {
+    let mut version = Version::<String>::new();
+    version.inc(&Timestamp::new(AuthorIndex(3), "a,b".to_owned()));
+    version.inc(&Timestamp::new(AuthorIndex(4), "c:\\".to_owned()));
+    assert_eq!("a\\,b:3,c\\:\\\\:4", version.to_string());
+    assert_eq!(Ok(version.clone()), version.to_string().parse());
+    assert!("a,b:3".parse::<Version<String>>().is_err());
+    assert!("a\\:3".parse::<Version<String>>().is_err());
+}
+
+#[test]
+fn parse_timestamp() {
+    assert_eq!(t(42, 1), t(42, 1).to_string().parse().unwrap());
+    assert_eq!(Ok(t(3, 2)), " <3,2> ".parse());
+    assert!("<3>".parse::<Timestamp<u8>>().is_err());
+    assert!("3, 2".parse::<Timestamp<u8>>().is_err());
+    assert!("<3, 256>".parse::<Timestamp<u8>>().is_err());
+}
+
+#[test]
+fn stable_version() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ab".chars());
+    cfold
+        .apply(Op::insert(t(3, 2), Some(t(2, 1)), 'c'))
+        .unwrap();
+    assert_eq!(*cfold.version(), cfold.stable_version(&[]));
+    assert_eq!(
+        v(vec![t(0, 0), t(1, 1)]),
+        cfold.stable_version(&[
+            v(vec![t(0, 0), t(2, 1), t(5, 3)]),
+            v(vec![t(0, 0), t(1, 1), t(3, 2)]),
+        ])
+    );
 }
 
 #[test]
@@ -46,8 +95,31 @@ fn iter_newer_ops() {
     );
 }
 
-fn t(log_index: usize, author: u8) -> Timestamp<u8> {
-    Timestamp::new(AuthorIndex(log_index), author)
+#[test]
+fn many_authors() {
+    let mut version = Version::<u8>::new();
+    for author in (0..=255u8).rev() {
+        version.inc(&t(author as usize, author));
+    }
+    version.inc(&t(7, 200));
+    version.inc(&t(300, 200));
+    assert_eq!(256, version.len());
+    assert_eq!(Some(AuthorIndex(300)), version.get(&200));
+    assert!(version.contains(&t(255, 255)));
+    assert!(!version.contains(&t(256, 255)));
+
+    let authors: Vec<u8> = version.iter().map(|t| t.author).collect();
+    assert_eq!((0..=255).collect::<Vec<u8>>(), authors);
+    let parsed: Version<u8> = version.to_string().parse().unwrap();
+    assert_eq!(version, parsed);
+
+    let few = v((0..10).map(|author| t(1000, author)).collect());
+    assert_eq!(None, version.partial_cmp(&few));
+    let stable: Vec<_> = Chronofold::<u8, char>::default()
+        .stable_version(&[version.clone(), few.clone()])
+        .iter()
+        .collect();
+    assert_eq!(vec![t(0, 0)], stable);
 }
 
 fn v(timestamps: Vec<Timestamp<u8>>) -> Version<u8> {
@@ -57,3 +129,77 @@ fn v(timestamps: Vec<Timestamp<u8>>) -> Version<u8> {
     }
     version
 }
+
+#[test]
+fn transform_index() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("hello world".chars());
+    let mut remote = cfold.clone();
+    let since = cfold.version().clone();
+
+    // Meanwhile, a remote author deletes "hello " and appends "!".
+    let ops: Vec<_> = {
+        let mut session = remote.session(2);
+        session.splice(LocalIndex(1)..LocalIndex(7), "".chars());
+        session.push_back('!');
+        session.iter_ops().map(Op::cloned).collect()
+    };
+    for op in ops {
+        cfold.apply(op).unwrap();
+    }
+    assert_eq!("world!", format!("{}", cfold));
+
+    assert_eq!(0, cfold.transform_index(0, &since)); // 'h' was deleted
+    assert_eq!(0, cfold.transform_index(6, &since)); // 'w'
+    assert_eq!(4, cfold.transform_index(10, &since)); // 'd'
+    assert_eq!(6, cfold.transform_index(11, &since)); // end
+}
+
+#[test]
+fn version_observer() {
+    use std::sync::{Arc, Mutex};
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut cfold = Chronofold::<u8, char>::default();
+    let log = seen.clone();
+    cfold.set_version_observer(move |version| log.lock().unwrap().push(version.to_string()));
+    cfold.session(1).extend("ab".chars());
+    cfold.session(1).push_back('c');
+    assert_eq!(vec!["0:0,1:2", "0:0,1:3"], *seen.lock().unwrap());
+
+    let mut source = Chronofold::<u8, char>::default();
+    source.session(2).extend("xyz".chars());
+    let ops: Vec<Op<u8, char>> = source.iter_ops(LocalIndex(1)..).map(Op::cloned).collect();
+    cfold.begin_remote_batch().apply_iter(ops);
+    assert_eq!(3, seen.lock().unwrap().len());
+    assert_eq!("0:0,1:3,2:3", seen.lock().unwrap()[2]);
+
+    cfold.remove_version_observer();
+    cfold.session(1).push_back('!');
+    assert_eq!(3, seen.lock().unwrap().len());
+}
+
+#[test]
+fn iter_ops_excluding() {
+    let mut cfold = Chronofold::<u8, char>::default();
+    cfold.session(1).extend("ab".chars());
+    let version = cfold.version().clone();
+    cfold.session(2).push_back('c');
+    cfold.session(1).push_back('d');
+    cfold.session(3).push_back('e');
+
+    let ops: Vec<Op<u8, &char>> = cfold.iter_ops_excluding(&version, &[2]).collect();
+    assert_eq!(
+        vec![
+            Timestamp::new(AuthorIndex(4), 1),
+            Timestamp::new(AuthorIndex(5), 3)
+        ],
+        ops.iter().map(|op| op.id).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        0,
+        cfold
+            .iter_ops_excluding::<&char>(&version, &[1, 2, 3])
+            .count()
+    );
+}